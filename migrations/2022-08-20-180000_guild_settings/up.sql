@@ -0,0 +1,6 @@
+-- Your SQL goes here
+create table guild_settings
+(
+    guild_id     bigint not null primary key,
+    confirm_veto bool   not null default false
+);
\ No newline at end of file
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+create table teams
+(
+    role_id                 bigint       not null primary key,
+    name                    varchar(100) not null,
+    captain_discord_id      bigint,
+    vice_captain_discord_id bigint
+);
\ No newline at end of file
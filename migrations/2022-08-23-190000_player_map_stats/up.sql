@@ -0,0 +1,16 @@
+-- Your SQL goes here
+create table player_map_stats
+(
+    steam_id       varchar not null,
+    series_map_id  integer not null references series_map (id) on delete cascade,
+    team_role_id   bigint  not null,
+    kills          integer not null default 0,
+    deaths         integer not null default 0,
+    assists        integer not null default 0,
+    headshot_kills integer not null default 0,
+    damage         integer not null default 0,
+    rounds_played  integer not null default 0,
+    primary key (steam_id, series_map_id)
+);
+
+create index player_map_stats_team_role_id on player_map_stats (team_role_id);
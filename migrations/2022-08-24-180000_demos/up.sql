@@ -0,0 +1,8 @@
+-- Your SQL goes here
+create table demos
+(
+    series_map_id integer   not null primary key references series_map (id) on delete cascade,
+    location      varchar   not null,
+    size_bytes    bigint    not null,
+    archived_at   timestamp not null
+);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+alter table match_servers
+    drop column location,
+    drop column capacity,
+    drop column enabled;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+alter table match_servers
+    add column location varchar,
+    add column capacity integer,
+    add column enabled  boolean not null default true;
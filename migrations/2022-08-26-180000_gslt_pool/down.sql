@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+alter table guild_settings
+    drop column admin_channel_id,
+    drop column gslt_warn_threshold;
+
+alter table gslt_tokens
+    drop column match_id,
+    drop column assigned_at;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+alter table gslt_tokens
+    add column match_id    integer references matches (id) on delete set null,
+    add column assigned_at timestamp;
+
+alter table guild_settings
+    add column admin_channel_id     bigint,
+    add column gslt_warn_threshold integer not null default 2;
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+-- encrypted tokens are not decrypted, they have to be added again
+drop table provider_credentials;
+
+alter table gslt_tokens
+    drop column id,
+    drop column token_hash,
+    add primary key (token);
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+-- tokens are encrypted by the bot on startup, rows are looked up by the token's hash instead
+alter table gslt_tokens
+    drop constraint gslt_tokens_pkey,
+    add column id         serial primary key,
+    add column token_hash varchar;
+update gslt_tokens
+set token_hash = encode(sha256(convert_to(token, 'UTF8')), 'hex');
+alter table gslt_tokens
+    alter column token_hash set not null,
+    add constraint gslt_tokens_token_hash_key unique (token_hash);
+
+create table provider_credentials
+(
+    guild_id         bigint  not null primary key,
+    dathost_user     varchar not null,
+    dathost_password varchar not null
+);
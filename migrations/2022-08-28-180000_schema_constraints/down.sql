@@ -0,0 +1,28 @@
+-- This file should undo anything in `up.sql`
+drop index gslt_tokens_match_id;
+drop index matches_team_two_role_id;
+drop index matches_team_one_role_id;
+drop index series_map_match_id;
+drop index match_setup_step_match_id;
+
+alter table matches
+    drop constraint matches_series_type_check,
+    drop constraint matches_match_state_check;
+
+alter table series_map
+    drop constraint series_map_match_id_fkey,
+    add constraint series_map_match_id_fkey foreign key (match_id) references matches (id);
+
+alter table match_setup_step
+    drop constraint match_setup_step_step_type_check,
+    drop constraint match_setup_step_match_id_fkey,
+    add constraint match_setup_step_match_id_fkey foreign key (match_id) references matches (id);
+
+alter table users
+    drop constraint users_discord_id_key;
+
+insert into users
+select *
+from users_duplicates;
+
+drop table users_duplicates;
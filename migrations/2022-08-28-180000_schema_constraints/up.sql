@@ -0,0 +1,33 @@
+-- Your SQL goes here
+-- only the newest user of a discord id is kept, the others are moved to users_duplicates
+create table users_duplicates as
+select a.*
+from users a
+where exists(select 1 from users b where b.discord_id = a.discord_id and b.id > a.id);
+
+delete
+from users a
+    using users_duplicates d
+where a.id = d.id;
+
+alter table users
+    add constraint users_discord_id_key unique (discord_id);
+
+alter table match_setup_step
+    drop constraint match_setup_step_match_id_fkey,
+    add constraint match_setup_step_match_id_fkey foreign key (match_id) references matches (id) on delete cascade,
+    add constraint match_setup_step_step_type_check check (step_type in ('veto', 'pick'));
+
+alter table series_map
+    drop constraint series_map_match_id_fkey,
+    add constraint series_map_match_id_fkey foreign key (match_id) references matches (id) on delete cascade;
+
+alter table matches
+    add constraint matches_match_state_check check (match_state in ('entered', 'scheduled', 'completed')),
+    add constraint matches_series_type_check check (series_type in ('bo1', 'bo3', 'bo5'));
+
+create index match_setup_step_match_id on match_setup_step (match_id);
+create index series_map_match_id on series_map (match_id);
+create index matches_team_one_role_id on matches (team_one_role_id);
+create index matches_team_two_role_id on matches (team_two_role_id);
+create index gslt_tokens_match_id on gslt_tokens (match_id);
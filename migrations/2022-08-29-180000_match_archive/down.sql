@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+update matches
+set match_state = coalesce(archived_from_state, 'entered')
+where match_state = 'archived';
+
+alter table matches
+    drop constraint matches_match_state_check,
+    add constraint matches_match_state_check check (match_state in ('entered', 'scheduled', 'completed')),
+    drop column archived_from_state,
+    drop column archive_reason,
+    drop column archived_by,
+    drop column archived_at;
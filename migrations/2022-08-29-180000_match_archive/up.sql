@@ -0,0 +1,9 @@
+-- Your SQL goes here
+alter table matches
+    add column archived_at         timestamp,
+    add column archived_by         bigint,
+    add column archive_reason      varchar(500),
+    add column archived_from_state varchar(50),
+    drop constraint matches_match_state_check,
+    add constraint matches_match_state_check
+        check (match_state in ('entered', 'scheduled', 'completed', 'archived'));
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+create table match_edits
+(
+    id        serial primary key,
+    match_id  integer     not null references matches (id) on delete cascade,
+    edited_by bigint,
+    field     varchar(50) not null,
+    old_value varchar,
+    new_value varchar,
+    edited_at timestamp   not null
+);
+
+create index match_edits_match_id on match_edits (match_id);
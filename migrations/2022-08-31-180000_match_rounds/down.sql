@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+drop index matches_round;
+
+alter table matches
+    drop column deadline,
+    drop column round;
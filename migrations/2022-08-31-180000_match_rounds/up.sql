@@ -0,0 +1,6 @@
+-- Your SQL goes here
+alter table matches
+    add column round    integer check (round > 0),
+    add column deadline timestamp;
+
+create index matches_round on matches (round);
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+drop table bracket_matches;
+drop table bracket_teams;
+drop table brackets;
+
+alter table matches
+    drop column team_two_score,
+    drop column team_one_score,
+    drop column winner_role_id;
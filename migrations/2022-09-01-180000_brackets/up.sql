@@ -0,0 +1,46 @@
+-- Your SQL goes here
+alter table matches
+    add column winner_role_id bigint,
+    add column team_one_score integer check (team_one_score >= 0),
+    add column team_two_score integer check (team_two_score >= 0);
+
+create table brackets
+(
+    id          serial primary key,
+    name        varchar(100) not null unique,
+    format      varchar(50)  not null check (format in ('single', 'double', 'gsl')),
+    series_type varchar(50)  not null check (series_type in ('bo1', 'bo3', 'bo5')),
+    created_at  timestamp    not null
+);
+
+create table bracket_teams
+(
+    bracket_id integer      not null references brackets (id) on delete cascade,
+    role_id    bigint       not null,
+    name       varchar(100) not null,
+    seed       integer      not null check (seed > 0),
+    primary key (bracket_id, role_id),
+    unique (bracket_id, seed)
+);
+
+-- winner_to & loser_to are the seq of the bracket match the team moves on to, in the slot
+create table bracket_matches
+(
+    bracket_id       integer     not null references brackets (id) on delete cascade,
+    seq              integer     not null,
+    section          varchar(50) not null check (section in ('upper', 'lower', 'final', 'group')),
+    group_number     integer,
+    round            integer     not null,
+    position         integer     not null,
+    team_one_role_id bigint,
+    team_two_role_id bigint,
+    team_one_bye     boolean     not null default false,
+    team_two_bye     boolean     not null default false,
+    winner_to        integer,
+    winner_slot      integer check (winner_slot in (0, 1)),
+    loser_to         integer,
+    loser_slot       integer check (loser_slot in (0, 1)),
+    winner_role_id   bigint,
+    match_id         integer unique references matches (id) on delete set null,
+    primary key (bracket_id, seq)
+);
@@ -0,0 +1,14 @@
+-- This file should undo anything in `up.sql`
+alter table brackets
+    drop column season_id;
+
+drop index matches_season;
+
+alter table matches
+    drop column division_id,
+    drop column season_id;
+
+drop table season_maps;
+drop table season_teams;
+drop table divisions;
+drop table seasons;
@@ -0,0 +1,47 @@
+-- Your SQL goes here
+create table seasons
+(
+    id         serial primary key,
+    name       varchar(100) not null unique,
+    active     boolean      not null default false,
+    created_at timestamp    not null
+);
+
+-- at most one season is active
+create unique index seasons_active on seasons (active) where active;
+
+create table divisions
+(
+    id        serial primary key,
+    season_id integer      not null references seasons (id) on delete cascade,
+    name      varchar(100) not null,
+    unique (season_id, name)
+);
+
+-- teams playing in a season, in at most one of its divisions
+create table season_teams
+(
+    season_id   integer not null references seasons (id) on delete cascade,
+    role_id     bigint  not null references teams (role_id) on delete cascade,
+    division_id integer references divisions (id) on delete set null,
+    primary key (season_id, role_id)
+);
+
+create index season_teams_division on season_teams (division_id);
+
+-- seasons without maps of their own use every map
+create table season_maps
+(
+    season_id integer      not null references seasons (id) on delete cascade,
+    map       varchar(100) not null references maps (name) on delete cascade,
+    primary key (season_id, map)
+);
+
+alter table matches
+    add column season_id   integer references seasons (id) on delete set null,
+    add column division_id integer references divisions (id) on delete set null;
+
+create index matches_season on matches (season_id, division_id);
+
+alter table brackets
+    add column season_id integer references seasons (id) on delete set null;
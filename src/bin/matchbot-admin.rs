@@ -0,0 +1,385 @@
+//! Operator tool for the bot's database, runs without connecting to Discord
+
+use clap::{Parser, Subcommand};
+use csgo_matchbot::config;
+use csgo_matchbot::crypto::Keyring;
+use csgo_matchbot::models::{
+    parse_schedule, Map, Match, MatchChanges, MatchServer, MatchState, SeriesType,
+};
+use csgo_matchbot::{
+    add_map, add_token, delete_map, delete_match_server, delete_token, edit_match, find_match,
+    get_all_matches, get_map_pool, get_match_edits, get_match_servers, get_tokens,
+    release_match_tokens, reset_match_setup, run_migrations, update_match_server,
+    update_match_state,
+};
+use diesel::{Connection, PgConnection};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::File;
+use std::path::Path;
+use std::process::exit;
+
+#[derive(Parser)]
+#[clap(name = "matchbot-admin", about = "Maintains the matchbot database")]
+struct Cli {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Runs pending database migrations
+    Migrate,
+    /// Manages the map pool
+    #[clap(subcommand)]
+    Maps(PoolCommand),
+    /// Manages the servers matches are duplicated from
+    #[clap(subcommand)]
+    Servers(PoolCommand),
+    /// Manages the GSLT token pool, tokens are exported in plaintext
+    #[clap(subcommand)]
+    Tokens(PoolCommand),
+    /// Lists, edits & exports matches
+    #[clap(subcommand)]
+    Matches(MatchCommand),
+}
+
+/// Files are read & written as YAML, or as CSV when their name ends with `.csv`
+#[derive(Subcommand)]
+enum PoolCommand {
+    /// Adds every entry in the file, existing entries are kept or updated
+    Import { file: String },
+    /// Writes every entry to the file
+    Export { file: String },
+    /// Prints every entry
+    List,
+    /// Removes the entry with the name, region or token
+    Remove { key: String },
+}
+
+#[derive(Subcommand)]
+enum MatchCommand {
+    /// Prints matches, optionally only those in a state
+    List {
+        #[clap(long)]
+        state: Option<String>,
+    },
+    /// Edits a match, only the given fields are changed & every change is logged
+    Edit {
+        match_id: i32,
+        #[clap(long)]
+        team_one_name: Option<String>,
+        #[clap(long)]
+        team_two_name: Option<String>,
+        #[clap(long)]
+        note: Option<String>,
+        /// Month/Day/Year @ Time <Timezone>
+        #[clap(long)]
+        scheduled: Option<String>,
+        /// bo1, bo3 or bo5
+        #[clap(long)]
+        series_type: Option<String>,
+    },
+    /// Forces a match into a state, i.e. to retry a stuck setup
+    State {
+        match_id: i32,
+        /// entered, scheduled or completed
+        state: String,
+        /// Removes the match's setup steps & series maps & frees its GSLT tokens
+        #[clap(long)]
+        reset_setup: bool,
+    },
+    /// Prints the match's edit log
+    Edits { match_id: i32 },
+    /// Writes every match to the file
+    Export { file: String },
+}
+
+/// GSLT token as it is exported, imported tokens always start out free
+#[derive(Serialize, Deserialize)]
+struct TokenRecord {
+    token: String,
+    #[serde(default)]
+    in_use: bool,
+    #[serde(default)]
+    match_id: Option<i32>,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let conn = establish_connection();
+    let result = match cli.command {
+        Command::Migrate => run_migrations(&conn, &mut std::io::stdout())
+            .map_err(|err| format!("unable to run migrations: {}", err)),
+        Command::Maps(command) => maps(&conn, command),
+        Command::Servers(command) => servers(&conn, command),
+        Command::Tokens(command) => tokens(&conn, &load_keyring(), command),
+        Command::Matches(command) => matches(&conn, command),
+    };
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        exit(1);
+    }
+}
+
+fn establish_connection() -> PgConnection {
+    let database_url = env::var("DATABASE_URL").unwrap_or_else(|_| {
+        eprintln!("error: DATABASE_URL must be set");
+        exit(1);
+    });
+    PgConnection::establish(&database_url).unwrap_or_else(|err| {
+        eprintln!("error: unable to connect to the database: {}", err);
+        exit(1);
+    })
+}
+
+/// Keys tokens are encrypted with, from `secret_keys` in the bot's config file or `SECRET_KEYS`
+fn load_keyring() -> Keyring {
+    config::load_keyring().unwrap_or_else(|err| {
+        eprint!("error: {}", err);
+        exit(1);
+    })
+}
+
+fn maps(conn: &PgConnection, command: PoolCommand) -> Result<(), String> {
+    match command {
+        PoolCommand::Import { file } => {
+            let records: Vec<Map> = read_records(&file)?;
+            let added = records.iter().filter(|map| add_map(conn, map)).count();
+            println!("Added {} of {} maps", added, records.len());
+        }
+        PoolCommand::Export { file } => write_records(&file, &get_map_pool(conn))?,
+        PoolCommand::List => {
+            for map in get_map_pool(conn) {
+                println!("{}", map.name);
+            }
+        }
+        PoolCommand::Remove { key } => {
+            if delete_map(conn, &key) == 0 {
+                return Err(format!("no map named '{}'", key));
+            }
+            println!("Removed map '{}'", key);
+        }
+    }
+    Ok(())
+}
+
+fn servers(conn: &PgConnection, command: PoolCommand) -> Result<(), String> {
+    match command {
+        PoolCommand::Import { file } => {
+            let records: Vec<MatchServer> = read_records(&file)?;
+            let count = records.len();
+            for server in records {
+                update_match_server(conn, server);
+            }
+            println!("Imported {} servers", count);
+        }
+        PoolCommand::Export { file } => write_records(&file, &get_match_servers(conn))?,
+        PoolCommand::List => {
+            for server in get_match_servers(conn) {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    server.region_label,
+                    server.server_id,
+                    server.location.unwrap_or_default(),
+                    server.capacity.map(|c| c.to_string()).unwrap_or_default(),
+                    if server.enabled {
+                        "enabled"
+                    } else {
+                        "disabled"
+                    }
+                );
+            }
+        }
+        PoolCommand::Remove { key } => {
+            if delete_match_server(conn, &key) == 0 {
+                return Err(format!("no server in region '{}'", key));
+            }
+            println!("Removed server '{}'", key);
+        }
+    }
+    Ok(())
+}
+
+fn tokens(conn: &PgConnection, keyring: &Keyring, command: PoolCommand) -> Result<(), String> {
+    match command {
+        PoolCommand::Import { file } => {
+            let records: Vec<TokenRecord> = read_records(&file)?;
+            let added = records
+                .iter()
+                .filter(|record| add_token(conn, keyring, &record.token))
+                .count();
+            println!("Added {} of {} tokens", added, records.len());
+        }
+        PoolCommand::Export { file } => {
+            let records: Vec<TokenRecord> = get_tokens(conn, keyring)
+                .into_iter()
+                .map(|token| TokenRecord {
+                    token: token.token,
+                    in_use: token.in_use,
+                    match_id: token.match_id,
+                })
+                .collect();
+            write_records(&file, &records)?;
+        }
+        PoolCommand::List => {
+            for token in get_tokens(conn, keyring) {
+                println!(
+                    "{}\t{}\t{}",
+                    token.id,
+                    token.masked(),
+                    match token.match_id {
+                        Some(match_id) => format!("match {}", match_id),
+                        None if token.in_use => String::from("in use"),
+                        None => String::from("free"),
+                    }
+                );
+            }
+        }
+        PoolCommand::Remove { key } => {
+            if delete_token(conn, &key) == 0 {
+                return Err(String::from("token not found"));
+            }
+            println!("Removed token");
+        }
+    }
+    Ok(())
+}
+
+fn matches(conn: &PgConnection, command: MatchCommand) -> Result<(), String> {
+    match command {
+        MatchCommand::List { state } => {
+            let state = state.as_deref().map(parse_state).transpose()?;
+            for m in get_all_matches(conn)
+                .into_iter()
+                .filter(|m| state.is_none_or(|state| m.match_state == state))
+            {
+                print_match(&m);
+            }
+        }
+        MatchCommand::Edit {
+            match_id,
+            team_one_name,
+            team_two_name,
+            note,
+            scheduled,
+            series_type,
+        } => {
+            let m = find_match(conn, match_id).ok_or_else(|| format!("no match {}", match_id))?;
+            let changes = MatchChanges {
+                team_one_role_id: team_one_name.as_ref().map(|_| m.team_one_role_id),
+                team_one_name,
+                team_two_role_id: team_two_name.as_ref().map(|_| m.team_two_role_id),
+                team_two_name,
+                note,
+                scheduled_time_str: scheduled
+                    .as_deref()
+                    .map(|s| {
+                        parse_schedule(s).ok_or_else(|| {
+                            format!("cannot parse schedule '{}', use Month/Day/Year @ Time", s)
+                        })
+                    })
+                    .transpose()?,
+                series_type: series_type
+                    .as_deref()
+                    .map(|s| {
+                        s.parse::<SeriesType>()
+                            .map_err(|_| format!("unknown series type '{}'", s))
+                    })
+                    .transpose()?,
+            };
+            let (edited, _) =
+                edit_match(conn, &m, &changes, None).map_err(|err| err.to_string())?;
+            print_match(&edited);
+        }
+        MatchCommand::Edits { match_id } => {
+            for edit in get_match_edits(conn, match_id) {
+                println!(
+                    "{}\t{}\t{}\t{} -> {}",
+                    edit.edited_at.format("%Y-%m-%d %H:%M"),
+                    edit.edited_by
+                        .map(|id| id.to_string())
+                        .unwrap_or_else(|| String::from("admin tool")),
+                    edit.field,
+                    edit.old_value.as_deref().unwrap_or("none"),
+                    edit.new_value.as_deref().unwrap_or("none")
+                );
+            }
+        }
+        MatchCommand::State {
+            match_id,
+            state,
+            reset_setup,
+        } => {
+            find_match(conn, match_id).ok_or_else(|| format!("no match {}", match_id))?;
+            let state = parse_state(&state)?;
+            if reset_setup {
+                let removed = reset_match_setup(conn, match_id);
+                let released = release_match_tokens(conn, match_id);
+                println!(
+                    "Removed {} setup steps & series maps, released {} tokens",
+                    removed, released
+                );
+            }
+            print_match(&update_match_state(conn, match_id, state));
+        }
+        MatchCommand::Export { file } => write_records(&file, &get_all_matches(conn))?,
+    }
+    Ok(())
+}
+
+fn parse_state(state: &str) -> Result<MatchState, String> {
+    state
+        .parse()
+        .map_err(|_| format!("unknown match state '{}'", state))
+}
+
+fn print_match(m: &Match) {
+    println!(
+        "{}\t{:?}\t{} vs {}\t{:?}\t{}\t{}",
+        m.id,
+        m.match_state,
+        m.team_one_name,
+        m.team_two_name,
+        m.series_type,
+        m.scheduled_time_str.as_deref().unwrap_or("unscheduled"),
+        m.note.as_deref().unwrap_or_default()
+    );
+}
+
+fn is_csv(file: &str) -> bool {
+    Path::new(file)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+}
+
+fn read_records<T: DeserializeOwned>(file: &str) -> Result<Vec<T>, String> {
+    let reader = File::open(file).map_err(|err| format!("unable to open '{}': {}", file, err))?;
+    if is_csv(file) {
+        csv::Reader::from_reader(reader)
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .map_err(|err| format!("invalid '{}': {}", file, err))
+    } else {
+        serde_yaml::from_reader(reader).map_err(|err| format!("invalid '{}': {}", file, err))
+    }
+}
+
+fn write_records<T: Serialize>(file: &str, records: &[T]) -> Result<(), String> {
+    let writer =
+        File::create(file).map_err(|err| format!("unable to create '{}': {}", file, err))?;
+    let written = if is_csv(file) {
+        let mut writer = csv::Writer::from_writer(writer);
+        records
+            .iter()
+            .try_for_each(|record| writer.serialize(record))
+            .and_then(|_| writer.flush().map_err(csv::Error::from))
+            .map_err(|err| err.to_string())
+    } else {
+        serde_yaml::to_writer(writer, records).map_err(|err| err.to_string())
+    };
+    written.map_err(|err| format!("unable to write '{}': {}", file, err))?;
+    println!("Wrote {} entries to '{}'", records.len(), file);
+    Ok(())
+}
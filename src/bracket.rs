@@ -0,0 +1,445 @@
+//! Playoff brackets, deciding a bracket match moves its winner & loser on to later matches
+
+use crate::models::{BracketFormat, BracketMatch, BracketSection};
+use std::collections::HashSet;
+use std::fmt;
+
+/// Team spot of a bracket match
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    /// Waiting on the match the team comes from
+    Pending,
+    Team(i64),
+    /// No team will take the spot, the other team moves on without playing
+    Bye,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BracketError {
+    NotEnoughTeams,
+    /// The team is seeded more than once
+    DuplicateTeam(i64),
+    NameTaken,
+    UnknownMatch,
+    /// The match is still waiting on a team
+    NotReady,
+    AlreadyDecided,
+    NotInMatch,
+}
+
+impl fmt::Display for BracketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BracketError::NotEnoughTeams => write!(f, "a bracket needs at least two teams"),
+            BracketError::DuplicateTeam(role_id) => {
+                write!(f, "<@&{}> is seeded more than once", role_id)
+            }
+            BracketError::NameTaken => write!(f, "a bracket with this name already exists"),
+            BracketError::UnknownMatch => write!(f, "the match is not part of the bracket"),
+            BracketError::NotReady => write!(f, "the match is still waiting on a team"),
+            BracketError::AlreadyDecided => write!(f, "the match is already decided"),
+            BracketError::NotInMatch => write!(f, "the winner does not play in this match"),
+        }
+    }
+}
+
+impl std::error::Error for BracketError {}
+
+impl BracketMatch {
+    fn new(
+        bracket_id: i32,
+        seq: usize,
+        section: BracketSection,
+        group_number: Option<i32>,
+        round: i32,
+        position: usize,
+    ) -> Self {
+        BracketMatch {
+            bracket_id,
+            seq: seq as i32,
+            section,
+            group_number,
+            round,
+            position: position as i32,
+            team_one_role_id: None,
+            team_two_role_id: None,
+            team_one_bye: false,
+            team_two_bye: false,
+            winner_to: None,
+            winner_slot: None,
+            loser_to: None,
+            loser_slot: None,
+            winner_role_id: None,
+            match_id: None,
+        }
+    }
+
+    pub fn slot(&self, slot: usize) -> Slot {
+        let (role_id, bye) = match slot {
+            0 => (self.team_one_role_id, self.team_one_bye),
+            _ => (self.team_two_role_id, self.team_two_bye),
+        };
+        match (role_id, bye) {
+            (Some(role_id), _) => Slot::Team(role_id),
+            (None, true) => Slot::Bye,
+            (None, false) => Slot::Pending,
+        }
+    }
+
+    fn set_slot(&mut self, slot: usize, value: Slot) {
+        let (role_id, bye) = match value {
+            Slot::Pending => (None, false),
+            Slot::Team(role_id) => (Some(role_id), false),
+            Slot::Bye => (None, true),
+        };
+        if slot == 0 {
+            self.team_one_role_id = role_id;
+            self.team_one_bye = bye;
+        } else {
+            self.team_two_role_id = role_id;
+            self.team_two_bye = bye;
+        }
+    }
+
+    /// Both teams are known & the match is not decided yet
+    pub fn is_ready(&self) -> bool {
+        self.team_one_role_id.is_some()
+            && self.team_two_role_id.is_some()
+            && self.winner_role_id.is_none()
+    }
+
+    /// Winner & loser, once the match is played or decided by a bye
+    pub fn outcome(&self) -> Option<(Slot, Slot)> {
+        let slots = [self.slot(0), self.slot(1)];
+        if let Some(winner) = self.winner_role_id {
+            let loser = slots
+                .into_iter()
+                .find(|slot| *slot != Slot::Team(winner))
+                .unwrap_or(Slot::Bye);
+            return Some((Slot::Team(winner), loser));
+        }
+        match slots {
+            [Slot::Team(team), Slot::Bye] | [Slot::Bye, Slot::Team(team)] => {
+                Some((Slot::Team(team), Slot::Bye))
+            }
+            [Slot::Bye, Slot::Bye] => Some((Slot::Bye, Slot::Bye)),
+            _ => None,
+        }
+    }
+
+    /// GSL group of the match, i.e. `Group B`
+    pub fn group_name(&self) -> Option<String> {
+        self.group_number
+            .map(|group| format!("Group {}", (b'A' + group as u8) as char))
+    }
+
+    /// Name of the match's round within its section or group, i.e. `Lower round 2` or `decider`
+    pub fn round_name(&self, format: BracketFormat) -> String {
+        match self.section {
+            BracketSection::Final => String::from("Grand final"),
+            BracketSection::Group => String::from(match (self.round, self.position) {
+                (1, _) => "opening match",
+                (2, 0) => "winners' match",
+                (2, _) => "elimination match",
+                _ => "decider",
+            }),
+            BracketSection::Upper if format == BracketFormat::Single => {
+                format!("Round {}", self.round)
+            }
+            BracketSection::Upper => format!("Upper round {}", self.round),
+            BracketSection::Lower => format!("Lower round {}", self.round),
+        }
+    }
+
+    /// Name of the match in its bracket, i.e. `Lower round 2` or `Group B decider`
+    pub fn label(&self, format: BracketFormat) -> String {
+        match self.group_name() {
+            Some(group) => format!("{} {}", group, self.round_name(format)),
+            None => self.round_name(format),
+        }
+    }
+}
+
+/// Seeds in bracket order, pairing the best seed with the worst so that the top seeds can only
+/// meet in the last rounds, i.e. `1, 8, 4, 5, 2, 7, 3, 6` for 8 seeds. `size` is a power of two.
+pub fn seed_order(size: usize) -> Vec<usize> {
+    let mut order = vec![1];
+    while order.len() < size {
+        let count = order.len() * 2;
+        order = order.iter().flat_map(|&s| [s, count + 1 - s]).collect();
+    }
+    order
+}
+
+/// Matches of a new bracket for the teams in seed order, the best seeds get the byes. Matches
+/// decided by byes are already decided.
+pub fn build_bracket(
+    bracket_id: i32,
+    format: BracketFormat,
+    seeds: &[i64],
+) -> Result<Vec<BracketMatch>, BracketError> {
+    if seeds.len() < 2 {
+        return Err(BracketError::NotEnoughTeams);
+    }
+    let mut seen = HashSet::new();
+    if let Some(&duplicate) = seeds.iter().find(|&&role_id| !seen.insert(role_id)) {
+        return Err(BracketError::DuplicateTeam(duplicate));
+    }
+    let seed = |n: usize| match seeds.get(n - 1) {
+        Some(&role_id) => Slot::Team(role_id),
+        None => Slot::Bye,
+    };
+    let mut matches = match format {
+        BracketFormat::Single | BracketFormat::Double => elimination(
+            bracket_id,
+            seeds.len(),
+            format == BracketFormat::Double,
+            seed,
+        ),
+        BracketFormat::Gsl => gsl_groups(bracket_id, seeds.len(), seed),
+    };
+    let byes: Vec<usize> = matches
+        .iter()
+        .filter(|m| m.winner_role_id.is_none() && m.outcome().is_some())
+        .map(|m| m.seq as usize)
+        .collect();
+    let mut changed = Vec::new();
+    for seq in byes {
+        advance(&mut matches, seq, &mut changed);
+    }
+    Ok(matches)
+}
+
+fn push(
+    matches: &mut Vec<BracketMatch>,
+    bracket_id: i32,
+    section: BracketSection,
+    group_number: Option<i32>,
+    round: i32,
+    position: usize,
+) -> usize {
+    let seq = matches.len();
+    matches.push(BracketMatch::new(
+        bracket_id,
+        seq,
+        section,
+        group_number,
+        round,
+        position,
+    ));
+    seq
+}
+
+fn link_winner(matches: &mut [BracketMatch], from: usize, to: usize, slot: usize) {
+    matches[from].winner_to = Some(to as i32);
+    matches[from].winner_slot = Some(slot as i32);
+}
+
+fn link_loser(matches: &mut [BracketMatch], from: usize, to: usize, slot: usize) {
+    matches[from].loser_to = Some(to as i32);
+    matches[from].loser_slot = Some(slot as i32);
+}
+
+/// Single or double elimination, the lower bracket alternates between playing out its own
+/// winners & taking in the losers of the next upper round, in reverse order to avoid rematches
+fn elimination(
+    bracket_id: i32,
+    teams: usize,
+    double: bool,
+    seed: impl Fn(usize) -> Slot,
+) -> Vec<BracketMatch> {
+    let mut size = teams.next_power_of_two();
+    if double {
+        size = size.max(4);
+    }
+    let mut matches = Vec::new();
+    let mut upper: Vec<Vec<usize>> = Vec::new();
+    let mut count = size / 2;
+    while count >= 1 {
+        let round = upper.len() as i32 + 1;
+        let seqs = (0..count)
+            .map(|p| {
+                push(
+                    &mut matches,
+                    bracket_id,
+                    BracketSection::Upper,
+                    None,
+                    round,
+                    p,
+                )
+            })
+            .collect();
+        upper.push(seqs);
+        count /= 2;
+    }
+    for (p, pair) in seed_order(size).chunks(2).enumerate() {
+        let seq = upper[0][p];
+        matches[seq].set_slot(0, seed(pair[0]));
+        matches[seq].set_slot(1, seed(pair[1]));
+    }
+    for r in 1..upper.len() {
+        for (j, &seq) in upper[r].iter().enumerate() {
+            link_winner(&mut matches, upper[r - 1][2 * j], seq, 0);
+            link_winner(&mut matches, upper[r - 1][2 * j + 1], seq, 1);
+        }
+    }
+    if !double {
+        return matches;
+    }
+    let mut lower: Vec<Vec<usize>> = Vec::new();
+    for r in 1..upper.len() {
+        let count = size >> (r + 1);
+        let round = lower.len() as i32 + 1;
+        let odd: Vec<usize> = (0..count)
+            .map(|p| {
+                push(
+                    &mut matches,
+                    bracket_id,
+                    BracketSection::Lower,
+                    None,
+                    round,
+                    p,
+                )
+            })
+            .collect();
+        for (j, &seq) in odd.iter().enumerate() {
+            match lower.last() {
+                None => {
+                    link_loser(&mut matches, upper[0][2 * j], seq, 0);
+                    link_loser(&mut matches, upper[0][2 * j + 1], seq, 1);
+                }
+                Some(previous) => {
+                    let (a, b) = (previous[2 * j], previous[2 * j + 1]);
+                    link_winner(&mut matches, a, seq, 0);
+                    link_winner(&mut matches, b, seq, 1);
+                }
+            }
+        }
+        let even: Vec<usize> = (0..count)
+            .map(|p| {
+                push(
+                    &mut matches,
+                    bracket_id,
+                    BracketSection::Lower,
+                    None,
+                    round + 1,
+                    p,
+                )
+            })
+            .collect();
+        for (j, &seq) in even.iter().enumerate() {
+            link_winner(&mut matches, odd[j], seq, 0);
+            link_loser(&mut matches, upper[r][count - 1 - j], seq, 1);
+        }
+        lower.push(odd);
+        lower.push(even);
+    }
+    let grand_final = push(&mut matches, bracket_id, BracketSection::Final, None, 1, 0);
+    link_winner(&mut matches, upper[upper.len() - 1][0], grand_final, 0);
+    link_winner(&mut matches, lower[lower.len() - 1][0], grand_final, 1);
+    matches
+}
+
+/// Groups of four seeded in a snake, the opening matches' winners meet in the winners' match &
+/// its losers in the elimination match, the decider is played by the winners' match loser & the
+/// elimination match winner
+fn gsl_groups(bracket_id: i32, teams: usize, seed: impl Fn(usize) -> Slot) -> Vec<BracketMatch> {
+    let groups = teams.div_ceil(4);
+    let mut members = vec![Vec::new(); groups];
+    for i in 0..groups * 4 {
+        let (pot, within) = (i / groups, i % groups);
+        let group = if pot % 2 == 0 {
+            within
+        } else {
+            groups - 1 - within
+        };
+        members[group].push(seed(i + 1));
+    }
+    let mut matches = Vec::new();
+    for (g, members) in members.iter().enumerate() {
+        let group = Some(g as i32);
+        let mut add = |round, position| {
+            push(
+                &mut matches,
+                bracket_id,
+                BracketSection::Group,
+                group,
+                round,
+                position,
+            )
+        };
+        let (opening_a, opening_b) = (add(1, 0), add(1, 1));
+        let (winners, elimination) = (add(2, 0), add(2, 1));
+        let decider = add(3, 0);
+        matches[opening_a].set_slot(0, members[0]);
+        matches[opening_a].set_slot(1, members[3]);
+        matches[opening_b].set_slot(0, members[1]);
+        matches[opening_b].set_slot(1, members[2]);
+        link_winner(&mut matches, opening_a, winners, 0);
+        link_winner(&mut matches, opening_b, winners, 1);
+        link_loser(&mut matches, opening_a, elimination, 0);
+        link_loser(&mut matches, opening_b, elimination, 1);
+        link_loser(&mut matches, winners, decider, 0);
+        link_winner(&mut matches, elimination, decider, 1);
+    }
+    matches
+}
+
+/// Moves the winner & loser of a decided match on, deciding matches that get a bye on the way
+fn advance(matches: &mut [BracketMatch], seq: usize, changed: &mut Vec<usize>) {
+    let (winner, loser) = match matches[seq].outcome() {
+        Some(outcome) => outcome,
+        None => return,
+    };
+    let targets = [
+        (matches[seq].winner_to, matches[seq].winner_slot, winner),
+        (matches[seq].loser_to, matches[seq].loser_slot, loser),
+    ];
+    for (to, slot, team) in targets {
+        if let (Some(to), Some(slot)) = (to, slot) {
+            let to = to as usize;
+            matches[to].set_slot(slot as usize, team);
+            changed.push(to);
+            if matches[to].outcome().is_some() {
+                advance(matches, to, changed);
+            }
+        }
+    }
+}
+
+/// Decides the bracket match, returning the seqs of every match that changed
+pub fn report_winner(
+    matches: &mut [BracketMatch],
+    seq: usize,
+    winner: i64,
+) -> Result<Vec<usize>, BracketError> {
+    let bracket_match = matches.get(seq).ok_or(BracketError::UnknownMatch)?;
+    if bracket_match.winner_role_id.is_some() || bracket_match.outcome().is_some() {
+        return Err(BracketError::AlreadyDecided);
+    }
+    if !bracket_match.is_ready() {
+        return Err(BracketError::NotReady);
+    }
+    if bracket_match.team_one_role_id != Some(winner)
+        && bracket_match.team_two_role_id != Some(winner)
+    {
+        return Err(BracketError::NotInMatch);
+    }
+    matches[seq].winner_role_id = Some(winner);
+    let mut changed = vec![seq];
+    advance(matches, seq, &mut changed);
+    Ok(changed)
+}
+
+/// Teams that won their way out of the bracket, the champion or the teams advancing from the
+/// GSL groups
+pub fn advancing(matches: &[BracketMatch]) -> Vec<i64> {
+    matches
+        .iter()
+        .filter(|m| m.winner_to.is_none())
+        .filter_map(|m| match m.outcome() {
+            Some((Slot::Team(winner), _)) => Some(winner),
+            _ => None,
+        })
+        .collect()
+}
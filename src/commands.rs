@@ -1,10 +1,10 @@
-
 use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::str::FromStr;
 
 use async_std::prelude::StreamExt;
-use chrono::{Local, Utc};
+use chrono::{Local, NaiveDate, Utc};
 use regex::Regex;
 use serenity::client::Context;
 use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
@@ -12,19 +12,44 @@ use serenity::model::application::interaction::InteractionResponseType;
 use serenity::model::prelude::interaction::application_command::CommandDataOptionValue;
 use serenity::model::prelude::Role;
 use serenity::utils::MessageBuilder;
+use tracing::{debug, error, field, info, instrument, Span};
 
+use crate::demos::demo_storage;
+use crate::scoreboard::track_match;
 use crate::utils::*;
-use crate::Setup;
-use crate::SetupMap;
-use crate::State::{MapVeto, ServerPick, SidePick};
-use crate::StepType::Pick;
-use csgo_matchbot::models::{Match, MatchSetupStep, MatchState, NewMatch, SeriesType};
+use csgo_matchbot::fixtures::{
+    assign_divisions, division_round_robin, fill_deadlines, parse_fixtures, round_robin,
+    FixtureFormat,
+};
+use csgo_matchbot::models::{
+    parse_schedule, BracketFormat, Match, MatchChanges, MatchScope, MatchServer, MatchState,
+    NewBracket, NewDivision, NewMatch, NewSeason, PlayerMapStats, PlayerStatsTotals,
+    ProviderCredentials, Season, SeasonTeam, SeriesType, Team,
+};
+use csgo_matchbot::standings::standings;
+use csgo_matchbot::veto::{Action, Setup, SetupError};
 use csgo_matchbot::{
-    create_match, create_user, delete_match, get_match, get_match_setup_steps, get_matches,
-    get_next_team_match, update_match_schedule,
+    activate_season, add_token, archive_match, count_free_tokens, create_bracket, create_division,
+    create_match, create_matches, create_season, create_user, delete_match_server,
+    delete_season_team, delete_token, edit_match, find_bracket, find_division, find_match,
+    find_season, find_user_by_discord_id, get_active_season, get_bracket_matches,
+    get_bracket_teams, get_divisions, get_guild_settings, get_latest_bracket, get_map_pool,
+    get_match, get_match_demos, get_match_setup_steps, get_matches, get_next_team_match,
+    get_player_stats, get_result_matches, get_season_teams, get_seasons, get_team,
+    get_team_player_stats, get_teams, get_token, get_tokens, get_users, report_match_result,
+    restore_match, set_match_server_enabled, set_season_maps, update_guild_settings,
+    update_match_schedule, update_match_server, update_provider_credentials, update_season_team,
+    update_team, update_token,
 };
+use diesel::PgConnection;
 
+/// Runs the match setup in a span that the started match's scoreboard & demos are tracked in
+#[instrument(
+    skip_all,
+    fields(guild_id = ?msg.guild_id, user_id = %msg.user.id, match_id, phase)
+)]
 pub(crate) async fn handle_setup(context: &Context, msg: &ApplicationCommandInteraction) {
+    let prefix = get_config(context).await.discord.team_role_prefix;
     let mut next_match = None;
     if let Ok(roles) = context
         .http
@@ -33,16 +58,15 @@ pub(crate) async fn handle_setup(context: &Context, msg: &ApplicationCommandInte
     {
         if let Ok(team_role) = find_user_team_role(roles, &msg.user, &context).await {
             let conn = get_pg_conn(context).await;
-            next_match = get_next_team_match(&conn, team_role.id.0 as i64);
+            let season = get_active_season(&conn).map(|s| s.id);
+            next_match = get_next_team_match(&conn, team_role.id.0 as i64, season);
         }
     } else {
         msg.create_interaction_response(&context.http, |response| {
             response
                 .kind(InteractionResponseType::ChannelMessageWithSource)
                 .interaction_response_data(|message| {
-                    message.ephemeral(true).content(
-                        "You are not part of any team. Verify you have a role starting with `Team`",
-                    )
+                    message.ephemeral(true).content(no_team_message(&prefix))
                 })
         })
         .await
@@ -63,6 +87,23 @@ pub(crate) async fn handle_setup(context: &Context, msg: &ApplicationCommandInte
         .expect("Expected resp");
         return;
     }
+    let dathost_config = match get_dathost_config(context, msg.guild_id.unwrap()).await {
+        Some(dathost_config) => dathost_config,
+        None => {
+            msg.create_interaction_response(&context.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|message| {
+                        message.ephemeral(true).content(
+                            "No Dathost account is set up, an admin has to add one with `/dathost`",
+                        )
+                    })
+            })
+            .await
+            .expect("Expected resp");
+            return;
+        }
+    };
     msg.create_interaction_response(&context.http, |response| {
         response
             .kind(InteractionResponseType::ChannelMessageWithSource)
@@ -71,295 +112,234 @@ pub(crate) async fn handle_setup(context: &Context, msg: &ApplicationCommandInte
     .await
     .expect("Expected resp");
     let current_match = next_match.unwrap();
-    let maps: Vec<String> = get_maps(context).await;
-    let mut setup: Setup = Setup {
-        maps_remaining: maps,
-        maps: vec![],
-        vetoes: vec![],
-        series_type: current_match.series_type,
-        team_one_name: current_match.team_one_name,
-        team_two_name: current_match.team_two_name,
-        team_one: Some(current_match.team_one_role_id),
-        team_two: Some(current_match.team_two_role_id),
-        match_id: Some(current_match.id),
-        veto_pick_order: vec![],
-        current_step: 0,
-        current_phase: ServerPick,
-        server_id: None,
-        team_two_conn_str: None,
-        team_one_conn_str: None,
-    };
+    let span = Span::current();
+    span.record("match_id", current_match.id);
+    let maps: Vec<String> = get_maps(context, current_match.season_id).await;
     let match_servers = get_servers(context).await;
-    let m = msg
+    let mut setup = match Setup::new(
+        current_match.id,
+        current_match.series_type,
+        (current_match.team_one_role_id, current_match.team_one_name),
+        (current_match.team_two_role_id, current_match.team_two_name),
+        maps,
+        match_servers.iter().map(|s| s.server_id.clone()).collect(),
+    ) {
+        Ok(setup) => setup,
+        Err(err) => {
+            msg.edit_original_interaction_response(&context.http, |r| r.content(err.to_string()))
+                .await
+                .expect("Expected resp");
+            return;
+        }
+    };
+    span.record("phase", field::debug(setup.current_phase));
+    info!("setup started");
+    let metrics = get_metrics(context).await;
+    let mut active_setup = Some(metrics.setup_started());
+    let guild_settings = {
+        let conn = get_pg_conn(context).await;
+        get_guild_settings(&conn, msg.guild_id.unwrap().0 as i64)
+    };
+    let init_veto_msg = veto_start_message(&setup);
+    let mut m = msg
         .channel_id
         .send_message(&context, |m| {
-            m.content(format!("<@&{}> selects server.", setup.team_two.unwrap()))
-                .components(|c| c.add_action_row(create_server_action_row(&match_servers)))
+            m.content(setup_prompt(&setup, &init_veto_msg))
+                .components(|c| {
+                    c.add_action_row(setup_action_row(&setup, &match_servers))
+                        .add_action_row(create_setup_admin_action_row())
+                })
         })
         .await
         .unwrap();
 
-    let result = match current_match.series_type {
-        SeriesType::Bo1 => handle_bo1_setup(setup.clone()).await,
-        SeriesType::Bo3 => handle_bo3_setup(setup.clone()).await,
-        SeriesType::Bo5 => handle_bo5_setup(setup.clone()).await,
-    };
-    setup.veto_pick_order = result.0;
-    let init_veto_msg = result.1;
-
-    // Wait for the user to make a selection
-    let mut cib = m.await_component_interactions(&context).build();
+    // Wait for the user to make a selection, with confirmations a ban or pick stays pending
+    // until its team answers the latest confirmation prompt
+    let mut pending: Option<(u32, Action)> = None;
+    let mut confirmations = 0;
+    let mut cib = setup_interactions(context, &m, setup.match_id);
     while let Some(mci) = cib.next().await {
-        match setup.current_phase {
-            ServerPick => {
-                if let Ok(role_id) = user_team_author(context, &setup, &mci).await {
-                    if setup.team_two.unwrap() != role_id as i64 {
-                        mci.create_interaction_response(&context, |r| {
-                            r.kind(InteractionResponseType::ChannelMessageWithSource)
-                                .interaction_response_data(|d| {
-                                    d.ephemeral(true).content(
-                                        "It is not your team's turn to pick or ban a server",
-                                    )
-                                })
+        let custom_id = mci.data.custom_id.as_str();
+        let confirmation = setup_confirmation(custom_id);
+        let action = if custom_id == "undo" {
+            if !user_is_admin(context, &mci).await {
+                mci.create_interaction_response(&context, |r| {
+                    r.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|d| {
+                            d.ephemeral(true)
+                                .content("Only admins can undo setup steps")
                         })
-                        .await
-                        .unwrap();
-                        continue;
-                    }
-                    let server_id = mci.data.values.get(0).unwrap();
-                    setup.server_id = Some(server_id.clone());
-                    mci.create_interaction_response(&context, |r| {
-                        r.kind(InteractionResponseType::UpdateMessage)
-                            .interaction_response_data(|d| {
-                                d.content(&init_veto_msg).components(|c| {
-                                    c.add_action_row(create_map_action_row(
-                                        setup.maps_remaining.clone(),
-                                        &setup.veto_pick_order[0].step_type,
-                                    ))
-                                })
-                            })
-                    })
-                    .await
-                    .unwrap();
-                    setup.current_phase = MapVeto;
-                } else {
-                    no_team_resp(context, &mci).await;
-                }
+                })
+                .await
+                .unwrap();
+                continue;
             }
-            MapVeto => {
-                let map_selected = mci.data.values.get(0).unwrap();
-                if let Ok(role_id) = user_team_author(context, &setup, &mci).await {
-                    if setup
-                        .veto_pick_order
-                        .get(setup.current_step)
-                        .unwrap()
-                        .team_role_id
-                        != role_id as i64
-                    {
-                        mci.create_interaction_response(&context, |r| {
-                            r.kind(InteractionResponseType::ChannelMessageWithSource)
-                                .interaction_response_data(|d| {
-                                    d.ephemeral(true)
-                                        .content("It is not your team's turn to pick or ban")
-                                })
-                        })
-                        .await
-                        .unwrap();
+            // a confirmation of the step being undone must not apply to the rolled back state
+            pending = None;
+            Action::Undo
+        } else {
+            let turn_role_id = match setup.turn_role_id() {
+                Some(turn_role_id) => turn_role_id,
+                None => continue,
+            };
+            let role_id = match setup_actor(context, &setup, &mci, turn_role_id).await {
+                Some(role_id) => role_id as i64,
+                None => continue,
+            };
+            if let Some((confirmed, _, seq)) = confirmation {
+                let team_role_id = match &pending {
+                    Some((
+                        pending_seq,
+                        Action::Ban { team_role_id, .. } | Action::Pick { team_role_id, .. },
+                    )) if *pending_seq == seq => *team_role_id,
+                    _ => {
+                        if let Err(err) =
+                            answer_setup_confirmation(context, &mci, "No longer pending").await
+                        {
+                            error!(error = %err, "unable to answer setup confirmation");
+                        }
                         continue;
                     }
-
-                    if setup.veto_pick_order[setup.current_step].step_type == Pick {
-                        setup.maps.push(SetupMap {
-                            map: map_selected.clone(),
-                            picked_by: setup
-                                .veto_pick_order
-                                .get(setup.current_step)
-                                .unwrap()
-                                .team_role_id,
-                            match_id: 0,
-                            start_attack_team_role_id: None,
-                            start_defense_team_role_id: None,
-                        })
-                    }
-                    setup.veto_pick_order[setup.current_step].map =
-                        Some(String::from(map_selected));
-
-                    if setup.veto_pick_order.len() == setup.current_step + 1 {
-                        let first_map = setup.maps.get(0).unwrap();
-                        let other_role_id =
-                            if setup.maps[0].picked_by == setup.team_one.unwrap() as i64 {
-                                setup.team_two.unwrap()
-                            } else {
-                                setup.team_one.unwrap()
-                            };
-                        mci.create_interaction_response(&context, |r| {
-                            r.kind(InteractionResponseType::UpdateMessage).interaction_response_data(
-                                |d| {
-                                    d.content(format!("Map veto completed.\nIt is <@&{}> turn to pick starting side for `{}`", other_role_id, first_map.map))
-                                        .components(|c| c.add_action_row(create_sidepick_action_row()))
-                                },
-                            )
-                        }).await.unwrap();
-                        setup.current_step = 0;
-                        setup.current_phase = SidePick;
-                        continue;
+                };
+                if team_role_id != role_id {
+                    setup_error_resp(context, &mci, SetupError::NotYourTurn(role_id)).await;
+                    continue;
+                }
+                let (_, action) = pending.take().unwrap();
+                if !confirmed {
+                    if let Err(err) = answer_setup_confirmation(context, &mci, "Cancelled").await {
+                        error!(error = %err, "unable to answer setup confirmation");
                     }
+                    continue;
+                }
+                action
+            } else {
+                match mci
+                    .data
+                    .values
+                    .first()
+                    .and_then(|value| setup_action(&setup, role_id, custom_id, value))
+                {
+                    Some(action) => action,
+                    None => continue,
+                }
+            }
+        };
 
-                    let next_step_type = setup.veto_pick_order[setup.current_step + 1].step_type;
-                    let next_role_id = setup
-                        .veto_pick_order
-                        .get(setup.current_step + 1)
-                        .unwrap()
-                        .team_role_id;
-                    println!("{:#?}", map_selected);
-                    let map_index = setup
-                        .maps_remaining
-                        .iter()
-                        .position(|m| m == map_selected)
-                        .unwrap();
-                    let mut row = String::new();
-                    let setup_info: Vec<MatchSetupStep> = setup
-                        .veto_pick_order
-                        .iter()
-                        .map(|v| MatchSetupStep {
-                            id: 0,
-                            match_id: 0,
-                            step_type: v.step_type,
-                            team_role_id: v.team_role_id,
-                            map: v.map.clone(),
-                        })
-                        .collect();
-                    let m = Match {
-                        id: 0,
-                        team_one_role_id: setup.team_one.unwrap(),
-                        team_one_name: setup.team_one_name.clone(),
-                        team_two_role_id: setup.team_two.unwrap(),
-                        team_two_name: setup.team_two_name.clone(),
-                        note: None,
-                        date_added: Utc::now().naive_local(),
-                        match_state: MatchState::Entered,
-                        scheduled_time_str: None,
-                        series_type: SeriesType::Bo1,
-                    };
-                    row.push_str(print_veto_info(&setup_info, &m).as_str());
-                    setup.maps_remaining.remove(map_index);
-                    mci.create_interaction_response(&context, |r| {
-                        r.kind(InteractionResponseType::UpdateMessage)
-                            .interaction_response_data(|d| {
-                                d.content(format!(
-                                    "{}\nIt is <@&{}> turn to {}",
-                                    row,
-                                    next_role_id,
-                                    &next_step_type.to_string()
-                                ))
-                                .components(|c| {
-                                    c.add_action_row(create_map_action_row(
-                                        setup.maps_remaining.clone(),
-                                        &next_step_type,
-                                    ))
-                                })
-                            })
-                    })
-                    .await
-                    .unwrap();
-                    setup.current_step += 1;
-                } else {
-                    no_team_resp(context, &mci).await;
+        if let Action::Ban { map, .. } | Action::Pick { map, .. } = &action {
+            if guild_settings.confirm_veto && confirmation.is_none() {
+                // only ask for confirmation if the selection would be accepted
+                if let Err(err) = setup.clone().apply(action.clone()) {
+                    setup_error_resp(context, &mci, err).await;
+                    continue;
                 }
+                let prompt = format!(
+                    "Confirm {} of `{}`?",
+                    setup.veto_step().unwrap().step_type,
+                    setup.map_pool.get(map).unwrap().name
+                );
+                confirmations += 1;
+                pending = Some((confirmations, action));
+                if let Err(err) =
+                    confirm_setup_step(context, &mci, prompt, setup.match_id, confirmations).await
+                {
+                    error!(error = %err, "unable to ask for setup confirmation");
+                }
+                continue;
             }
-            SidePick => {
-                let option_selected = mci.data.values.get(0).unwrap();
-                if let Ok(role_id) = user_team_author(context, &setup, &mci).await {
-                    let picked_by = setup.maps[setup.current_step].picked_by;
-                    let not_picked_by = if picked_by == setup.team_one.unwrap() as i64 {
-                        setup.team_two.unwrap()
-                    } else {
-                        setup.team_one.unwrap()
-                    };
-                    if picked_by == role_id as i64 {
-                        mci.create_interaction_response(&context, |r| {
-                            r.kind(InteractionResponseType::ChannelMessageWithSource)
-                                .interaction_response_data(|d| {
-                                    d.ephemeral(true)
-                                        .content("It is not your team's turn to pick sides")
-                                })
-                        })
-                        .await
-                        .unwrap();
-                        continue;
-                    }
-                    if setup.maps.len() != setup.current_step + 1 {
-                        let next_team_picked_by =
-                            &setup.maps.get(setup.current_step + 1).unwrap().picked_by;
-                        let next_team = if next_team_picked_by == &(setup.team_one.unwrap() as i64)
-                        {
-                            setup.team_two.unwrap()
-                        } else {
-                            setup.team_one.unwrap()
-                        };
-                        let next_map = &setup.maps.get(setup.current_step + 1).unwrap().map;
-                        mci.create_interaction_response(&context, |r| {
-                            r.kind(InteractionResponseType::UpdateMessage)
-                                .interaction_response_data(|d| {
-                                    d.content(format!(
-                                        "It is <@&{}> turn to pick starting side on {}",
-                                        next_team, next_map
-                                    ))
-                                    .components(|c| c.add_action_row(create_sidepick_action_row()))
-                                })
-                        })
+        }
+        match setup.apply(action) {
+            Ok(event) => {
+                info!(event = ?event, "setup step");
+                metrics.setup_step(&event);
+                span.record("phase", field::debug(setup.current_phase));
+                pending = None;
+            }
+            Err(err) => {
+                debug!(error = %err, "setup action rejected");
+                setup_error_resp(context, &mci, err).await;
+                continue;
+            }
+        }
+
+        if confirmation.is_some() {
+            // the confirmation prompt is answered, the setup message is edited instead
+            if let Err(err) = answer_setup_confirmation(context, &mci, "Confirmed").await {
+                error!(error = %err, "unable to answer setup confirmation");
+            }
+            if !setup.is_completed() {
+                if let Err(err) =
+                    edit_setup_message(context, &mut m, &setup, &init_veto_msg, &match_servers)
                         .await
-                        .unwrap();
-                    }
-                    if option_selected == &String::from("ct") {
-                        setup.maps[setup.current_step].start_defense_team_role_id =
-                            Some(not_picked_by);
-                        setup.maps[setup.current_step].start_attack_team_role_id =
-                            Some(picked_by as i64);
-                    } else {
-                        setup.maps[setup.current_step].start_attack_team_role_id =
-                            Some(not_picked_by);
-                        setup.maps[setup.current_step].start_defense_team_role_id =
-                            Some(picked_by as i64);
-                    }
-                    setup.current_step += 1;
-                    if setup.maps.len() == setup.current_step {
-                        let new_msg = msg
-                            .channel_id
-                            .send_message(&context, |m| {
-                                m.content("Match setup completed, starting server...")
-                            })
-                            .await
-                            .unwrap();
-                        m.delete(&context)
-                            .await
-                            .expect("Expected message to delete");
-                        match start_server(context, msg.guild_id.unwrap(), &mut setup).await {
-                            Ok(resp) => {
-                                finish_setup(context, &setup).await;
-                                create_conn_message(context, &new_msg, resp, &setup).await;
-                                return;
-                            }
-                            Err(err) => {
-                                eprintln!("{:#?}", err)
-                            }
-                        }
-                    }
-                } else {
-                    no_team_resp(context, &mci).await;
+                {
+                    error!(error = %err, "unable to update setup message");
                 }
+                continue;
+            }
+        } else if !setup.is_completed() {
+            update_setup_message(context, &mci, &setup, &init_veto_msg, &match_servers)
+                .await
+                .unwrap();
+            continue;
+        }
+        // no longer counted as active while the server starts & connect info is shown
+        active_setup.take();
+        let mut new_msg = msg
+            .channel_id
+            .send_message(&context, |m| {
+                m.content("Match setup completed, starting server...")
+            })
+            .await
+            .unwrap();
+        m.delete(&context)
+            .await
+            .expect("Expected message to delete");
+        match start_server(context, msg.guild_id.unwrap(), &mut setup, &dathost_config).await {
+            Ok(mut started) => {
+                finish_setup(context, &setup).await;
+                if let Some(tracked) = started.tracked.take() {
+                    tokio::spawn(track_match(
+                        context.clone(),
+                        new_msg.channel_id,
+                        setup.clone(),
+                        tracked,
+                        dathost_config.clone(),
+                    ));
+                }
+                create_conn_message(context, &new_msg, started, &setup).await;
+                return;
+            }
+            Err(err) => {
+                error!(error = %err, "unable to start server");
+                // the setup message is gone, the veto is not saved so the match can be set up again
+                if let Err(err) = new_msg
+                    .edit(&context, |m| {
+                        m.content(format!(
+                            "Unable to start the server: {}\nUse `/setup` to try again",
+                            err
+                        ))
+                    })
+                    .await
+                {
+                    error!(error = %err, "unable to post server start failure");
+                }
+                return;
             }
         }
     }
 }
 
 pub(crate) async fn handle_map_list(context: &Context) -> String {
-    let maps: Vec<String> = get_maps(context).await;
+    let season = {
+        let conn = get_pg_conn(context).await;
+        get_active_season(&conn).map(|s| s.id)
+    };
+    let maps: Vec<String> = get_maps(context, season).await;
     let map_str: String = maps.iter().map(|map| format!("- `{}`\n", map)).collect();
-    return MessageBuilder::new()
+    MessageBuilder::new()
         .push_line("Current map pool:")
         .push(map_str)
-        .build();
+        .build()
 }
 
 pub(crate) async fn handle_schedule(
@@ -369,15 +349,19 @@ pub(crate) async fn handle_schedule(
     let option_one = msg
         .data
         .options
-        .get(0)
+        .first()
         .expect("Expected date option")
         .resolved
         .as_ref()
         .expect("Expected object");
     let mut date: Option<String> = None;
     if let CommandDataOptionValue::String(date_str) = option_one {
-        date = Some(date_str.clone());
+        match parse_schedule(date_str) {
+            Some(schedule) => date = Some(schedule),
+            None => return schedule_format_message(date_str),
+        }
     }
+    let prefix = get_config(context).await.discord.team_role_prefix;
     if let Ok(roles) = context
         .http
         .get_guild_roles(*msg.guild_id.unwrap().as_u64())
@@ -385,7 +369,7 @@ pub(crate) async fn handle_schedule(
     {
         let team_roles: Vec<Role> = roles
             .into_iter()
-            .filter(|r| r.name.starts_with("Team"))
+            .filter(|r| r.name.starts_with(&prefix))
             .collect();
         let mut user_team_role: Option<Role> = None;
         for team_role in team_roles {
@@ -403,7 +387,9 @@ pub(crate) async fn handle_schedule(
         }
         if let Some(team_role) = user_team_role {
             let conn = get_pg_conn(context).await;
-            return if let Some(next_match) = get_next_team_match(&conn, team_role.id.0 as i64) {
+            let season = get_active_season(&conn).map(|s| s.id);
+            let next_match = get_next_team_match(&conn, team_role.id.0 as i64, season);
+            return if let Some(next_match) = next_match {
                 update_match_schedule(&conn, next_match.id, date.clone().unwrap());
                 let resp_str = format!(
                     "Your next match (<@&{}> vs <@&{}>) is scheduled for `{}`",
@@ -417,20 +403,34 @@ pub(crate) async fn handle_schedule(
             };
         }
     }
-    String::from("You are not part of any team. Verify you have a role starting with `Team`")
+    no_team_message(&prefix)
+}
+
+fn schedule_format_message(value: &str) -> String {
+    format!(
+        "Cannot parse `{}`, use `Month/Day/Year @ Time <Timezone>`, e.g. `08/30/2022 @ 18:00 CET`",
+        value
+    )
+}
+
+fn no_team_message(prefix: &str) -> String {
+    format!(
+        "You are not part of any team. Verify you have a role starting with `{}`",
+        prefix
+    )
 }
 
 pub(crate) async fn handle_match(context: &Context, msg: &ApplicationCommandInteraction) -> String {
     let option_one = msg
         .data
         .options
-        .get(0)
+        .first()
         .expect("Expected match id")
         .resolved
         .as_ref()
         .expect("Expected object");
 
-    return if let CommandDataOptionValue::String(match_id) = option_one {
+    if let CommandDataOptionValue::String(match_id) = option_one {
         let match_id_parsed = match_id.clone().parse::<i32>().unwrap();
         let conn = get_pg_conn(context).await;
         let m: Match = get_match(&conn, match_id_parsed);
@@ -441,33 +441,64 @@ pub(crate) async fn handle_match(context: &Context, msg: &ApplicationCommandInte
         row
     } else {
         String::from("Discord API error")
-    };
+    }
 }
 
 pub(crate) async fn handle_matches(
     context: &Context,
     msg: &ApplicationCommandInteraction,
 ) -> String {
-    let option_one = msg.data.options.get(0);
     let mut show_completed = false;
-    if let Some(option) = option_one {
-        if let Some(CommandDataOptionValue::Boolean(display)) = &option.resolved {
-            show_completed = *display;
+    let mut show_archived = false;
+    let mut season_name = None;
+    let mut division_name = None;
+    let mut all_divisions = false;
+    for option in &msg.data.options {
+        match (option.name.as_str(), &option.resolved) {
+            ("showcompleted", Some(CommandDataOptionValue::Boolean(display))) => {
+                show_completed = *display
+            }
+            ("showarchived", Some(CommandDataOptionValue::Boolean(display))) => {
+                show_archived = *display
+            }
+            ("season", Some(CommandDataOptionValue::String(name))) => {
+                season_name = Some(name.trim())
+            }
+            ("division", Some(CommandDataOptionValue::String(name))) => {
+                division_name = Some(name.trim())
+            }
+            ("alldivisions", Some(CommandDataOptionValue::Boolean(value))) => {
+                all_divisions = *value
+            }
+            _ => {}
         }
     }
+    let (season, division) =
+        match resolve_scope(context, msg, season_name, division_name, all_divisions).await {
+            Ok(scope) => scope,
+            Err(err) => return err,
+        };
+    let state = if show_archived {
+        MatchState::Archived
+    } else if show_completed {
+        MatchState::Completed
+    } else {
+        MatchState::Entered
+    };
     let conn = get_pg_conn(context).await;
-    let matches = get_matches(&conn, 20, show_completed);
+    let scope = match_scope(season.as_ref(), division.as_ref());
+    let matches = get_matches(&conn, 20, state, scope);
     if matches.is_empty() {
         return String::from("No matches have been added");
     }
-    let matches_str: String = matches
-        .iter()
-        .map(|m| {
-            let mut row = String::new();
-            row.push_str(print_match_info(m, true).as_str());
-            row
-        })
-        .collect();
+    let mut matches_str = String::new();
+    if season.is_some() {
+        matches_str.push_str(&format!(
+            "**{}**\n",
+            print_scope(season.as_ref(), division.as_ref())
+        ));
+    }
+    matches_str.extend(matches.iter().map(|m| print_match_info(m, true)));
     matches_str
 }
 
@@ -482,7 +513,7 @@ pub(crate) async fn handle_add_match(
     let option_one = msg
         .data
         .options
-        .get(0)
+        .first()
         .expect("Expected teamone option")
         .resolved
         .as_ref()
@@ -533,6 +564,15 @@ pub(crate) async fn handle_add_match(
     if note != String::new() {
         note_content = Some(note.as_str());
     }
+    let conn = get_pg_conn(context).await;
+    let season = get_active_season(&conn);
+    let division_id = season.as_ref().and_then(|season| {
+        SeasonTeam::shared_division(
+            &get_season_teams(&conn, season.id),
+            team_one_role_id as i64,
+            team_two_role_id as i64,
+        )
+    });
     let new_match = NewMatch {
         team_one_role_id: team_one_role_id as i64,
         team_one_name,
@@ -542,8 +582,11 @@ pub(crate) async fn handle_add_match(
         series_type: &series_type,
         date_added: &Local::now().naive_local(),
         match_state: &MatchState::Entered,
+        round: None,
+        deadline: None,
+        season_id: season.map(|s| s.id),
+        division_id,
     };
-    let conn = get_pg_conn(context).await;
     create_match(&conn, new_match);
     String::from("Successfully added new match")
 }
@@ -556,27 +599,1050 @@ pub(crate) async fn handle_delete_match(
     if let Err(error) = admin_check {
         return error;
     }
-    let option_one = msg
+    let mut parsed_match_id: Option<i32> = None;
+    let mut reason: Option<&str> = None;
+    for option in &msg.data.options {
+        match (option.name.as_str(), &option.resolved) {
+            ("matchid", Some(CommandDataOptionValue::Integer(match_id))) => {
+                parsed_match_id = i32::try_from(*match_id).ok();
+            }
+            ("reason", Some(CommandDataOptionValue::String(r))) => reason = Some(r.as_str()),
+            _ => {}
+        }
+    }
+    let id = match parsed_match_id {
+        Some(id) => id,
+        None => return String::from("Cannot parse match id input"),
+    };
+    let conn = get_pg_conn(context).await;
+    match archive_match(&conn, id, msg.user.id.0 as i64, reason) {
+        Some(_) => format!(
+            "Archived match `{}`, it can be restored with `/restorematch`",
+            id
+        ),
+        None => format!("Match `{}` does not exist or is already archived", id),
+    }
+}
+
+pub(crate) async fn handle_edit_match(
+    context: &Context,
+    msg: &ApplicationCommandInteraction,
+) -> String {
+    let admin_check = admin_check(context, msg).await;
+    if let Err(error) = admin_check {
+        return error;
+    }
+    let mut match_id: Option<i32> = None;
+    let mut changes = MatchChanges::default();
+    for option in &msg.data.options {
+        match (option.name.as_str(), &option.resolved) {
+            ("matchid", Some(CommandDataOptionValue::Integer(id))) => {
+                match_id = i32::try_from(*id).ok();
+            }
+            ("teamone", Some(CommandDataOptionValue::Role(role))) => {
+                changes.team_one_role_id = Some(role.id.0 as i64);
+                changes.team_one_name = Some(role.name.clone());
+            }
+            ("teamtwo", Some(CommandDataOptionValue::Role(role))) => {
+                changes.team_two_role_id = Some(role.id.0 as i64);
+                changes.team_two_name = Some(role.name.clone());
+            }
+            ("type", Some(CommandDataOptionValue::String(s_type))) => {
+                changes.series_type = SeriesType::from_str(s_type).ok();
+            }
+            ("note", Some(CommandDataOptionValue::String(note))) => {
+                changes.note = Some(note.clone());
+            }
+            ("scheduled", Some(CommandDataOptionValue::String(scheduled))) => {
+                match parse_schedule(scheduled) {
+                    Some(schedule) => changes.scheduled_time_str = Some(schedule),
+                    None => return schedule_format_message(scheduled),
+                }
+            }
+            _ => {}
+        }
+    }
+    let id = match match_id {
+        Some(id) => id,
+        None => return String::from("Cannot parse match id input"),
+    };
+    let conn = get_pg_conn(context).await;
+    let m = match find_match(&conn, id) {
+        Some(m) => m,
+        None => return format!("Match `{}` does not exist", id),
+    };
+    match edit_match(&conn, &m, &changes, Some(msg.user.id.0 as i64)) {
+        Ok((_, field_changes)) => {
+            let mut resp = format!("Updated match `{}`:\n", id);
+            for change in field_changes {
+                resp.push_str(&format!(
+                    "- {}: `{}` → `{}`\n",
+                    change.field,
+                    change.old_value.as_deref().unwrap_or("none"),
+                    change.new_value.as_deref().unwrap_or("none")
+                ));
+            }
+            resp
+        }
+        Err(err) => format!("Cannot edit match `{}`: {}", id, err),
+    }
+}
+
+pub(crate) async fn handle_restore_match(
+    context: &Context,
+    msg: &ApplicationCommandInteraction,
+) -> String {
+    let admin_check = admin_check(context, msg).await;
+    if let Err(error) = admin_check {
+        return error;
+    }
+    let match_id = msg
         .data
         .options
-        .get(0)
-        .expect("Expected matchid option")
-        .resolved
-        .as_ref()
-        .expect("Expected object");
-    let mut parsed_match_id: Option<i32> = None;
-    if let CommandDataOptionValue::Integer(match_id) = option_one {
-        if let Ok(id) = i32::try_from(*match_id) {
-            parsed_match_id = Some(id);
+        .first()
+        .and_then(|o| o.resolved.as_ref())
+        .and_then(|v| match v {
+            CommandDataOptionValue::Integer(id) => i32::try_from(*id).ok(),
+            _ => None,
+        });
+    let id = match match_id {
+        Some(id) => id,
+        None => return String::from("Cannot parse match id input"),
+    };
+    let conn = get_pg_conn(context).await;
+    match restore_match(&conn, id) {
+        Some(m) => format!(
+            "Restored match `{}`, it is `{}` again",
+            id,
+            format!("{:?}", m.match_state).to_lowercase()
+        ),
+        None => format!("Match `{}` is not archived", id),
+    }
+}
+
+pub(crate) async fn handle_fixtures(
+    context: &Context,
+    msg: &ApplicationCommandInteraction,
+) -> String {
+    let admin_check = admin_check(context, msg).await;
+    if let Err(error) = admin_check {
+        return error;
+    }
+    let subcommand = match msg.data.options.first() {
+        Some(subcommand) => subcommand,
+        None => return String::from("Discord API error"),
+    };
+    let mut series_type = SeriesType::Bo1;
+    let mut double = false;
+    let mut start = Local::now().naive_local().date();
+    let mut days = 7;
+    let mut attachment = None;
+    for option in &subcommand.options {
+        match (option.name.as_str(), &option.resolved) {
+            ("type", Some(CommandDataOptionValue::String(s_type))) => {
+                series_type = SeriesType::from_str(s_type).unwrap_or(series_type)
+            }
+            ("double", Some(CommandDataOptionValue::Boolean(value))) => double = *value,
+            ("start", Some(CommandDataOptionValue::String(value))) => {
+                match NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d") {
+                    Ok(date) => start = date,
+                    Err(_) => return String::from("Cannot parse start date, use `YYYY-MM-DD`"),
+                }
+            }
+            ("days", Some(CommandDataOptionValue::Integer(value))) => days = *value,
+            ("file", Some(CommandDataOptionValue::Attachment(file))) => attachment = Some(file),
+            _ => {}
+        }
+    }
+    if days < 1 {
+        return String::from("Rounds have to be at least one day long");
+    }
+    let (season, teams, season_teams) = {
+        let conn = get_pg_conn(context).await;
+        let season = get_active_season(&conn);
+        let season_teams = season
+            .as_ref()
+            .map(|season| get_season_teams(&conn, season.id))
+            .unwrap_or_default();
+        (season, get_teams(&conn), season_teams)
+    };
+    let mut fixtures = match (subcommand.name.as_str(), attachment) {
+        ("generate", _) => {
+            // every registered team plays until teams are added to the season
+            let fixtures = if season_teams.is_empty() {
+                round_robin(&teams, double)
+            } else {
+                division_round_robin(&teams, &season_teams, double)
+            };
+            if fixtures.is_empty() {
+                return String::from(
+                    "At least two teams have to be registered with `/captain` or added to the \
+                     season's divisions with `/season team`",
+                );
+            }
+            fixtures
+        }
+        ("import", Some(attachment)) => {
+            let format = match FixtureFormat::from_file_name(&attachment.filename) {
+                Some(format) => format,
+                None => return String::from("Fixture lists have to be `.csv` or `.json` files"),
+            };
+            let contents = match attachment.download().await {
+                Ok(contents) => contents,
+                Err(err) => return format!("Cannot download `{}`: {}", attachment.filename, err),
+            };
+            match parse_fixtures(&contents, format, &teams) {
+                Ok(mut fixtures) => {
+                    assign_divisions(&mut fixtures, &season_teams);
+                    fixtures
+                }
+                Err(err) => return format!("Cannot import fixtures: {}", err),
+            }
+        }
+        _ => return String::from("Discord API error"),
+    };
+    fill_deadlines(&mut fixtures, start, days);
+    let date_added = Local::now().naive_local();
+    let season_id = season.as_ref().map(|s| s.id);
+    let new_matches = fixtures
+        .iter()
+        .map(|f| f.new_match(&series_type, &date_added, season_id))
+        .collect();
+    let conn = get_pg_conn(context).await;
+    let created = create_matches(&conn, new_matches);
+    let rounds = fixtures.iter().map(|f| f.round).max().unwrap_or_default();
+    let last_deadline = fixtures.iter().filter_map(|f| f.deadline).max();
+    let mut resp = format!("Added {} matches in {} rounds", created, rounds);
+    if let Some(season) = season {
+        resp.push_str(&format!(" to `{}`", season.name));
+    }
+    if let Some(deadline) = last_deadline {
+        resp.push_str(&format!(
+            ", the last deadline is `{}`",
+            deadline.format("%Y-%m-%d %H:%M")
+        ));
+    }
+    resp
+}
+
+pub(crate) async fn handle_result(
+    context: &Context,
+    msg: &ApplicationCommandInteraction,
+) -> String {
+    let admin_check = admin_check(context, msg).await;
+    if let Err(error) = admin_check {
+        return error;
+    }
+    let mut match_id: Option<i32> = None;
+    let mut winner: Option<i64> = None;
+    let mut team_one_score: Option<i32> = None;
+    let mut team_two_score: Option<i32> = None;
+    for option in &msg.data.options {
+        match (option.name.as_str(), &option.resolved) {
+            ("matchid", Some(CommandDataOptionValue::Integer(id))) => {
+                match_id = i32::try_from(*id).ok();
+            }
+            ("winner", Some(CommandDataOptionValue::Role(role))) => winner = Some(role.id.0 as i64),
+            ("teamonescore", Some(CommandDataOptionValue::Integer(score))) => {
+                team_one_score = i32::try_from(*score).ok()
+            }
+            ("teamtwoscore", Some(CommandDataOptionValue::Integer(score))) => {
+                team_two_score = i32::try_from(*score).ok()
+            }
+            _ => {}
+        }
+    }
+    let (id, winner) = match (match_id, winner) {
+        (Some(id), Some(winner)) => (id, winner),
+        _ => return String::from("Cannot parse match id input"),
+    };
+    let score = match (team_one_score, team_two_score) {
+        (Some(one), Some(two)) => Some((one, two)),
+        (None, None) => None,
+        _ => return String::from("Give the maps won by both teams, or neither"),
+    };
+    let conn = get_pg_conn(context).await;
+    let m = match find_match(&conn, id) {
+        Some(m) => m,
+        None => return format!("Match `{}` does not exist", id),
+    };
+    match report_match_result(&conn, &m, winner, score) {
+        Ok((_, started)) => {
+            let mut resp = format!("<@&{}> won match `{}`\n", winner, id);
+            resp.push_str(&print_started_matches(&started));
+            resp
+        }
+        Err(err) => format!("Cannot report the result of match `{}`: {}", id, err),
+    }
+}
+
+pub(crate) async fn handle_bracket(
+    context: &Context,
+    msg: &ApplicationCommandInteraction,
+) -> String {
+    let subcommand = match msg.data.options.first() {
+        Some(subcommand) => subcommand,
+        None => return String::from("Discord API error"),
+    };
+    let mut name = None;
+    let mut format = None;
+    let mut series_type = SeriesType::Bo3;
+    let mut seeds = None;
+    let mut team_count = None;
+    for option in &subcommand.options {
+        match (option.name.as_str(), &option.resolved) {
+            ("name", Some(CommandDataOptionValue::String(value))) => name = Some(value.trim()),
+            ("format", Some(CommandDataOptionValue::String(value))) => {
+                format = BracketFormat::from_str(value).ok()
+            }
+            ("type", Some(CommandDataOptionValue::String(s_type))) => {
+                series_type = SeriesType::from_str(s_type).unwrap_or(series_type)
+            }
+            ("seeds", Some(CommandDataOptionValue::String(value))) => seeds = Some(value),
+            ("teams", Some(CommandDataOptionValue::Integer(value))) => {
+                team_count = usize::try_from(*value).ok()
+            }
+            _ => {}
+        }
+    }
+    if subcommand.name == "show" {
+        let conn = get_pg_conn(context).await;
+        let bracket = match name {
+            Some(name) => find_bracket(&conn, name),
+            None => get_latest_bracket(&conn),
+        };
+        return match bracket {
+            Some(bracket) => print_bracket(
+                &bracket,
+                &get_bracket_matches(&conn, bracket.id),
+                &get_bracket_teams(&conn, bracket.id),
+            ),
+            None => String::from("No bracket found"),
+        };
+    }
+    let admin_check = admin_check(context, msg).await;
+    if let Err(error) = admin_check {
+        return error;
+    }
+    let (name, format) = match (name, format) {
+        (Some(name), Some(format)) if !name.is_empty() => (name, format),
+        _ => return String::from("Discord API error"),
+    };
+    let seeded: Vec<(i64, String)> = match seeds {
+        Some(seeds) => {
+            let roles = match context
+                .http
+                .get_guild_roles(*msg.guild_id.unwrap().as_u64())
+                .await
+            {
+                Ok(roles) => roles,
+                Err(_) => return String::from("Discord API error"),
+            };
+            let mention_regex = Regex::new(r"<@&(\d+)>").unwrap();
+            let mut seeded = Vec::new();
+            for role_id in mention_regex
+                .captures_iter(seeds)
+                .filter_map(|c| c[1].parse::<u64>().ok())
+            {
+                match roles.iter().find(|r| r.id.0 == role_id) {
+                    Some(role) => seeded.push((role_id as i64, role.name.clone())),
+                    None => return format!("Unknown role `{}`", role_id),
+                }
+            }
+            seeded
+        }
+        None => {
+            let conn = get_pg_conn(context).await;
+            let scope = MatchScope {
+                season_id: get_active_season(&conn).map(|s| s.id),
+                division_id: None,
+            };
+            standings(&get_result_matches(&conn, scope))
+                .into_iter()
+                .take(team_count.unwrap_or(usize::MAX))
+                .map(|standing| (standing.role_id, standing.name))
+                .collect()
+        }
+    };
+    let conn = get_pg_conn(context).await;
+    let new_bracket = NewBracket {
+        name,
+        format,
+        series_type,
+        created_at: Utc::now().naive_utc(),
+        season_id: get_active_season(&conn).map(|s| s.id),
+    };
+    match create_bracket(&conn, new_bracket, &seeded) {
+        Ok((_, started)) => {
+            let mut resp = format!(
+                "Created {} bracket `{}` with {} teams\n",
+                format,
+                name,
+                seeded.len()
+            );
+            resp.push_str(&print_started_matches(&started));
+            resp
         }
+        Err(err) => format!("Cannot create bracket: {}", err),
     }
-    if let Some(id) = parsed_match_id {
+}
+
+pub(crate) async fn handle_standings(
+    context: &Context,
+    msg: &ApplicationCommandInteraction,
+) -> String {
+    let mut season_name = None;
+    let mut division_name = None;
+    let mut all_divisions = false;
+    for option in &msg.data.options {
+        match (option.name.as_str(), &option.resolved) {
+            ("season", Some(CommandDataOptionValue::String(name))) => {
+                season_name = Some(name.trim())
+            }
+            ("division", Some(CommandDataOptionValue::String(name))) => {
+                division_name = Some(name.trim())
+            }
+            ("alldivisions", Some(CommandDataOptionValue::Boolean(value))) => {
+                all_divisions = *value
+            }
+            _ => {}
+        }
+    }
+    let (season, division) =
+        match resolve_scope(context, msg, season_name, division_name, all_divisions).await {
+            Ok(scope) => scope,
+            Err(err) => return err,
+        };
+    let conn = get_pg_conn(context).await;
+    let results = get_result_matches(&conn, match_scope(season.as_ref(), division.as_ref()));
+    if results.is_empty() {
+        return String::from("No results have been reported");
+    }
+    print_standings(
+        &print_scope(season.as_ref(), division.as_ref()),
+        &standings(&results),
+    )
+}
+
+pub(crate) async fn handle_season(
+    context: &Context,
+    msg: &ApplicationCommandInteraction,
+) -> String {
+    let subcommand = match msg.data.options.first() {
+        Some(subcommand) => subcommand,
+        None => return String::from("Discord API error"),
+    };
+    let mut name = None;
+    let mut activate = false;
+    let mut map_names = None;
+    let mut season_name = None;
+    let mut team = None;
+    let mut division_name = None;
+    for option in &subcommand.options {
+        match (option.name.as_str(), &option.resolved) {
+            ("name", Some(CommandDataOptionValue::String(value))) => name = Some(value.trim()),
+            ("activate", Some(CommandDataOptionValue::Boolean(value))) => activate = *value,
+            ("maps", Some(CommandDataOptionValue::String(value))) => map_names = Some(value),
+            ("season", Some(CommandDataOptionValue::String(value))) => {
+                season_name = Some(value.trim())
+            }
+            ("team", Some(CommandDataOptionValue::Role(role))) => team = Some(role),
+            ("division", Some(CommandDataOptionValue::String(value))) => {
+                division_name = Some(value.trim())
+            }
+            _ => {}
+        }
+    }
+    if subcommand.name == "list" {
         let conn = get_pg_conn(context).await;
-        delete_match(&conn, id);
+        let seasons = get_seasons(&conn);
+        if seasons.is_empty() {
+            return String::from("No seasons have been created");
+        }
+        let mut resp = String::new();
+        for season in seasons {
+            let season_teams = get_season_teams(&conn, season.id);
+            let active = if season.active { " **active**" } else { "" };
+            resp.push_str(&format!(
+                "- `{}`{} - {} teams\n",
+                season.name,
+                active,
+                season_teams.len()
+            ));
+            for division in get_divisions(&conn, season.id) {
+                let division_teams: Vec<String> = season_teams
+                    .iter()
+                    .filter(|t| t.division_id == Some(division.id))
+                    .map(|t| format!("<@&{}>", t.role_id))
+                    .collect();
+                resp.push_str(&format!(
+                    "    _{}:_ {}\n",
+                    division.name,
+                    division_teams.join(", ")
+                ));
+            }
+        }
+        return resp;
+    }
+    let admin_check = admin_check(context, msg).await;
+    if let Err(error) = admin_check {
+        return error;
+    }
+    match subcommand.name.as_str() {
+        "create" => {
+            let name = match name {
+                Some(name) if !name.is_empty() => name,
+                _ => return String::from("Discord API error"),
+            };
+            let new_season = NewSeason {
+                name,
+                created_at: Utc::now().naive_utc(),
+            };
+            let conn = get_pg_conn(context).await;
+            match create_season(&conn, new_season) {
+                Some(season) if activate || get_active_season(&conn).is_none() => {
+                    activate_season(&conn, season.id);
+                    format!("Created season `{}`, it is the active season", name)
+                }
+                Some(_) => format!("Created season `{}`", name),
+                None => format!("Season `{}` already exists", name),
+            }
+        }
+        "activate" => {
+            let name = name.unwrap_or_default();
+            let conn = get_pg_conn(context).await;
+            match find_season(&conn, name) {
+                Some(season) => {
+                    activate_season(&conn, season.id);
+                    format!("`{}` is now the active season", name)
+                }
+                None => format!("Season `{}` does not exist", name),
+            }
+        }
+        _ => {
+            let conn = get_pg_conn(context).await;
+            let season = match season_name {
+                Some(season_name) => match find_season(&conn, season_name) {
+                    Some(season) => season,
+                    None => return format!("Season `{}` does not exist", season_name),
+                },
+                None => match get_active_season(&conn) {
+                    Some(season) => season,
+                    None => {
+                        return String::from(
+                            "There is no active season, create one with `/season create`",
+                        )
+                    }
+                },
+            };
+            match subcommand.name.as_str() {
+                "maps" => set_season_map_pool(&conn, &season, map_names),
+                "division" => {
+                    let name = name.unwrap_or_default();
+                    let new_division = NewDivision {
+                        season_id: season.id,
+                        name,
+                    };
+                    match create_division(&conn, new_division) {
+                        Some(_) => format!("Added division `{}` to `{}`", name, season.name),
+                        None => format!("`{}` already has a division `{}`", season.name, name),
+                    }
+                }
+                "team" => {
+                    let team = match team {
+                        Some(team) => team,
+                        None => return String::from("Discord API error"),
+                    };
+                    let division = match division_name {
+                        Some(division_name) => {
+                            match find_division(&conn, season.id, division_name) {
+                                Some(division) => Some(division),
+                                None => {
+                                    return format!(
+                                        "Division `{}` does not exist in `{}`",
+                                        division_name, season.name
+                                    )
+                                }
+                            }
+                        }
+                        None => None,
+                    };
+                    if get_team(&conn, team.id.0 as i64).is_none() {
+                        update_team(
+                            &conn,
+                            Team {
+                                role_id: team.id.0 as i64,
+                                name: team.name.clone(),
+                                captain_discord_id: None,
+                                vice_captain_discord_id: None,
+                            },
+                        );
+                    }
+                    update_season_team(
+                        &conn,
+                        SeasonTeam {
+                            season_id: season.id,
+                            role_id: team.id.0 as i64,
+                            division_id: division.as_ref().map(|d| d.id),
+                        },
+                    );
+                    match division {
+                        Some(division) => format!(
+                            "<@&{}> plays in `{}` division `{}`",
+                            team.id.0, season.name, division.name
+                        ),
+                        None => format!("<@&{}> plays in `{}`", team.id.0, season.name),
+                    }
+                }
+                "removeteam" => {
+                    let team = match team {
+                        Some(team) => team,
+                        None => return String::from("Discord API error"),
+                    };
+                    if delete_season_team(&conn, season.id, team.id.0 as i64) > 0 {
+                        format!("Removed <@&{}> from `{}`", team.id.0, season.name)
+                    } else {
+                        format!("<@&{}> does not play in `{}`", team.id.0, season.name)
+                    }
+                }
+                _ => String::from("Discord API error"),
+            }
+        }
+    }
+}
+
+/// Sets the season's map pool from a list of map names, an empty list uses every map
+fn set_season_map_pool(conn: &PgConnection, season: &Season, map_names: Option<&String>) -> String {
+    let map_pool = get_map_pool(conn);
+    let mut pool = Vec::new();
+    for map_name in map_names
+        .map(|names| names.split(|c: char| c == ',' || c.is_whitespace()))
+        .into_iter()
+        .flatten()
+        .filter(|name| !name.is_empty())
+    {
+        match map_pool
+            .iter()
+            .find(|map| map.name.eq_ignore_ascii_case(map_name))
+        {
+            Some(map) if !pool.contains(&map.name) => pool.push(map.name.clone()),
+            Some(_) => {}
+            None => return format!("Unknown map `{}`", map_name),
+        }
+    }
+    set_season_maps(conn, season.id, &pool);
+    if pool.is_empty() {
+        format!("`{}` uses every map", season.name)
     } else {
-        return String::from("Cannot parse match id input");
+        format!("`{}` map pool: `{}`", season.name, pool.join("`, `"))
+    }
+}
+
+pub(crate) async fn handle_settings(
+    context: &Context,
+    msg: &ApplicationCommandInteraction,
+) -> String {
+    let admin_check = admin_check(context, msg).await;
+    if let Err(error) = admin_check {
+        return error;
+    }
+    let conn = get_pg_conn(context).await;
+    let mut settings = get_guild_settings(&conn, msg.guild_id.unwrap().0 as i64);
+    let mut changed = false;
+    for option in &msg.data.options {
+        match (option.name.as_str(), &option.resolved) {
+            ("confirmveto", Some(CommandDataOptionValue::Boolean(confirm_veto))) => {
+                settings.confirm_veto = *confirm_veto;
+                changed = true;
+            }
+            ("adminchannel", Some(CommandDataOptionValue::Channel(channel))) => {
+                settings.admin_channel_id = Some(channel.id.0 as i64);
+                changed = true;
+            }
+            ("gsltthreshold", Some(CommandDataOptionValue::Integer(threshold))) => {
+                settings.gslt_warn_threshold = i32::try_from(*threshold).unwrap_or(i32::MAX);
+                changed = true;
+            }
+            _ => {}
+        }
+    }
+    if changed {
+        settings = update_guild_settings(&conn, settings);
+    }
+    MessageBuilder::new()
+        .push_line("Guild settings:")
+        .push_line(format!(
+            "- Confirm map bans & picks: `{}`",
+            settings.confirm_veto
+        ))
+        .push_line(match settings.admin_channel_id {
+            Some(channel_id) => format!("- Admin channel: <#{}>", channel_id),
+            None => String::from("- Admin channel: `none`"),
+        })
+        .push_line(format!(
+            "- Warn when free GSLT tokens fall below: `{}`",
+            settings.gslt_warn_threshold
+        ))
+        .build()
+}
+
+pub(crate) async fn handle_captain(
+    context: &Context,
+    msg: &ApplicationCommandInteraction,
+) -> String {
+    let admin_check = admin_check(context, msg).await;
+    if let Err(error) = admin_check {
+        return error;
+    }
+    let mut team_role = None;
+    let mut captain = None;
+    let mut vice_captain = None;
+    for option in &msg.data.options {
+        match (option.name.as_str(), &option.resolved) {
+            ("team", Some(CommandDataOptionValue::Role(role))) => team_role = Some(role),
+            ("captain", Some(CommandDataOptionValue::User(user, _))) => captain = Some(user),
+            ("vicecaptain", Some(CommandDataOptionValue::User(user, _))) => {
+                vice_captain = Some(user)
+            }
+            _ => {}
+        }
+    }
+    let (team_role, captain) = match (team_role, captain) {
+        (Some(team_role), Some(captain)) => (team_role, captain),
+        _ => return String::from("Discord API error"),
+    };
+    let conn = get_pg_conn(context).await;
+    let mut team = get_team(&conn, team_role.id.0 as i64).unwrap_or(Team {
+        role_id: team_role.id.0 as i64,
+        name: team_role.name.clone(),
+        captain_discord_id: None,
+        vice_captain_discord_id: None,
+    });
+    team.name = team_role.name.clone();
+    team.captain_discord_id = Some(captain.id.0 as i64);
+    team.vice_captain_discord_id = vice_captain.map(|u| u.id.0 as i64);
+    update_team(&conn, team);
+    let mut response = MessageBuilder::new();
+    response
+        .mention(captain)
+        .push(" is now the captain of ")
+        .mention(team_role);
+    if let Some(vice_captain) = vice_captain {
+        response
+            .push(", with ")
+            .mention(vice_captain)
+            .push(" as vice-captain");
+    }
+    response.build()
+}
+
+pub(crate) async fn handle_servers(
+    context: &Context,
+    msg: &ApplicationCommandInteraction,
+) -> String {
+    let admin_check = admin_check(context, msg).await;
+    if let Err(error) = admin_check {
+        return error;
+    }
+    let subcommand = match msg.data.options.first() {
+        Some(subcommand) => subcommand,
+        None => return String::from("Discord API error"),
+    };
+    let mut region = None;
+    let mut server_id = None;
+    let mut location = None;
+    let mut capacity = None;
+    for option in &subcommand.options {
+        match (option.name.as_str(), &option.resolved) {
+            ("region", Some(CommandDataOptionValue::String(value))) => region = Some(value),
+            ("serverid", Some(CommandDataOptionValue::String(value))) => server_id = Some(value),
+            ("location", Some(CommandDataOptionValue::String(value))) => {
+                location = Some(value.clone())
+            }
+            ("capacity", Some(CommandDataOptionValue::Integer(value))) => {
+                capacity = i32::try_from(*value).ok()
+            }
+            _ => {}
+        }
+    }
+    let region = match region {
+        Some(region) => region,
+        None => return String::from("Discord API error"),
+    };
+    let conn = get_pg_conn(context).await;
+    match (subcommand.name.as_str(), server_id) {
+        ("add", Some(server_id)) => {
+            update_match_server(
+                &conn,
+                MatchServer {
+                    region_label: region.clone(),
+                    server_id: server_id.clone(),
+                    location,
+                    capacity,
+                    enabled: true,
+                },
+            );
+            format!("Server `{}` saved & enabled", region)
+        }
+        ("remove", _) => match delete_match_server(&conn, region) {
+            0 => format!("Unknown server `{}`", region),
+            _ => format!("Server `{}` removed", region),
+        },
+        ("disable", _) => match set_match_server_enabled(&conn, region, false) {
+            Some(_) => format!(
+                "Server `{}` disabled, use `/servers add` to enable it",
+                region
+            ),
+            None => format!("Unknown server `{}`", region),
+        },
+        _ => String::from("Discord API error"),
+    }
+}
+
+pub(crate) async fn handle_gslt(context: &Context, msg: &ApplicationCommandInteraction) -> String {
+    let admin_check = admin_check(context, msg).await;
+    if let Err(error) = admin_check {
+        return error;
+    }
+    let subcommand = match msg.data.options.first() {
+        Some(subcommand) => subcommand,
+        None => return String::from("Discord API error"),
+    };
+    let token = subcommand
+        .options
+        .iter()
+        .find(|o| o.name == "token")
+        .and_then(|o| match &o.resolved {
+            Some(CommandDataOptionValue::String(token)) => Some(token.trim()),
+            _ => None,
+        });
+    let keyring = get_keyring(context).await;
+    let conn = get_pg_conn(context).await;
+    let response = match (subcommand.name.as_str(), token) {
+        ("list", _) => {
+            let tokens = get_tokens(&conn, &keyring);
+            if tokens.is_empty() {
+                return String::from("The GSLT pool is empty, add tokens with `/gslt add`");
+            }
+            let mut response = MessageBuilder::new();
+            response.push_line(format!(
+                "{} of {} GSLT tokens free:",
+                count_free_tokens(&conn),
+                tokens.len()
+            ));
+            for token in tokens {
+                response.push(format!("- `{}`", token.masked()));
+                match (token.in_use, token.match_id, token.assigned_at) {
+                    (false, _, _) => response.push_line(" free"),
+                    (true, Some(match_id), Some(assigned_at)) => response.push_line(format!(
+                        " match {} since {}",
+                        match_id,
+                        assigned_at.format("%Y-%m-%d %H:%M")
+                    )),
+                    (true, _, _) => response.push_line(" in use"),
+                };
+            }
+            return response.build();
+        }
+        ("add", Some(token)) => match add_token(&conn, &keyring, token) {
+            true => String::from("Token added to the GSLT pool"),
+            false => String::from("Token already is in the GSLT pool"),
+        },
+        ("remove", Some(token)) => match delete_token(&conn, token) {
+            0 => String::from("Unknown GSLT token"),
+            _ => String::from("Token removed from the GSLT pool"),
+        },
+        ("release", Some(token)) => match get_token(&conn, &keyring, token) {
+            Some(mut token) => {
+                token.in_use = false;
+                token.match_id = None;
+                token.assigned_at = None;
+                update_token(&conn, token);
+                String::from("Token released")
+            }
+            None => String::from("Unknown GSLT token"),
+        },
+        _ => return String::from("Discord API error"),
+    };
+    drop(conn);
+    warn_low_gslt_pool(context, msg.guild_id.unwrap()).await;
+    response
+}
+
+pub(crate) async fn handle_dathost(
+    context: &Context,
+    msg: &ApplicationCommandInteraction,
+) -> String {
+    let admin_check = admin_check(context, msg).await;
+    if let Err(error) = admin_check {
+        return error;
+    }
+    let mut user = None;
+    let mut password = None;
+    for option in &msg.data.options {
+        match (option.name.as_str(), &option.resolved) {
+            ("user", Some(CommandDataOptionValue::String(value))) => user = Some(value),
+            ("password", Some(CommandDataOptionValue::String(value))) => password = Some(value),
+            _ => {}
+        }
+    }
+    let (user, password) = match (user, password) {
+        (Some(user), Some(password)) => (user, password),
+        _ => return String::from("Discord API error"),
+    };
+    let keyring = get_keyring(context).await;
+    let conn = get_pg_conn(context).await;
+    update_provider_credentials(
+        &conn,
+        &keyring,
+        ProviderCredentials {
+            guild_id: msg.guild_id.unwrap().0 as i64,
+            dathost_user: user.clone(),
+            dathost_password: password.clone(),
+        },
+    );
+    format!("Match servers now run on the Dathost account `{}`", user)
+}
+
+/// Player or team stats, summed over every recorded map
+pub(crate) async fn handle_stats(context: &Context, msg: &ApplicationCommandInteraction) -> String {
+    let mut user = &msg.user;
+    let mut team_role = None;
+    let mut season_name = None;
+    for option in &msg.data.options {
+        match (option.name.as_str(), &option.resolved) {
+            ("user", Some(CommandDataOptionValue::User(u, _))) => user = u,
+            ("team", Some(CommandDataOptionValue::Role(role))) => team_role = Some(role),
+            ("season", Some(CommandDataOptionValue::String(name))) => {
+                season_name = Some(name.trim())
+            }
+            _ => {}
+        }
+    }
+    let season = match resolve_scope(context, msg, season_name, None, true).await {
+        Ok((season, _)) => season,
+        Err(err) => return err,
+    };
+    let scope = print_scope(season.as_ref(), None);
+    let season_id = season.map(|s| s.id);
+    let conn = get_pg_conn(context).await;
+    if let Some(team_role) = team_role {
+        let stats = get_team_player_stats(&conn, team_role.id.0 as i64, season_id);
+        if stats.is_empty() {
+            return MessageBuilder::new()
+                .push("No stats recorded for ")
+                .mention(team_role)
+                .push(format!(" in {} yet", scope))
+                .build();
+        }
+        let discord_ids: HashMap<String, i64> = get_users(&conn)
+            .into_iter()
+            .map(|u| (u.steam_id, u.discord_id))
+            .collect();
+        let mut players: HashMap<&str, Vec<&PlayerMapStats>> = HashMap::new();
+        for s in &stats {
+            players.entry(s.steam_id.as_str()).or_default().push(s);
+        }
+        let mut players: Vec<(&str, PlayerStatsTotals)> = players
+            .into_iter()
+            .map(|(steam_id, s)| (steam_id, s.into_iter().collect()))
+            .collect();
+        players.sort_by(|a, b| b.1.adr().total_cmp(&a.1.adr()));
+        let maps: HashSet<i32> = stats.iter().map(|s| s.series_map_id).collect();
+        let mut response = MessageBuilder::new();
+        response.push("Stats for ").mention(team_role).push(format!(
+            " in {} over {} maps\n",
+            scope,
+            maps.len()
+        ));
+        for (steam_id, totals) in players {
+            match discord_ids.get(steam_id) {
+                Some(discord_id) => response.push(format!("<@{}>", discord_id)),
+                None => response.push_mono_safe(steam_id),
+            };
+            response.push(format!(" - {}\n", print_player_stats(&totals)));
+        }
+        return response.build();
+    }
+    let steam_id = match find_user_by_discord_id(&conn, user.id.0 as i64) {
+        Some(u) => u.steam_id,
+        None => {
+            return MessageBuilder::new()
+                .mention(user)
+                .push(" has not set a Steam ID, use `/steamid` to set one")
+                .build();
+        }
+    };
+    let totals: PlayerStatsTotals = get_player_stats(&conn, &steam_id, season_id)
+        .iter()
+        .collect();
+    if totals.maps == 0 {
+        return MessageBuilder::new()
+            .push("No stats recorded for ")
+            .mention(user)
+            .push(format!(" in {} yet", scope))
+            .build();
+    }
+    MessageBuilder::new()
+        .push("Stats for ")
+        .mention(user)
+        .push(format!(
+            " in {} over {} maps\n{}",
+            scope,
+            totals.maps,
+            print_player_stats(&totals)
+        ))
+        .build()
+}
+
+pub(crate) async fn handle_demo(context: &Context, msg: &ApplicationCommandInteraction) -> String {
+    let mut match_id = None;
+    let mut map = None;
+    for option in &msg.data.options {
+        match (option.name.as_str(), &option.resolved) {
+            ("matchid", Some(CommandDataOptionValue::Integer(id))) => match_id = Some(*id),
+            ("map", Some(CommandDataOptionValue::String(name))) => map = Some(name),
+            _ => {}
+        }
+    }
+    let match_id = match match_id.and_then(|id| i32::try_from(id).ok()) {
+        Some(match_id) => match_id,
+        None => return String::from("Discord API error"),
+    };
+    let config = get_config(context).await;
+    let storage = match config.demos.as_ref().map(demo_storage) {
+        Some(Ok(storage)) => storage,
+        Some(Err(err)) => {
+            error!(error = %err, "unable to open demo storage");
+            return String::from("Demo storage is unavailable, please try again later");
+        }
+        None => return String::from("Demo archival is not enabled"),
+    };
+    let conn = get_pg_conn(context).await;
+    let demos: Vec<_> = get_match_demos(&conn, match_id)
+        .into_iter()
+        .filter(|(series_map, _)| match map {
+            Some(map) => series_map.map.eq_ignore_ascii_case(map.trim()),
+            None => true,
+        })
+        .collect();
+    if demos.is_empty() {
+        return format!("No demos archived for match #{} yet", match_id);
+    }
+    let mut response = format!("Demos for match #{}:\n", match_id);
+    for (series_map, demo) in demos {
+        match storage.url(&demo.location).await {
+            Ok(url) => response
+                .push_str(format!("**{}**: {}\n", series_map.map.to_lowercase(), url).as_str()),
+            Err(err) => {
+                error!(location = %demo.location, error = %err, "unable to link demo");
+                response.push_str(
+                    format!("**{}**: _unavailable_\n", series_map.map.to_lowercase()).as_str(),
+                )
+            }
+        }
     }
-    String::from("Successfully deleted match")
+    response
 }
 
 pub(crate) async fn handle_steam_id(
@@ -587,7 +1653,7 @@ pub(crate) async fn handle_steam_id(
     let option = inc_command
         .data
         .options
-        .get(0)
+        .first()
         .expect("Expected steamid option")
         .resolved
         .as_ref()
@@ -609,7 +1675,7 @@ pub(crate) async fn handle_steam_id(
             .push("Updated steamid for ")
             .mention(&inc_command.user)
             .push(" to `")
-            .push(&steamid)
+            .push(steamid)
             .push("`\n")
             .push_line("Your steam community profile (please double check this is correct):")
             .push_line(format!(
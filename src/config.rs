@@ -0,0 +1,549 @@
+use crate::crypto::Keyring;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serenity::prelude::TypeMapKey;
+use std::env;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+
+/// Env var pointing to the config file, `--config <path>` takes precedence
+const CONFIG_FILE_ENV: &str = "CONFIG_FILE";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Config {
+    pub discord: DiscordConfig,
+    /// Game server provider match servers run on
+    #[serde(default)]
+    pub provider: Provider,
+    /// Dathost account of guilds without their own, set with `/dathost`
+    pub dathost: Option<DathostConfig>,
+    pub demos: Option<DemoConfig>,
+    pub webhooks: Option<WebhookConfig>,
+    /// Shorten server links with TinyURL when the embedded HTTP server is disabled
+    #[serde(default)]
+    pub tinyurl_fallback: bool,
+    pub metrics: Option<MetricsConfig>,
+    #[serde(default)]
+    pub timers: TimersConfig,
+    /// Comma separated `<id>:<base64 key>` entries secrets are encrypted with, newest key first
+    #[serde(default)]
+    pub secret_keys: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DiscordConfig {
+    pub token: String,
+    pub admin_role_id: u64,
+    pub captain_role_id: Option<u64>,
+    pub application_id: u64,
+    pub guild_id: u64,
+    /// Roles whose name starts with the prefix are teams
+    #[serde(default = "default_team_role_prefix")]
+    pub team_role_prefix: String,
+    /// Channel admin warnings are posted in for guilds that did not set one with `/settings`
+    pub admin_channel_id: Option<u64>,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    #[default]
+    Dathost,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DathostConfig {
+    pub user: String,
+    pub password: String,
+}
+
+/// Embedded HTTP server receiving match events, matches are only polled without one
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    #[serde(default = "default_webhook_bind_addr")]
+    pub bind_addr: String,
+    pub public_url: String,
+}
+
+/// Prometheus metrics endpoint, served on its own address so it can be kept private
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    pub bind_addr: String,
+}
+
+/// Where finished-map demos are archived, demos are not archived without one
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum DemoConfig {
+    Local {
+        dir: String,
+        /// Url the demo dir is served from, demos are linked from it
+        #[serde(default)]
+        public_url: String,
+    },
+    S3 {
+        bucket: String,
+        region: String,
+        endpoint: String,
+        access_key: String,
+        secret_key: String,
+    },
+}
+
+/// Polling & timeout intervals, in seconds
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimersConfig {
+    /// Between Dathost polls of matches that do not send webhook events
+    pub poll_interval: u64,
+    /// Between fallback polls of matches that send webhook events
+    pub webhook_poll_interval: u64,
+    /// Until the server connect info button is removed
+    pub conn_info_timeout: u64,
+    /// Between attempts to download a finished map's demo
+    pub demo_retry_interval: u64,
+}
+
+impl Default for TimersConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: 15,
+            webhook_poll_interval: 60,
+            conn_info_timeout: 60 * 60 * 5,
+            demo_retry_interval: 60,
+        }
+    }
+}
+
+impl TimersConfig {
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval)
+    }
+
+    pub fn webhook_poll_interval(&self) -> Duration {
+        Duration::from_secs(self.webhook_poll_interval)
+    }
+
+    pub fn conn_info_timeout(&self) -> Duration {
+        Duration::from_secs(self.conn_info_timeout)
+    }
+
+    pub fn demo_retry_interval(&self) -> Duration {
+        Duration::from_secs(self.demo_retry_interval)
+    }
+}
+
+fn default_team_role_prefix() -> String {
+    String::from("Team")
+}
+
+fn default_webhook_bind_addr() -> String {
+    String::from("0.0.0.0:8080")
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(String, std::io::Error),
+    Parse(String, String),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, err) => write!(f, "unable to read '{}': {}", path, err),
+            ConfigError::Parse(source, err) => write!(f, "invalid {}: {}", source, err),
+            ConfigError::Invalid(errors) => {
+                writeln!(f, "invalid config:")?;
+                for error in errors {
+                    writeln!(f, "  - {}", error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Env vars overriding config file values, with the path of the value they override. Vars marked
+/// `Within` only apply when their section is configured, in the file or by an earlier var
+const ENV_OVERRIDES: [(&str, &[&str], EnvKind, EnvScope); 23] = [
+    (
+        "DISCORD_TOKEN",
+        &["discord", "token"],
+        EnvKind::String,
+        EnvScope::Enables,
+    ),
+    (
+        "DISCORD_ADMIN_ROLE_ID",
+        &["discord", "admin_role_id"],
+        EnvKind::Integer,
+        EnvScope::Enables,
+    ),
+    (
+        "DISCORD_CAPTAIN_ROLE_ID",
+        &["discord", "captain_role_id"],
+        EnvKind::Integer,
+        EnvScope::Enables,
+    ),
+    (
+        "DISCORD_APPLICATION_ID",
+        &["discord", "application_id"],
+        EnvKind::Integer,
+        EnvScope::Enables,
+    ),
+    (
+        "DISCORD_GUILD_ID",
+        &["discord", "guild_id"],
+        EnvKind::Integer,
+        EnvScope::Enables,
+    ),
+    (
+        "DISCORD_TEAM_ROLE_PREFIX",
+        &["discord", "team_role_prefix"],
+        EnvKind::String,
+        EnvScope::Enables,
+    ),
+    (
+        "DISCORD_ADMIN_CHANNEL_ID",
+        &["discord", "admin_channel_id"],
+        EnvKind::Integer,
+        EnvScope::Enables,
+    ),
+    (
+        "PROVIDER",
+        &["provider"],
+        EnvKind::String,
+        EnvScope::Enables,
+    ),
+    (
+        "DATHOST_USER",
+        &["dathost", "user"],
+        EnvKind::String,
+        EnvScope::Enables,
+    ),
+    (
+        "DATHOST_PASSWORD",
+        &["dathost", "password"],
+        EnvKind::String,
+        EnvScope::Within,
+    ),
+    (
+        "DEMO_STORAGE",
+        &["demos", "backend"],
+        EnvKind::String,
+        EnvScope::Enables,
+    ),
+    (
+        "DEMO_DIR",
+        &["demos", "dir"],
+        EnvKind::String,
+        EnvScope::Within,
+    ),
+    (
+        "DEMO_PUBLIC_URL",
+        &["demos", "public_url"],
+        EnvKind::String,
+        EnvScope::Within,
+    ),
+    (
+        "DEMO_S3_BUCKET",
+        &["demos", "bucket"],
+        EnvKind::String,
+        EnvScope::Within,
+    ),
+    (
+        "DEMO_S3_REGION",
+        &["demos", "region"],
+        EnvKind::String,
+        EnvScope::Within,
+    ),
+    (
+        "DEMO_S3_ENDPOINT",
+        &["demos", "endpoint"],
+        EnvKind::String,
+        EnvScope::Within,
+    ),
+    (
+        "DEMO_S3_ACCESS_KEY",
+        &["demos", "access_key"],
+        EnvKind::String,
+        EnvScope::Within,
+    ),
+    (
+        "DEMO_S3_SECRET_KEY",
+        &["demos", "secret_key"],
+        EnvKind::String,
+        EnvScope::Within,
+    ),
+    (
+        "WEBHOOK_PUBLIC_URL",
+        &["webhooks", "public_url"],
+        EnvKind::String,
+        EnvScope::Enables,
+    ),
+    (
+        "WEBHOOK_BIND_ADDR",
+        &["webhooks", "bind_addr"],
+        EnvKind::String,
+        EnvScope::Within,
+    ),
+    (
+        "TINYURL_FALLBACK",
+        &["tinyurl_fallback"],
+        EnvKind::Bool,
+        EnvScope::Enables,
+    ),
+    (
+        "METRICS_BIND_ADDR",
+        &["metrics", "bind_addr"],
+        EnvKind::String,
+        EnvScope::Enables,
+    ),
+    (
+        "SECRET_KEYS",
+        &["secret_keys"],
+        EnvKind::String,
+        EnvScope::Enables,
+    ),
+];
+
+#[derive(Clone, Copy)]
+enum EnvKind {
+    String,
+    Integer,
+    Bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EnvScope {
+    Enables,
+    Within,
+}
+
+/// Loads the config file given with `--config` or `CONFIG_FILE`, if any, overrides its values
+/// with env vars & validates the result
+pub fn load_config() -> Result<Config, ConfigError> {
+    load_config_from(config_path().as_deref(), |var| env::var(var).ok())
+}
+
+/// Loads the config file at `path`, if any, overrides its values with the env vars `env_var`
+/// looks up & validates the result
+pub fn load_config_from(
+    path: Option<&str>,
+    env_var: impl Fn(&str) -> Option<String>,
+) -> Result<Config, ConfigError> {
+    let value = load_config_value(path, env_var)?;
+    let config: Config = serde_path_to_error::deserialize(value)
+        .map_err(|err| ConfigError::Parse(String::from("config"), err.to_string()))?;
+    let errors = config.validate();
+    if !errors.is_empty() {
+        return Err(ConfigError::Invalid(errors));
+    }
+    Ok(config)
+}
+
+/// Loads only the secret keys from the config file & env vars, for tools that don't need the
+/// rest of the config
+pub fn load_keyring() -> Result<Keyring, ConfigError> {
+    load_keyring_from(config_path().as_deref(), |var| env::var(var).ok())
+}
+
+/// Loads the secret keys from the config file at `path`, if any, or the env vars `env_var` looks
+/// up
+pub fn load_keyring_from(
+    path: Option<&str>,
+    env_var: impl Fn(&str) -> Option<String>,
+) -> Result<Keyring, ConfigError> {
+    let value = load_config_value(path, env_var)?;
+    let keys = value
+        .get("secret_keys")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    Keyring::parse(keys).map_err(|err| ConfigError::Invalid(vec![secret_keys_error(err)]))
+}
+
+/// The config file at `path`, if any, with the env vars `env_var` looks up applied
+fn load_config_value(
+    path: Option<&str>,
+    env_var: impl Fn(&str) -> Option<String>,
+) -> Result<Value, ConfigError> {
+    let mut value = match path {
+        Some(path) => read_config_file(path)?,
+        None => Value::Object(Map::new()),
+    };
+    for (var, path, kind, scope) in ENV_OVERRIDES {
+        if let Some(env_value) = env_var(var) {
+            if scope == EnvScope::Within && value.get(path[0]).is_none_or(Value::is_null) {
+                continue;
+            }
+            set_path(&mut value, path, env_value_of(var, &env_value, kind)?);
+        }
+    }
+    Ok(value)
+}
+
+fn secret_keys_error(err: impl fmt::Display) -> String {
+    format!("secret_keys is invalid: {}", err)
+}
+
+fn config_path() -> Option<String> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next();
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(String::from(path));
+        }
+    }
+    env::var(CONFIG_FILE_ENV).ok()
+}
+
+fn read_config_file(path: &str) -> Result<Value, ConfigError> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|err| ConfigError::Read(String::from(path), err))?;
+    let parsed = match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str::<Value>(&contents).map_err(|err| err.to_string()),
+        _ => serde_yaml::from_str::<Value>(&contents).map_err(|err| err.to_string()),
+    };
+    match parsed {
+        // an empty yaml file parses as null
+        Ok(Value::Null) => Ok(Value::Object(Map::new())),
+        Ok(value) => Ok(value),
+        Err(err) => Err(ConfigError::Parse(String::from(path), err)),
+    }
+}
+
+fn env_value_of(var: &str, value: &str, kind: EnvKind) -> Result<Value, ConfigError> {
+    let invalid = |expected: &str| {
+        ConfigError::Parse(
+            String::from(var),
+            format!("expected {}, got '{}'", expected, value),
+        )
+    };
+    match kind {
+        EnvKind::String => Ok(Value::from(value)),
+        EnvKind::Integer => value
+            .trim()
+            .parse::<u64>()
+            .map(Value::from)
+            .map_err(|_| invalid("an integer")),
+        EnvKind::Bool => value
+            .trim()
+            .parse::<bool>()
+            .map(Value::from)
+            .map_err(|_| invalid("true or false")),
+    }
+}
+
+fn set_path(value: &mut Value, path: &[&str], new_value: Value) {
+    let mut current = value;
+    for key in path {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        current = current
+            .as_object_mut()
+            .unwrap()
+            .entry(*key)
+            .or_insert(Value::Null);
+    }
+    *current = new_value;
+}
+
+impl Config {
+    /// Every problem with the config, so they can all be fixed at once
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut require = |ok: bool, error: &str| {
+            if !ok {
+                errors.push(String::from(error));
+            }
+        };
+        require(
+            !self.discord.token.trim().is_empty(),
+            "discord.token is empty",
+        );
+        require(
+            self.discord.admin_role_id != 0,
+            "discord.admin_role_id is 0",
+        );
+        require(
+            self.discord.application_id != 0,
+            "discord.application_id is 0",
+        );
+        require(self.discord.guild_id != 0, "discord.guild_id is 0");
+        require(
+            !self.discord.team_role_prefix.is_empty(),
+            "discord.team_role_prefix is empty",
+        );
+        if let Some(dathost) = &self.dathost {
+            require(!dathost.user.is_empty(), "dathost.user is empty");
+            require(!dathost.password.is_empty(), "dathost.password is empty");
+        }
+        if let Some(webhooks) = &self.webhooks {
+            require(
+                webhooks.bind_addr.parse::<SocketAddr>().is_ok(),
+                "webhooks.bind_addr is not a socket address, i.e. 0.0.0.0:8080",
+            );
+            require(
+                webhooks.public_url.starts_with("http://")
+                    || webhooks.public_url.starts_with("https://"),
+                "webhooks.public_url is not an http(s) url",
+            );
+        }
+        if let Some(metrics) = &self.metrics {
+            require(
+                metrics.bind_addr.parse::<SocketAddr>().is_ok(),
+                "metrics.bind_addr is not a socket address, i.e. 127.0.0.1:9090",
+            );
+        }
+        match &self.demos {
+            Some(DemoConfig::Local { dir, public_url }) => {
+                require(!dir.is_empty(), "demos.dir is empty");
+                require(
+                    public_url.starts_with("http://") || public_url.starts_with("https://"),
+                    "demos.public_url is not an http(s) url",
+                );
+            }
+            Some(DemoConfig::S3 {
+                bucket, endpoint, ..
+            }) => {
+                require(!bucket.is_empty(), "demos.bucket is empty");
+                require(!endpoint.is_empty(), "demos.endpoint is empty");
+            }
+            None => {}
+        }
+        for (name, secs) in [
+            ("timers.poll_interval", self.timers.poll_interval),
+            (
+                "timers.webhook_poll_interval",
+                self.timers.webhook_poll_interval,
+            ),
+            ("timers.conn_info_timeout", self.timers.conn_info_timeout),
+            (
+                "timers.demo_retry_interval",
+                self.timers.demo_retry_interval,
+            ),
+        ] {
+            require(secs > 0, &format!("{} must be at least 1 second", name));
+        }
+        if let Err(err) = Keyring::parse(&self.secret_keys) {
+            errors.push(secret_keys_error(err));
+        }
+        errors
+    }
+
+    /// Keys secrets are encrypted with, only call on a validated config
+    pub fn keyring(&self) -> Keyring {
+        Keyring::parse(&self.secret_keys).expect("secret_keys are validated")
+    }
+}
+
+impl TypeMapKey for Config {
+    type Value = Config;
+}
@@ -0,0 +1,133 @@
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{AeadCore, Aes256Gcm, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Prefix of encrypted values, followed by the id of the key & the base64 nonce & ciphertext
+const ENCRYPTED_PREFIX: &str = "enc";
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CryptoError {
+    NoKeys,
+    InvalidKey(String),
+    UnknownKey(String),
+    Malformed,
+    Decrypt,
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoError::NoKeys => write!(f, "no secret keys given"),
+            CryptoError::InvalidKey(id) => {
+                write!(f, "secret key '{}' is not a base64 encoded 32 byte key", id)
+            }
+            CryptoError::UnknownKey(id) => write!(f, "secret key '{}' is not configured", id),
+            CryptoError::Malformed => write!(f, "encrypted secret is malformed"),
+            CryptoError::Decrypt => write!(f, "unable to decrypt secret"),
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+/// Keys secret columns are encrypted with, the first key encrypts & every key decrypts so keys
+/// can be rotated by adding a new key in front of the old ones
+pub struct Keyring {
+    keys: Vec<(String, Aes256Gcm)>,
+}
+
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ids: Vec<&str> = self.keys.iter().map(|(id, _)| id.as_str()).collect();
+        f.debug_struct("Keyring").field("keys", &ids).finish()
+    }
+}
+
+impl Keyring {
+    /// Parses comma separated `<id>:<base64 key>` entries, newest key first
+    pub fn parse(keys: &str) -> Result<Self, CryptoError> {
+        let mut parsed = Vec::new();
+        for entry in keys.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (id, key) = entry
+                .split_once(':')
+                .ok_or_else(|| CryptoError::InvalidKey(String::new()))?;
+            let key = STANDARD
+                .decode(key)
+                .ok()
+                .filter(|key| key.len() == KEY_LENGTH)
+                .ok_or_else(|| CryptoError::InvalidKey(String::from(id)))?;
+            let cipher = Aes256Gcm::new_from_slice(&key)
+                .map_err(|_| CryptoError::InvalidKey(String::from(id)))?;
+            parsed.push((String::from(id), cipher));
+        }
+        if parsed.is_empty() {
+            return Err(CryptoError::NoKeys);
+        }
+        Ok(Self { keys: parsed })
+    }
+
+    /// Encrypts the secret with the current key
+    pub fn encrypt(&self, secret: &str) -> String {
+        let (id, cipher) = &self.keys[0];
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let mut sealed = nonce.to_vec();
+        sealed.extend(
+            cipher
+                .encrypt(&nonce, secret.as_bytes())
+                .expect("Expected secret to encrypt"),
+        );
+        format!("{}:{}:{}", ENCRYPTED_PREFIX, id, STANDARD.encode(sealed))
+    }
+
+    pub fn decrypt(&self, value: &str) -> Result<String, CryptoError> {
+        let (id, sealed) = match value.split(':').collect::<Vec<_>>()[..] {
+            [ENCRYPTED_PREFIX, id, sealed] => (id, sealed),
+            _ => return Err(CryptoError::Malformed),
+        };
+        let (_, cipher) = self
+            .keys
+            .iter()
+            .find(|(key_id, _)| key_id == id)
+            .ok_or_else(|| CryptoError::UnknownKey(String::from(id)))?;
+        let sealed = STANDARD
+            .decode(sealed)
+            .map_err(|_| CryptoError::Malformed)?;
+        if sealed.len() < NONCE_LENGTH {
+            return Err(CryptoError::Malformed);
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+        let secret = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| CryptoError::Decrypt)?;
+        String::from_utf8(secret).map_err(|_| CryptoError::Decrypt)
+    }
+
+    /// Checks if the value is plaintext or was encrypted with an older key
+    pub fn needs_reencryption(&self, value: &str) -> bool {
+        !value.starts_with(&format!("{}:{}:", ENCRYPTED_PREFIX, self.keys[0].0))
+    }
+
+    /// Encrypts the value with the current key, values stored before encryption was introduced
+    /// are taken as plaintext
+    pub fn reencrypt(&self, value: &str) -> Result<String, CryptoError> {
+        let secret = if value.starts_with(&format!("{}:", ENCRYPTED_PREFIX)) {
+            self.decrypt(value)?
+        } else {
+            String::from(value)
+        };
+        Ok(self.encrypt(&secret))
+    }
+}
+
+/// Hex SHA-256 of the secret, used to look up encrypted secrets
+pub fn secret_hash(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
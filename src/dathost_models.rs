@@ -16,3 +16,44 @@ pub struct Ports {
     pub game: i64,
     pub gotv: i64,
 }
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DathostMatch {
+    pub id: String,
+    pub game_server_id: String,
+    pub map: String,
+    pub started: bool,
+    pub finished: bool,
+    pub cancel_reason: Option<String>,
+    pub rounds_played: i64,
+    pub team1_stats: DathostTeamStats,
+    pub team2_stats: DathostTeamStats,
+    pub player_stats: Vec<DathostPlayerStats>,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DathostTeamStats {
+    pub score: i64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DathostPlayerStats {
+    pub steam_id: String,
+    pub kills: i32,
+    pub deaths: i32,
+    pub assists: i32,
+    pub kills_with_headshot: i32,
+    pub damage_dealt: i32,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DathostMatchSeries {
+    pub id: String,
+    pub finished: bool,
+    pub cancel_reason: Option<String>,
+    pub matches: Vec<DathostMatch>,
+}
@@ -0,0 +1,227 @@
+use crate::metrics::Metrics;
+use crate::utils::{get_config, get_metrics, get_pg_conn};
+use chrono::Utc;
+use csgo_matchbot::config::{DathostConfig, DemoConfig};
+use csgo_matchbot::models::{Demo, SeriesMap};
+use csgo_matchbot::update_demo;
+use reqwest::Client;
+use s3::creds::Credentials;
+use s3::error::S3Error;
+use s3::{Bucket, Region};
+use serenity::async_trait;
+use serenity::prelude::Context;
+use std::fmt;
+use std::path::PathBuf;
+use tracing::{debug, error, info, instrument, warn};
+use urlencoding::encode;
+
+/// GOTV keeps writing the demo for `tv_delay` after the map ends, so downloads are retried
+const DEMO_DOWNLOAD_ATTEMPTS: u32 = 10;
+const DEMO_URL_EXPIRY_SECS: u32 = 60 * 60 * 24;
+
+#[derive(Debug)]
+pub enum DemoStorageError {
+    Io(std::io::Error),
+    S3(S3Error),
+    Status(u16),
+}
+
+impl fmt::Display for DemoStorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DemoStorageError::Io(err) => write!(f, "demo storage io error: {}", err),
+            DemoStorageError::S3(err) => write!(f, "demo storage s3 error: {}", err),
+            DemoStorageError::Status(status) => {
+                write!(f, "demo storage returned status {}", status)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DemoStorageError {}
+
+impl From<std::io::Error> for DemoStorageError {
+    fn from(err: std::io::Error) -> Self {
+        DemoStorageError::Io(err)
+    }
+}
+
+impl From<S3Error> for DemoStorageError {
+    fn from(err: S3Error) -> Self {
+        DemoStorageError::S3(err)
+    }
+}
+
+/// Backend archived demos are stored in
+#[async_trait]
+pub trait DemoStorage: Send + Sync {
+    /// Stores the demo under `key`, returning the location to link it to its series map with
+    async fn store(&self, key: &str, demo: &[u8]) -> Result<String, DemoStorageError>;
+
+    /// Link the stored demo can be downloaded from
+    async fn url(&self, location: &str) -> Result<String, DemoStorageError>;
+}
+
+pub struct LocalDemoStorage {
+    dir: PathBuf,
+    public_url: String,
+}
+
+#[async_trait]
+impl DemoStorage for LocalDemoStorage {
+    async fn store(&self, key: &str, demo: &[u8]) -> Result<String, DemoStorageError> {
+        let path = self.dir.join(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, demo).await?;
+        Ok(String::from(key))
+    }
+
+    async fn url(&self, location: &str) -> Result<String, DemoStorageError> {
+        Ok(format!(
+            "{}/{}",
+            self.public_url.trim_end_matches('/'),
+            location
+        ))
+    }
+}
+
+/// S3-compatible object storage, demos are shared through presigned links
+pub struct S3DemoStorage {
+    bucket: Bucket,
+}
+
+#[async_trait]
+impl DemoStorage for S3DemoStorage {
+    async fn store(&self, key: &str, demo: &[u8]) -> Result<String, DemoStorageError> {
+        let resp = self.bucket.put_object(key, demo).await?;
+        if resp.status_code() != 200 {
+            return Err(DemoStorageError::Status(resp.status_code()));
+        }
+        Ok(String::from(key))
+    }
+
+    async fn url(&self, location: &str) -> Result<String, DemoStorageError> {
+        Ok(self
+            .bucket
+            .presign_get(location, DEMO_URL_EXPIRY_SECS, None)?)
+    }
+}
+
+pub fn demo_storage(config: &DemoConfig) -> Result<Box<dyn DemoStorage>, DemoStorageError> {
+    match config {
+        DemoConfig::Local { dir, public_url } => Ok(Box::new(LocalDemoStorage {
+            dir: PathBuf::from(dir),
+            public_url: public_url.clone(),
+        })),
+        DemoConfig::S3 {
+            bucket,
+            region,
+            endpoint,
+            access_key,
+            secret_key,
+        } => {
+            let credentials =
+                Credentials::new(Some(access_key), Some(secret_key), None, None, None)
+                    .map_err(|err| DemoStorageError::S3(S3Error::Credentials(err)))?;
+            let region = Region::Custom {
+                region: region.clone(),
+                endpoint: endpoint.clone(),
+            };
+            let bucket = Bucket::new(bucket, region, credentials)?.with_path_style();
+            Ok(Box::new(S3DemoStorage { bucket }))
+        }
+    }
+}
+
+/// Downloads the map's demo from the game server once GOTV is done writing it & archives it
+#[instrument(skip_all, fields(series_map_id = series_map.id, map = %series_map.map))]
+pub async fn archive_demo(
+    context: Context,
+    dathost_config: DathostConfig,
+    series_map: SeriesMap,
+    game_server_id: String,
+    dathost_match_id: String,
+) {
+    let config = get_config(&context).await;
+    let storage = match config.demos.as_ref().map(demo_storage) {
+        Some(Ok(storage)) => storage,
+        Some(Err(err)) => {
+            error!(error = %err, "unable to open demo storage");
+            return;
+        }
+        None => return,
+    };
+    let client = Client::new();
+    let metrics = get_metrics(&context).await;
+    for attempt in 1..=DEMO_DOWNLOAD_ATTEMPTS {
+        tokio::time::sleep(config.timers.demo_retry_interval()).await;
+        let demo = match download_demo(
+            &client,
+            &metrics,
+            &dathost_config,
+            &game_server_id,
+            &dathost_match_id,
+        )
+        .await
+        {
+            Ok(demo) => demo,
+            Err(err) => {
+                debug!(attempt, error = %err, "demo not available yet");
+                continue;
+            }
+        };
+        let key = format!(
+            "match-{}/{}-{}.dem",
+            series_map.match_id,
+            series_map.id,
+            series_map.map.to_lowercase()
+        );
+        match storage.store(&key, &demo).await {
+            Ok(location) => {
+                let conn = get_pg_conn(&context).await;
+                update_demo(
+                    &conn,
+                    Demo {
+                        series_map_id: series_map.id,
+                        location,
+                        size_bytes: demo.len() as i64,
+                        archived_at: Utc::now().naive_utc(),
+                    },
+                );
+                info!(key = %key, size_bytes = demo.len(), "archived demo");
+            }
+            Err(err) => error!(key = %key, error = %err, "unable to store demo"),
+        }
+        return;
+    }
+    warn!("giving up on demo");
+}
+
+/// Dathost saves match demos in the game server's root, named after the match
+async fn download_demo(
+    client: &Client,
+    metrics: &Metrics,
+    dathost_config: &DathostConfig,
+    game_server_id: &str,
+    dathost_match_id: &str,
+) -> Result<Vec<u8>, reqwest::Error> {
+    let demo = metrics
+        .provider_request(
+            "download_demo",
+            client
+                .get(format!(
+                    "https://dathost.net/api/0.1/game-servers/{}/files/{}",
+                    encode(game_server_id),
+                    encode(&format!("{}.dem", dathost_match_id))
+                ))
+                .basic_auth(&dathost_config.user, Some(&dathost_config.password))
+                .send(),
+        )
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    Ok(demo.to_vec())
+}
@@ -0,0 +1,337 @@
+//! Season fixtures, generated as a round robin of the registered teams or imported from a file
+
+use crate::models::{MatchState, NewMatch, SeasonTeam, SeriesType, Team};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Match of a season's schedule, team one is the home team
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixture {
+    pub round: i32,
+    pub team_one_role_id: i64,
+    pub team_one_name: String,
+    pub team_two_role_id: i64,
+    pub team_two_name: String,
+    /// Overrides the series type the fixtures are created with
+    pub series_type: Option<SeriesType>,
+    pub deadline: Option<NaiveDateTime>,
+    pub note: Option<String>,
+    /// Division of the season both teams play in
+    pub division_id: Option<i32>,
+}
+
+impl Fixture {
+    fn new(round: i32, home: &Team, away: &Team) -> Self {
+        Fixture {
+            round,
+            team_one_role_id: home.role_id,
+            team_one_name: home.name.clone(),
+            team_two_role_id: away.role_id,
+            team_two_name: away.name.clone(),
+            series_type: None,
+            deadline: None,
+            note: None,
+            division_id: None,
+        }
+    }
+
+    pub fn new_match<'a>(
+        &'a self,
+        series_type: &'a SeriesType,
+        date_added: &'a NaiveDateTime,
+        season_id: Option<i32>,
+    ) -> NewMatch<'a> {
+        NewMatch {
+            team_one_role_id: self.team_one_role_id,
+            team_one_name: &self.team_one_name,
+            team_two_role_id: self.team_two_role_id,
+            team_two_name: &self.team_two_name,
+            note: self.note.as_deref(),
+            series_type: self.series_type.as_ref().unwrap_or(series_type),
+            date_added,
+            match_state: &MatchState::Entered,
+            round: Some(self.round),
+            deadline: self.deadline,
+            season_id,
+            division_id: self.division_id,
+        }
+    }
+}
+
+/// Round robin of the teams using the circle method, every team plays every other team once, or
+/// twice with home & away swapped when `double` is set. Home & away games of a team differ by at
+/// most one per leg, teams sit out a round when their count is odd.
+pub fn round_robin(teams: &[Team], double: bool) -> Vec<Fixture> {
+    let mut circle: Vec<Option<&Team>> = teams.iter().map(Some).collect();
+    if circle.len() % 2 == 1 {
+        // the bye stays in place so that the fixed position alternates home & away evenly
+        circle.insert(0, None);
+    }
+    let size = circle.len();
+    let rounds = size.saturating_sub(1);
+    let mut fixtures = Vec::new();
+    for round in 0..rounds {
+        for i in 0..size / 2 {
+            let (a, b) = match (circle[i], circle[size - 1 - i]) {
+                (Some(a), Some(b)) => (a, b),
+                _ => continue,
+            };
+            let a_home = if i == 0 { round % 2 == 0 } else { i % 2 == 0 };
+            let (home, away) = if a_home { (a, b) } else { (b, a) };
+            fixtures.push(Fixture::new(round as i32 + 1, home, away));
+        }
+        circle[1..].rotate_right(1);
+    }
+    if double {
+        let second_leg: Vec<Fixture> = fixtures
+            .iter()
+            .map(|f| Fixture {
+                round: f.round + rounds as i32,
+                team_one_role_id: f.team_two_role_id,
+                team_one_name: f.team_two_name.clone(),
+                team_two_role_id: f.team_one_role_id,
+                team_two_name: f.team_one_name.clone(),
+                ..f.clone()
+            })
+            .collect();
+        fixtures.extend(second_leg);
+    }
+    fixtures
+}
+
+/// Round robin within each division of the season's teams, teams without a division play each
+/// other. The divisions play their rounds at the same time.
+pub fn division_round_robin(
+    teams: &[Team],
+    season_teams: &[SeasonTeam],
+    double: bool,
+) -> Vec<Fixture> {
+    let mut divisions: Vec<Option<i32>> = season_teams.iter().map(|t| t.division_id).collect();
+    divisions.sort_unstable();
+    divisions.dedup();
+    let mut fixtures = Vec::new();
+    for division_id in divisions {
+        let division_teams: Vec<Team> = teams
+            .iter()
+            .filter(|team| {
+                season_teams
+                    .iter()
+                    .any(|t| t.role_id == team.role_id && t.division_id == division_id)
+            })
+            .cloned()
+            .collect();
+        fixtures.extend(
+            round_robin(&division_teams, double)
+                .into_iter()
+                .map(|f| Fixture { division_id, ..f }),
+        );
+    }
+    fixtures.sort_by_key(|f| f.round);
+    fixtures
+}
+
+/// Sets the division of every fixture whose teams play in the same division of the season
+pub fn assign_divisions(fixtures: &mut [Fixture], season_teams: &[SeasonTeam]) {
+    for fixture in fixtures.iter_mut() {
+        fixture.division_id = SeasonTeam::shared_division(
+            season_teams,
+            fixture.team_one_role_id,
+            fixture.team_two_role_id,
+        );
+    }
+}
+
+/// Deadline of a round when rounds are `days` long starting on `start`, the end of its last day
+pub fn round_deadline(start: NaiveDate, days: i64, round: i32) -> NaiveDateTime {
+    let last_day = start + Duration::days(days * i64::from(round) - 1);
+    last_day.and_time(NaiveTime::from_hms_opt(23, 59, 59).unwrap())
+}
+
+/// Sets the default deadline of every fixture without one
+pub fn fill_deadlines(fixtures: &mut [Fixture], start: NaiveDate, days: i64) {
+    for fixture in fixtures.iter_mut().filter(|f| f.deadline.is_none()) {
+        fixture.deadline = Some(round_deadline(start, days, fixture.round));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureFormat {
+    Csv,
+    Json,
+}
+
+impl FixtureFormat {
+    /// Format of a fixture list by its file extension
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        let ext = Path::new(name).extension()?.to_str()?;
+        if ext.eq_ignore_ascii_case("csv") {
+            Some(FixtureFormat::Csv)
+        } else if ext.eq_ignore_ascii_case("json") {
+            Some(FixtureFormat::Json)
+        } else {
+            None
+        }
+    }
+}
+
+/// Problem with an imported fixture list, rows are counted from the first fixture
+#[derive(Debug, PartialEq, Eq)]
+pub enum FixtureError {
+    Parse(String),
+    Empty,
+    InvalidRound {
+        row: usize,
+    },
+    UnknownTeam {
+        row: usize,
+        team: String,
+    },
+    SameTeams {
+        row: usize,
+    },
+    InvalidSeriesType {
+        row: usize,
+        value: String,
+    },
+    InvalidDeadline {
+        row: usize,
+        value: String,
+    },
+    /// The team has more than one match in the round
+    DoubleBooked {
+        round: i32,
+        team: String,
+    },
+}
+
+impl fmt::Display for FixtureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FixtureError::Parse(err) => write!(f, "cannot read fixtures: {}", err),
+            FixtureError::Empty => write!(f, "the fixture list is empty"),
+            FixtureError::InvalidRound { row } => {
+                write!(f, "row {}: rounds start at 1", row)
+            }
+            FixtureError::UnknownTeam { row, team } => {
+                write!(f, "row {}: '{}' is not a registered team", row, team)
+            }
+            FixtureError::SameTeams { row } => write!(f, "row {}: a team cannot play itself", row),
+            FixtureError::InvalidSeriesType { row, value } => {
+                write!(f, "row {}: unknown series type '{}'", row, value)
+            }
+            FixtureError::InvalidDeadline { row, value } => write!(
+                f,
+                "row {}: deadline '{}' is not `YYYY-MM-DD` or `YYYY-MM-DD HH:MM`",
+                row, value
+            ),
+            FixtureError::DoubleBooked { round, team } => {
+                write!(f, "{} plays more than once in round {}", team, round)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FixtureError {}
+
+/// Fixture as it is imported, teams are role IDs or registered team names. Role IDs are strings
+/// in JSON as well, they do not fit in a JSON number.
+#[derive(Deserialize)]
+struct FixtureRecord {
+    round: i32,
+    team_one: String,
+    team_two: String,
+    #[serde(default)]
+    series_type: Option<String>,
+    #[serde(default)]
+    deadline: Option<String>,
+    #[serde(default)]
+    note: Option<String>,
+}
+
+/// Reads a fixture list with the columns `round`, `team_one`, `team_two` & the optional
+/// `series_type`, `deadline` & `note`, as CSV with a header row or as a JSON array
+pub fn parse_fixtures(
+    contents: &[u8],
+    format: FixtureFormat,
+    teams: &[Team],
+) -> Result<Vec<Fixture>, FixtureError> {
+    let records: Vec<FixtureRecord> = match format {
+        FixtureFormat::Csv => csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(contents)
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .map_err(|err| FixtureError::Parse(err.to_string()))?,
+        FixtureFormat::Json => {
+            serde_json::from_slice(contents).map_err(|err| FixtureError::Parse(err.to_string()))?
+        }
+    };
+    if records.is_empty() {
+        return Err(FixtureError::Empty);
+    }
+    let mut booked = HashSet::new();
+    let mut fixtures = Vec::with_capacity(records.len());
+    for (i, record) in records.into_iter().enumerate() {
+        let row = i + 1;
+        if record.round < 1 {
+            return Err(FixtureError::InvalidRound { row });
+        }
+        let home = find_team(teams, &record.team_one).ok_or(FixtureError::UnknownTeam {
+            row,
+            team: record.team_one.clone(),
+        })?;
+        let away = find_team(teams, &record.team_two).ok_or(FixtureError::UnknownTeam {
+            row,
+            team: record.team_two.clone(),
+        })?;
+        if home.role_id == away.role_id {
+            return Err(FixtureError::SameTeams { row });
+        }
+        for team in [home, away] {
+            if !booked.insert((record.round, team.role_id)) {
+                return Err(FixtureError::DoubleBooked {
+                    round: record.round,
+                    team: team.name.clone(),
+                });
+            }
+        }
+        let mut fixture = Fixture::new(record.round, home, away);
+        if let Some(value) = record.series_type.filter(|s| !s.is_empty()) {
+            fixture.series_type = Some(
+                SeriesType::from_str(&value.to_lowercase())
+                    .map_err(|_| FixtureError::InvalidSeriesType { row, value })?,
+            );
+        }
+        if let Some(value) = record.deadline.filter(|s| !s.is_empty()) {
+            fixture.deadline =
+                Some(parse_deadline(&value).ok_or(FixtureError::InvalidDeadline { row, value })?);
+        }
+        fixture.note = record.note.filter(|s| !s.is_empty());
+        fixtures.push(fixture);
+    }
+    Ok(fixtures)
+}
+
+fn find_team<'a>(teams: &'a [Team], team: &str) -> Option<&'a Team> {
+    let team = team.trim();
+    match team.parse::<i64>() {
+        Ok(role_id) => teams.iter().find(|t| t.role_id == role_id),
+        Err(_) => teams.iter().find(|t| t.name.eq_ignore_ascii_case(team)),
+    }
+}
+
+/// A date & time, or a date whose end is the deadline
+fn parse_deadline(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .map(|date| date.and_time(NaiveTime::from_hms_opt(23, 59, 59).unwrap()))
+        })
+}
@@ -1,28 +1,75 @@
 #[macro_use]
 extern crate diesel;
+#[macro_use]
+extern crate diesel_migrations;
 
 use self::models::{NewUser, User};
+use crate::bracket::{build_bracket, report_winner, BracketError};
+use crate::crypto::{secret_hash, CryptoError, Keyring};
 use crate::diesel::ExpressionMethods;
 use crate::models::{
-    GsltToken, Map, Match, MatchServer, MatchSetupStep, MatchState, NewMatch, NewMatchSetupStep,
-    NewSeriesMap,
+    Bracket, BracketMatch, BracketTeam, Demo, Division, FieldChange, GsltToken, GuildSettings, Map,
+    Match, MatchChanges, MatchEdit, MatchEditError, MatchScope, MatchServer, MatchSetupStep,
+    MatchState, NewBracket, NewDivision, NewGsltToken, NewMatch, NewMatchEdit, NewMatchSetupStep,
+    NewSeason, NewSeriesMap, PlayerMapStats, ProviderCredentials, ResultError, Season, SeasonMap,
+    SeasonTeam, SeriesMap, Team,
 };
 use crate::schema::gslt_tokens::dsl::gslt_tokens;
-use crate::schema::gslt_tokens::in_use;
+use crate::schema::gslt_tokens::{in_use, token_hash};
+use crate::schema::guild_settings::dsl::guild_settings;
 use crate::schema::maps::dsl::maps;
 use crate::schema::match_servers::dsl::match_servers;
 use crate::schema::matches::dsl::matches;
 use crate::schema::matches::{match_state, scheduled_time_str};
+use crate::schema::teams::dsl::teams;
 use crate::schema::users::dsl::users;
-use crate::MatchState::{Completed, Entered};
+use crate::MatchState::{Archived, Entered};
+use chrono::{NaiveDateTime, Utc};
 use diesel::associations::HasTable;
 use diesel::{
-    BoolExpressionMethods, EqAll, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl,
+    BoolExpressionMethods, Connection, EqAll, NullableExpressionMethods, OptionalExtension,
+    PgConnection, QueryDsl, QueryResult, RunQueryDsl,
 };
+use diesel_migrations::RunMigrationsError;
+use std::io::Write;
 
+pub mod bracket;
+pub mod config;
+pub mod crypto;
+pub mod fixtures;
 pub mod models;
 pub mod schema;
+pub mod standings;
+pub mod veto;
+
+embed_migrations!();
+
+/// Runs the pending migrations embedded at build time, printing each one run to `out`
+pub fn run_migrations(conn: &PgConnection, out: &mut dyn Write) -> Result<(), RunMigrationsError> {
+    embedded_migrations::run_with_output(conn, out)
+}
 
+/// Versions of the embedded migrations the database is missing, found by running them in a
+/// transaction that is always rolled back
+pub fn pending_migrations(conn: &PgConnection) -> Result<Vec<String>, RunMigrationsError> {
+    let mut out = Vec::new();
+    let dry_run = conn.transaction::<(), RunMigrationsError, _>(|| {
+        embedded_migrations::run_with_output(conn, &mut out)?;
+        Err(diesel::result::Error::RollbackTransaction.into())
+    });
+    match dry_run {
+        Err(RunMigrationsError::QueryError(diesel::result::Error::RollbackTransaction))
+        | Ok(()) => {}
+        Err(err) => return Err(err),
+    }
+    Ok(String::from_utf8_lossy(&out)
+        .lines()
+        .filter_map(|line| line.strip_prefix("Running migration "))
+        .map(String::from)
+        .collect())
+}
+
+/// Saves the user's SteamID, replacing the one they set before
 pub fn create_user(conn: &PgConnection, discord_id: i64, steam_id: &str) -> User {
     use schema::users;
 
@@ -33,10 +80,27 @@ pub fn create_user(conn: &PgConnection, discord_id: i64, steam_id: &str) -> User
 
     diesel::insert_into(users::table)
         .values(&new_user)
+        .on_conflict(users::discord_id)
+        .do_update()
+        .set(users::steam_id.eq(steam_id))
         .get_result(conn)
         .expect("Error saving new user")
 }
 
+/// Saves the matches in one insert, i.e. a season's fixtures
+pub fn create_matches(conn: &PgConnection, new_matches: Vec<NewMatch>) -> usize {
+    use schema::matches;
+
+    diesel::insert_into(matches::table)
+        .values(&new_matches)
+        .execute(conn)
+        .expect("Error saving new matches")
+}
+
+pub fn get_users(conn: &PgConnection) -> Vec<User> {
+    users.load::<User>(conn).expect("Expected user result")
+}
+
 pub fn get_user_by_discord_id(conn: &PgConnection, id: &i64) -> User {
     use crate::schema::users::discord_id;
     users
@@ -45,6 +109,15 @@ pub fn get_user_by_discord_id(conn: &PgConnection, id: &i64) -> User {
         .expect("Expected user")
 }
 
+pub fn find_user_by_discord_id(conn: &PgConnection, id: i64) -> Option<User> {
+    use crate::schema::users::discord_id;
+    users
+        .filter(discord_id.eq(id))
+        .first::<User>(conn)
+        .optional()
+        .expect("Expected user result")
+}
+
 pub fn create_match(conn: &PgConnection, new_match: NewMatch) -> usize {
     use schema::matches;
 
@@ -61,13 +134,19 @@ pub fn get_match(conn: &PgConnection, m_id: i32) -> Match {
         .expect("Expected match result")
 }
 
-pub fn get_matches(conn: &PgConnection, limit: i64, show_completed: bool) -> Vec<Match> {
+pub fn get_matches(
+    conn: &PgConnection,
+    limit: i64,
+    state: MatchState,
+    scope: MatchScope,
+) -> Vec<Match> {
     use crate::schema::matches::*;
-    let mut query = matches::table().into_boxed();
-    if show_completed {
-        query = query.filter(match_state.eq(Completed));
-    } else {
-        query = query.filter(match_state.eq(Entered));
+    let mut query = matches::table().filter(match_state.eq(state)).into_boxed();
+    if let Some(s_id) = scope.season_id {
+        query = query.filter(season_id.eq(s_id));
+    }
+    if let Some(d_id) = scope.division_id {
+        query = query.filter(division_id.eq(d_id));
     }
     query
         .order_by(id)
@@ -76,21 +155,94 @@ pub fn get_matches(conn: &PgConnection, limit: i64, show_completed: bool) -> Vec
         .expect("Expected match result")
 }
 
-pub fn get_next_team_match(conn: &PgConnection, team_role_id: i64) -> Option<Match> {
+/// The team's oldest unplayed match, in the season if one is given. Matches added before there
+/// were seasons, or outside of one, belong to every season.
+pub fn get_next_team_match(
+    conn: &PgConnection,
+    team_role_id: i64,
+    season: Option<i32>,
+) -> Option<Match> {
     use crate::schema::matches::*;
-    matches
+    let mut query = matches
         .filter(
             team_one_role_id
                 .eq(team_role_id)
                 .or(team_two_role_id.eq(team_role_id))
                 .and(match_state.eq(Entered)),
         )
+        .into_boxed();
+    if let Some(s_id) = season {
+        query = query.filter(season_id.eq(s_id).or(season_id.is_null()));
+    }
+    query
         .then_order_by(id)
         .first::<Match>(conn)
         .optional()
         .unwrap()
 }
 
+/// Every match, oldest first
+pub fn get_all_matches(conn: &PgConnection) -> Vec<Match> {
+    use crate::schema::matches::id;
+    matches
+        .order_by(id)
+        .load::<Match>(conn)
+        .expect("Expected match result")
+}
+
+pub fn find_match(conn: &PgConnection, m_id: i32) -> Option<Match> {
+    matches
+        .find(m_id)
+        .first::<Match>(conn)
+        .optional()
+        .expect("Expected match result")
+}
+
+/// Applies the edits allowed in the match's state & writes every changed field to the audit log
+pub fn edit_match(
+    conn: &PgConnection,
+    m: &Match,
+    changes: &MatchChanges,
+    edited_by: Option<i64>,
+) -> Result<(Match, Vec<FieldChange>), MatchEditError> {
+    use schema::match_edits;
+
+    let setup_started = !get_match_setup_steps(conn, m.id).is_empty();
+    let field_changes = changes.field_changes(m, setup_started)?;
+    let edited_at = Utc::now().naive_utc();
+    let edits: Vec<NewMatchEdit> = field_changes
+        .iter()
+        .map(|change| NewMatchEdit {
+            match_id: m.id,
+            edited_by,
+            field: String::from(change.field),
+            old_value: change.old_value.clone(),
+            new_value: change.new_value.clone(),
+            edited_at,
+        })
+        .collect();
+    let edited = conn
+        .transaction::<_, diesel::result::Error, _>(|| {
+            diesel::insert_into(match_edits::table)
+                .values(&edits)
+                .execute(conn)?;
+            diesel::update(matches.find(m.id))
+                .set(changes)
+                .get_result::<Match>(conn)
+        })
+        .unwrap_or_else(|_| panic!("unable to edit match id: {}", m.id));
+    Ok((edited, field_changes))
+}
+
+pub fn get_match_edits(conn: &PgConnection, m_id: i32) -> Vec<MatchEdit> {
+    use crate::schema::match_edits::dsl::*;
+    match_edits
+        .filter(match_id.eq(m_id))
+        .order_by(id)
+        .load::<MatchEdit>(conn)
+        .expect("Expected match edit result")
+}
+
 pub fn update_match_schedule(conn: &PgConnection, m_id: i32, time_str: String) -> Match {
     diesel::update(matches.find(m_id))
         .set(scheduled_time_str.eq(time_str))
@@ -105,9 +257,283 @@ pub fn update_match_state(conn: &PgConnection, m_id: i32, state: MatchState) ->
         .unwrap_or_else(|_| panic!("unable to find match id: {}", m_id))
 }
 
-pub fn delete_match(conn: &PgConnection, m_id: i32) -> usize {
+/// Moves the match to the archived state, recording who archived it & why, unless it already is.
+/// The GSLT tokens of its servers are returned to the pool.
+pub fn archive_match(
+    conn: &PgConnection,
+    m_id: i32,
+    admin_discord_id: i64,
+    reason: Option<&str>,
+) -> Option<Match> {
     use crate::schema::matches::*;
-    diesel::delete(matches.filter(id.eq_all(m_id)))
+    let archived = diesel::update(matches.find(m_id).filter(match_state.ne(Archived)))
+        .set((
+            archived_from_state.eq(match_state.nullable()),
+            match_state.eq(Archived),
+            archived_at.eq(Utc::now().naive_utc()),
+            archived_by.eq(admin_discord_id),
+            archive_reason.eq(reason),
+        ))
+        .get_result::<Match>(conn)
+        .optional()
+        .expect("Error archiving match");
+    if archived.is_some() {
+        release_match_tokens(conn, m_id);
+    }
+    archived
+}
+
+/// Moves an archived match back to the state it was archived in
+pub fn restore_match(conn: &PgConnection, m_id: i32) -> Option<Match> {
+    use crate::schema::matches::*;
+    let archived = matches
+        .find(m_id)
+        .filter(match_state.eq(Archived))
+        .first::<Match>(conn)
+        .optional()
+        .expect("Expected match result")?;
+    let restored_state = archived.archived_from_state.unwrap_or(Entered);
+    Some(
+        diesel::update(matches.find(m_id))
+            .set((
+                match_state.eq(restored_state),
+                archived_at.eq(None::<NaiveDateTime>),
+                archived_by.eq(None::<i64>),
+                archive_reason.eq(None::<String>),
+                archived_from_state.eq(None::<MatchState>),
+            ))
+            .get_result::<Match>(conn)
+            .expect("Error restoring match"),
+    )
+}
+
+/// Saves the match's winner & map score & returns the GSLT tokens of its servers to the pool.
+/// Bracket matches move their teams on, starting the next bracket matches once both their teams
+/// are known, which are returned with the updated match.
+pub fn report_match_result(
+    conn: &PgConnection,
+    m: &Match,
+    winner: i64,
+    score: Option<(i32, i32)>,
+) -> Result<(Match, Vec<Match>), ResultError> {
+    use crate::schema::matches::*;
+
+    m.check_result(winner, score)?;
+    let mut bracket_state = find_match_bracket(conn, m.id);
+    let mut changed = Vec::new();
+    if let Some((_, bracket_matches, seq)) = bracket_state.as_mut() {
+        changed = report_winner(bracket_matches, *seq, winner).map_err(ResultError::Bracket)?;
+    }
+    let reported = conn
+        .transaction::<_, diesel::result::Error, _>(|| {
+            let reported = diesel::update(matches.find(m.id))
+                .set((
+                    winner_role_id.eq(winner),
+                    team_one_score.eq(score.map(|(one, _)| one)),
+                    team_two_score.eq(score.map(|(_, two)| two)),
+                ))
+                .get_result::<Match>(conn)?;
+            let started = match bracket_state.as_mut() {
+                Some((bracket, bracket_matches, _)) => {
+                    for &seq in &changed {
+                        save_bracket_match(conn, &bracket_matches[seq])?;
+                    }
+                    start_bracket_matches(conn, bracket, bracket_matches)?
+                }
+                None => Vec::new(),
+            };
+            Ok((reported, started))
+        })
+        .unwrap_or_else(|_| panic!("unable to report result of match id: {}", m.id));
+    release_match_tokens(conn, m.id);
+    Ok(reported)
+}
+
+/// Matches in the scope with a reported result that are not part of a bracket, oldest first
+pub fn get_result_matches(conn: &PgConnection, scope: MatchScope) -> Vec<Match> {
+    use crate::schema::bracket_matches;
+    use crate::schema::matches::*;
+    let bracket_match_ids = bracket_matches::table
+        .select(bracket_matches::match_id)
+        .filter(bracket_matches::match_id.is_not_null());
+    let mut query = matches
+        .filter(winner_role_id.is_not_null())
+        .filter(match_state.ne(Archived))
+        .filter(diesel::dsl::not(id.nullable().eq_any(bracket_match_ids)))
+        .into_boxed();
+    if let Some(s_id) = scope.season_id {
+        query = query.filter(season_id.eq(s_id));
+    }
+    if let Some(d_id) = scope.division_id {
+        query = query.filter(division_id.eq(d_id));
+    }
+    query
+        .order_by(id)
+        .load::<Match>(conn)
+        .expect("Expected match result")
+}
+
+/// Creates a bracket for the teams in seed order & starts its first matches, which are returned
+pub fn create_bracket(
+    conn: &PgConnection,
+    new_bracket: NewBracket,
+    seeds: &[(i64, String)],
+) -> Result<(Bracket, Vec<Match>), BracketError> {
+    use crate::schema::{bracket_matches, bracket_teams, brackets};
+
+    let role_ids: Vec<i64> = seeds.iter().map(|(role_id, _)| *role_id).collect();
+    let mut new_matches = build_bracket(0, new_bracket.format, &role_ids)?;
+    if find_bracket(conn, new_bracket.name).is_some() {
+        return Err(BracketError::NameTaken);
+    }
+    let created = conn
+        .transaction::<_, diesel::result::Error, _>(|| {
+            let bracket = diesel::insert_into(brackets::table)
+                .values(&new_bracket)
+                .get_result::<Bracket>(conn)?;
+            let teams_seeded: Vec<BracketTeam> = seeds
+                .iter()
+                .enumerate()
+                .map(|(i, (role_id, name))| BracketTeam {
+                    bracket_id: bracket.id,
+                    role_id: *role_id,
+                    name: name.clone(),
+                    seed: i as i32 + 1,
+                })
+                .collect();
+            diesel::insert_into(bracket_teams::table)
+                .values(&teams_seeded)
+                .execute(conn)?;
+            for m in new_matches.iter_mut() {
+                m.bracket_id = bracket.id;
+            }
+            diesel::insert_into(bracket_matches::table)
+                .values(&new_matches)
+                .execute(conn)?;
+            let started = start_bracket_matches(conn, &bracket, &mut new_matches)?;
+            Ok((bracket, started))
+        })
+        .expect("Error saving bracket");
+    Ok(created)
+}
+
+pub fn find_bracket(conn: &PgConnection, bracket_name: &str) -> Option<Bracket> {
+    use crate::schema::brackets::dsl::*;
+    brackets
+        .filter(name.eq(bracket_name))
+        .first::<Bracket>(conn)
+        .optional()
+        .expect("Expected bracket result")
+}
+
+/// The most recently created bracket
+pub fn get_latest_bracket(conn: &PgConnection) -> Option<Bracket> {
+    use crate::schema::brackets::dsl::*;
+    brackets
+        .order_by(id.desc())
+        .first::<Bracket>(conn)
+        .optional()
+        .expect("Expected bracket result")
+}
+
+pub fn get_bracket_matches(conn: &PgConnection, b_id: i32) -> Vec<BracketMatch> {
+    use crate::schema::bracket_matches::dsl::*;
+    bracket_matches
+        .filter(bracket_id.eq(b_id))
+        .order_by(seq)
+        .load::<BracketMatch>(conn)
+        .expect("Expected bracket match result")
+}
+
+/// The bracket's teams, by seed
+pub fn get_bracket_teams(conn: &PgConnection, b_id: i32) -> Vec<BracketTeam> {
+    use crate::schema::bracket_teams::dsl::*;
+    bracket_teams
+        .filter(bracket_id.eq(b_id))
+        .order_by(seed)
+        .load::<BracketTeam>(conn)
+        .expect("Expected bracket team result")
+}
+
+/// Bracket the match was started for, with the bracket's matches & the match's seq
+fn find_match_bracket(
+    conn: &PgConnection,
+    m_id: i32,
+) -> Option<(Bracket, Vec<BracketMatch>, usize)> {
+    use crate::schema::bracket_matches;
+    use crate::schema::brackets::dsl::brackets;
+    let bracket_match = bracket_matches::table
+        .filter(bracket_matches::match_id.eq(m_id))
+        .first::<BracketMatch>(conn)
+        .optional()
+        .expect("Expected bracket match result")?;
+    let bracket = brackets
+        .find(bracket_match.bracket_id)
+        .first::<Bracket>(conn)
+        .expect("Expected bracket result");
+    let bracket_matches = get_bracket_matches(conn, bracket.id);
+    Some((bracket, bracket_matches, bracket_match.seq as usize))
+}
+
+fn save_bracket_match(conn: &PgConnection, bracket_match: &BracketMatch) -> QueryResult<usize> {
+    use crate::schema::bracket_matches::dsl::*;
+    diesel::update(bracket_matches.find((bracket_match.bracket_id, bracket_match.seq)))
+        .set(bracket_match)
+        .execute(conn)
+}
+
+/// Creates a match for every bracket match whose teams are known & that was not started yet
+fn start_bracket_matches(
+    conn: &PgConnection,
+    bracket: &Bracket,
+    bracket_matches: &mut [BracketMatch],
+) -> QueryResult<Vec<Match>> {
+    use crate::schema::matches;
+
+    let bracket_teams = get_bracket_teams(conn, bracket.id);
+    let team_name = |role_id: Option<i64>| {
+        bracket_teams
+            .iter()
+            .find(|t| Some(t.role_id) == role_id)
+            .map(|t| t.name.clone())
+            .unwrap_or_default()
+    };
+    let date_added = Utc::now().naive_utc();
+    let mut started = Vec::new();
+    for bracket_match in bracket_matches
+        .iter_mut()
+        .filter(|m| m.is_ready() && m.match_id.is_none())
+    {
+        let team_one_name = team_name(bracket_match.team_one_role_id);
+        let team_two_name = team_name(bracket_match.team_two_role_id);
+        let note = format!("{}: {}", bracket.name, bracket_match.label(bracket.format));
+        let new_match = NewMatch {
+            team_one_role_id: bracket_match.team_one_role_id.unwrap_or_default(),
+            team_one_name: &team_one_name,
+            team_two_role_id: bracket_match.team_two_role_id.unwrap_or_default(),
+            team_two_name: &team_two_name,
+            note: Some(&note),
+            series_type: &bracket.series_type,
+            date_added: &date_added,
+            match_state: &Entered,
+            round: None,
+            deadline: None,
+            season_id: bracket.season_id,
+            division_id: None,
+        };
+        let m = diesel::insert_into(matches::table)
+            .values(&new_match)
+            .get_result::<Match>(conn)?;
+        bracket_match.match_id = Some(m.id);
+        save_bracket_match(conn, bracket_match)?;
+        started.push(m);
+    }
+    Ok(started)
+}
+
+/// Deletes the match along with its setup steps, series maps, stats & demos
+pub fn delete_match(conn: &PgConnection, m_id: i32) -> usize {
+    diesel::delete(matches.find(m_id))
         .execute(conn)
         .expect("Error deleting match")
 }
@@ -129,6 +555,22 @@ pub fn get_match_setup_steps(conn: &PgConnection, m_id: i32) -> Vec<MatchSetupSt
         .expect("Expected MatchSetupStep result")
 }
 
+/// Removes the match's setup steps & series maps, the stats & demos of the maps are removed with
+/// them, so the match can be set up again
+pub fn reset_match_setup(conn: &PgConnection, m_id: i32) -> usize {
+    use crate::schema::{match_setup_step, series_map};
+
+    conn.transaction::<_, diesel::result::Error, _>(|| {
+        let steps =
+            diesel::delete(match_setup_step::table.filter(match_setup_step::match_id.eq(m_id)))
+                .execute(conn)?;
+        let series_maps = diesel::delete(series_map::table.filter(series_map::match_id.eq(m_id)))
+            .execute(conn)?;
+        Ok(steps + series_maps)
+    })
+    .expect("Error resetting match setup")
+}
+
 pub fn create_series_maps(conn: &PgConnection, new_series_maps: Vec<NewSeriesMap>) -> usize {
     use schema::series_map;
 
@@ -138,26 +580,556 @@ pub fn create_series_maps(conn: &PgConnection, new_series_maps: Vec<NewSeriesMap
         .expect("Error saving new setup step")
 }
 
+pub fn get_series_maps(conn: &PgConnection, m_id: i32) -> Vec<SeriesMap> {
+    use crate::schema::series_map::dsl::*;
+    series_map
+        .filter(match_id.eq_all(m_id))
+        .order(id)
+        .load::<SeriesMap>(conn)
+        .expect("Expected SeriesMap result")
+}
+
+pub fn update_demo(conn: &PgConnection, demo: Demo) -> Demo {
+    use schema::demos;
+
+    diesel::insert_into(demos::table)
+        .values(&demo)
+        .on_conflict(demos::series_map_id)
+        .do_update()
+        .set(&demo)
+        .get_result::<Demo>(conn)
+        .expect("Error saving demo")
+}
+
+pub fn get_match_demos(conn: &PgConnection, m_id: i32) -> Vec<(SeriesMap, Demo)> {
+    use schema::{demos, series_map};
+
+    series_map::table
+        .inner_join(demos::table)
+        .filter(series_map::match_id.eq(m_id))
+        .order(series_map::id)
+        .load::<(SeriesMap, Demo)>(conn)
+        .expect("Expected demo result")
+}
+
 pub fn get_map_pool(conn: &PgConnection) -> Vec<Map> {
     maps.load::<Map>(conn).expect("Expected match result")
 }
 
+/// Maps of the season's pool, or every map when the season has no pool of its own
+pub fn get_season_map_pool(conn: &PgConnection, s_id: Option<i32>) -> Vec<Map> {
+    use crate::schema::season_maps::dsl::*;
+    let season_pool = match s_id {
+        Some(s_id) => season_maps
+            .filter(season_id.eq(s_id))
+            .order_by(map)
+            .load::<SeasonMap>(conn)
+            .expect("Expected season map result"),
+        None => Vec::new(),
+    };
+    if season_pool.is_empty() {
+        return get_map_pool(conn);
+    }
+    season_pool
+        .into_iter()
+        .map(|season_map| Map {
+            name: season_map.map,
+        })
+        .collect()
+}
+
+/// Replaces the season's map pool, an empty pool falls back to every map
+pub fn set_season_maps(conn: &PgConnection, s_id: i32, map_names: &[String]) -> usize {
+    use crate::schema::season_maps::dsl::*;
+    let pool: Vec<SeasonMap> = map_names
+        .iter()
+        .map(|name| SeasonMap {
+            season_id: s_id,
+            map: name.clone(),
+        })
+        .collect();
+    conn.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::delete(season_maps.filter(season_id.eq(s_id))).execute(conn)?;
+        diesel::insert_into(season_maps).values(&pool).execute(conn)
+    })
+    .expect("Error saving season maps")
+}
+
+pub fn add_map(conn: &PgConnection, map: &Map) -> bool {
+    use schema::maps;
+
+    diesel::insert_into(maps::table)
+        .values(map)
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .expect("Error saving map")
+        > 0
+}
+
+pub fn delete_map(conn: &PgConnection, name: &str) -> usize {
+    diesel::delete(maps.find(name))
+        .execute(conn)
+        .expect("Error deleting map")
+}
+
 pub fn get_match_servers(conn: &PgConnection) -> Vec<MatchServer> {
     match_servers
         .load::<MatchServer>(conn)
         .expect("Expected match server result")
 }
 
-pub fn get_fresh_token(conn: &PgConnection) -> GsltToken {
+pub fn get_enabled_match_servers(conn: &PgConnection) -> Vec<MatchServer> {
+    use schema::match_servers::enabled;
+
+    match_servers
+        .filter(enabled.eq(true))
+        .order(schema::match_servers::region_label)
+        .load::<MatchServer>(conn)
+        .expect("Expected match server result")
+}
+
+pub fn update_match_server(conn: &PgConnection, server: MatchServer) -> MatchServer {
+    use schema::match_servers;
+
+    diesel::insert_into(match_servers::table)
+        .values(&server)
+        .on_conflict(match_servers::region_label)
+        .do_update()
+        .set(&server)
+        .get_result::<MatchServer>(conn)
+        .expect("Error saving match server")
+}
+
+pub fn delete_match_server(conn: &PgConnection, region: &str) -> usize {
+    diesel::delete(match_servers.find(region))
+        .execute(conn)
+        .expect("Error deleting match server")
+}
+
+pub fn set_match_server_enabled(
+    conn: &PgConnection,
+    region: &str,
+    is_enabled: bool,
+) -> Option<MatchServer> {
+    use schema::match_servers::enabled;
+
+    diesel::update(match_servers.find(region))
+        .set(enabled.eq(is_enabled))
+        .get_result::<MatchServer>(conn)
+        .optional()
+        .expect("Error updating match server")
+}
+
+fn decrypt_token(keyring: &Keyring, mut token: GsltToken) -> GsltToken {
+    token.token = keyring
+        .decrypt(&token.token)
+        .unwrap_or_else(|err| panic!("unable to decrypt gslt token {}: {}", token.id, err));
+    token
+}
+
+pub fn get_fresh_token(conn: &PgConnection, keyring: &Keyring) -> Option<GsltToken> {
     gslt_tokens
         .filter(in_use.eq(false))
         .first::<GsltToken>(conn)
-        .expect("Expected gslt token")
+        .optional()
+        .expect("Expected gslt token result")
+        .map(|token| decrypt_token(keyring, token))
+}
+
+pub fn get_token(conn: &PgConnection, keyring: &Keyring, token: &str) -> Option<GsltToken> {
+    gslt_tokens
+        .filter(token_hash.eq(secret_hash(token)))
+        .first::<GsltToken>(conn)
+        .optional()
+        .expect("Expected gslt token result")
+        .map(|token| decrypt_token(keyring, token))
+}
+
+pub fn get_tokens(conn: &PgConnection, keyring: &Keyring) -> Vec<GsltToken> {
+    gslt_tokens
+        .order((in_use.desc(), schema::gslt_tokens::assigned_at))
+        .load::<GsltToken>(conn)
+        .expect("Expected gslt token result")
+        .into_iter()
+        .map(|token| decrypt_token(keyring, token))
+        .collect()
+}
+
+pub fn count_tokens(conn: &PgConnection) -> i64 {
+    gslt_tokens
+        .count()
+        .get_result(conn)
+        .expect("Expected gslt token count")
+}
+
+pub fn count_free_tokens(conn: &PgConnection) -> i64 {
+    gslt_tokens
+        .filter(in_use.eq(false))
+        .count()
+        .get_result(conn)
+        .expect("Expected gslt token count")
+}
+
+/// Adds the token to the pool, returns false if it already is in the pool
+pub fn add_token(conn: &PgConnection, keyring: &Keyring, token: &str) -> bool {
+    diesel::insert_into(gslt_tokens)
+        .values(&NewGsltToken {
+            token: keyring.encrypt(token),
+            token_hash: secret_hash(token),
+            in_use: false,
+        })
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .expect("Error saving gslt token")
+        > 0
+}
+
+pub fn delete_token(conn: &PgConnection, token: &str) -> usize {
+    diesel::delete(gslt_tokens.filter(token_hash.eq(secret_hash(token))))
+        .execute(conn)
+        .expect("Error deleting gslt token")
+}
+
+/// Saves the token's assignment, the token itself is never written back
+pub fn update_token(conn: &PgConnection, token: GsltToken) -> usize {
+    use schema::gslt_tokens::{assigned_at, match_id};
+
+    diesel::update(gslt_tokens.find(token.id))
+        .set((
+            in_use.eq(token.in_use),
+            match_id.eq(token.match_id),
+            assigned_at.eq(token.assigned_at),
+        ))
+        .execute(conn)
+        .unwrap_or_else(|_| panic!("unable to find gslt token: {}", token.id))
+}
+
+/// Frees every token assigned to the match's servers
+pub fn release_match_tokens(conn: &PgConnection, m_id: i32) -> usize {
+    use schema::gslt_tokens::{assigned_at, match_id};
+
+    diesel::update(gslt_tokens.filter(match_id.eq(m_id)))
+        .set((
+            in_use.eq(false),
+            match_id.eq(None::<i32>),
+            assigned_at.eq(None::<NaiveDateTime>),
+        ))
+        .execute(conn)
+        .expect("Error releasing gslt tokens")
+}
+
+pub fn get_provider_credentials(
+    conn: &PgConnection,
+    keyring: &Keyring,
+    g_id: i64,
+) -> Option<ProviderCredentials> {
+    use schema::provider_credentials::dsl::provider_credentials;
+
+    let mut credentials = provider_credentials
+        .find(g_id)
+        .first::<ProviderCredentials>(conn)
+        .optional()
+        .expect("Expected provider credentials result")?;
+    credentials.dathost_password = keyring
+        .decrypt(&credentials.dathost_password)
+        .unwrap_or_else(|err| panic!("unable to decrypt credentials of {}: {}", g_id, err));
+    Some(credentials)
+}
+
+pub fn update_provider_credentials(
+    conn: &PgConnection,
+    keyring: &Keyring,
+    mut credentials: ProviderCredentials,
+) -> usize {
+    use schema::provider_credentials;
+
+    credentials.dathost_password = keyring.encrypt(&credentials.dathost_password);
+    diesel::insert_into(provider_credentials::table)
+        .values(&credentials)
+        .on_conflict(provider_credentials::guild_id)
+        .do_update()
+        .set(&credentials)
+        .execute(conn)
+        .expect("Error saving provider credentials")
+}
+
+/// Encrypts secrets stored as plaintext or with an older key with the current key, returning the
+/// number of secrets encrypted
+pub fn reencrypt_secrets(conn: &PgConnection, keyring: &Keyring) -> Result<usize, CryptoError> {
+    use schema::gslt_tokens::{id, token};
+    use schema::provider_credentials::dsl::{dathost_password, provider_credentials};
+
+    let mut tokens = Vec::new();
+    for row in gslt_tokens
+        .load::<GsltToken>(conn)
+        .expect("Expected gslt token result")
+    {
+        if keyring.needs_reencryption(&row.token) {
+            tokens.push((row.id, keyring.reencrypt(&row.token)?));
+        }
+    }
+    let mut passwords = Vec::new();
+    let credentials = provider_credentials
+        .load::<ProviderCredentials>(conn)
+        .expect("Expected provider credentials result");
+    for row in credentials {
+        if keyring.needs_reencryption(&row.dathost_password) {
+            passwords.push((row.guild_id, keyring.reencrypt(&row.dathost_password)?));
+        }
+    }
+    conn.transaction::<_, diesel::result::Error, _>(|| {
+        for (t_id, encrypted) in &tokens {
+            diesel::update(gslt_tokens.filter(id.eq(t_id)))
+                .set(token.eq(encrypted))
+                .execute(conn)?;
+        }
+        for (g_id, encrypted) in &passwords {
+            diesel::update(provider_credentials.find(g_id))
+                .set(dathost_password.eq(encrypted))
+                .execute(conn)?;
+        }
+        Ok(())
+    })
+    .expect("Error saving encrypted secrets");
+    Ok(tokens.len() + passwords.len())
+}
+
+pub fn get_guild_settings(conn: &PgConnection, g_id: i64) -> GuildSettings {
+    guild_settings
+        .find(g_id)
+        .first::<GuildSettings>(conn)
+        .optional()
+        .expect("Expected guild settings result")
+        .unwrap_or_else(|| GuildSettings::new(g_id))
+}
+
+pub fn update_guild_settings(conn: &PgConnection, settings: GuildSettings) -> GuildSettings {
+    use schema::guild_settings;
+
+    diesel::insert_into(guild_settings::table)
+        .values(&settings)
+        .on_conflict(guild_settings::guild_id)
+        .do_update()
+        .set(&settings)
+        .get_result::<GuildSettings>(conn)
+        .expect("Error saving guild settings")
+}
+
+pub fn get_team(conn: &PgConnection, role_id: i64) -> Option<Team> {
+    teams
+        .find(role_id)
+        .first::<Team>(conn)
+        .optional()
+        .expect("Expected team result")
+}
+
+/// Every registered team, by name
+pub fn get_teams(conn: &PgConnection) -> Vec<Team> {
+    use crate::schema::teams::name;
+    teams
+        .order_by(name)
+        .load::<Team>(conn)
+        .expect("Expected team result")
+}
+
+pub fn update_team(conn: &PgConnection, team: Team) -> Team {
+    use schema::teams;
+
+    diesel::insert_into(teams::table)
+        .values(&team)
+        .on_conflict(teams::role_id)
+        .do_update()
+        .set(&team)
+        .get_result::<Team>(conn)
+        .expect("Error saving team")
+}
+
+/// Creates the season, none if the name is taken
+pub fn create_season(conn: &PgConnection, new_season: NewSeason) -> Option<Season> {
+    use schema::seasons;
+
+    diesel::insert_into(seasons::table)
+        .values(&new_season)
+        .on_conflict_do_nothing()
+        .get_result::<Season>(conn)
+        .optional()
+        .expect("Error saving season")
+}
+
+/// Makes the season the active one in place of the season active before
+pub fn activate_season(conn: &PgConnection, s_id: i32) -> Season {
+    use crate::schema::seasons::dsl::*;
+    conn.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::update(seasons.filter(active))
+            .set(active.eq(false))
+            .execute(conn)?;
+        diesel::update(seasons.find(s_id))
+            .set(active.eq(true))
+            .get_result::<Season>(conn)
+    })
+    .expect("Error activating season")
+}
+
+pub fn find_season(conn: &PgConnection, season_name: &str) -> Option<Season> {
+    use crate::schema::seasons::dsl::*;
+    seasons
+        .filter(name.eq(season_name))
+        .first::<Season>(conn)
+        .optional()
+        .expect("Expected season result")
+}
+
+pub fn get_active_season(conn: &PgConnection) -> Option<Season> {
+    use crate::schema::seasons::dsl::*;
+    seasons
+        .filter(active)
+        .first::<Season>(conn)
+        .optional()
+        .expect("Expected season result")
+}
+
+/// Every season, oldest first
+pub fn get_seasons(conn: &PgConnection) -> Vec<Season> {
+    use crate::schema::seasons::dsl::*;
+    seasons
+        .order_by(id)
+        .load::<Season>(conn)
+        .expect("Expected season result")
+}
+
+/// Creates the division in the season, none if the season already has one by that name
+pub fn create_division(conn: &PgConnection, new_division: NewDivision) -> Option<Division> {
+    use schema::divisions;
+
+    diesel::insert_into(divisions::table)
+        .values(&new_division)
+        .on_conflict_do_nothing()
+        .get_result::<Division>(conn)
+        .optional()
+        .expect("Error saving division")
+}
+
+pub fn get_division(conn: &PgConnection, d_id: i32) -> Option<Division> {
+    use crate::schema::divisions::dsl::*;
+    divisions
+        .find(d_id)
+        .first::<Division>(conn)
+        .optional()
+        .expect("Expected division result")
+}
+
+pub fn find_division(conn: &PgConnection, s_id: i32, division_name: &str) -> Option<Division> {
+    use crate::schema::divisions::dsl::*;
+    divisions
+        .filter(season_id.eq(s_id))
+        .filter(name.eq(division_name))
+        .first::<Division>(conn)
+        .optional()
+        .expect("Expected division result")
+}
+
+/// The season's divisions, by name
+pub fn get_divisions(conn: &PgConnection, s_id: i32) -> Vec<Division> {
+    use crate::schema::divisions::dsl::*;
+    divisions
+        .filter(season_id.eq(s_id))
+        .order_by(name)
+        .load::<Division>(conn)
+        .expect("Expected division result")
+}
+
+/// Adds the team to the season or moves it to another division
+pub fn update_season_team(conn: &PgConnection, season_team: SeasonTeam) -> SeasonTeam {
+    use schema::season_teams;
+
+    diesel::insert_into(season_teams::table)
+        .values(&season_team)
+        .on_conflict((season_teams::season_id, season_teams::role_id))
+        .do_update()
+        .set(&season_team)
+        .get_result::<SeasonTeam>(conn)
+        .expect("Error saving season team")
+}
+
+pub fn delete_season_team(conn: &PgConnection, s_id: i32, team_role_id: i64) -> usize {
+    use crate::schema::season_teams::dsl::*;
+    diesel::delete(season_teams.find((s_id, team_role_id)))
+        .execute(conn)
+        .expect("Error deleting season team")
+}
+
+pub fn find_season_team(conn: &PgConnection, s_id: i32, team_role_id: i64) -> Option<SeasonTeam> {
+    use crate::schema::season_teams::dsl::*;
+    season_teams
+        .find((s_id, team_role_id))
+        .first::<SeasonTeam>(conn)
+        .optional()
+        .expect("Expected season team result")
+}
+
+pub fn get_season_teams(conn: &PgConnection, s_id: i32) -> Vec<SeasonTeam> {
+    use crate::schema::season_teams::dsl::*;
+    season_teams
+        .filter(season_id.eq(s_id))
+        .load::<SeasonTeam>(conn)
+        .expect("Expected season team result")
+}
+
+pub fn update_player_map_stats(conn: &PgConnection, stats: Vec<PlayerMapStats>) -> usize {
+    use schema::player_map_stats;
+
+    stats
+        .iter()
+        .map(|s| {
+            diesel::insert_into(player_map_stats::table)
+                .values(s)
+                .on_conflict((player_map_stats::steam_id, player_map_stats::series_map_id))
+                .do_update()
+                .set(s)
+                .execute(conn)
+                .expect("Error saving player map stats")
+        })
+        .sum()
+}
+
+/// Stats of the maps played in the season's matches, or of every map without a season
+fn season_player_stats(
+    season: Option<i32>,
+) -> schema::player_map_stats::BoxedQuery<'static, diesel::pg::Pg> {
+    use crate::schema::{matches, player_map_stats, series_map};
+    let mut query = player_map_stats::table.into_boxed();
+    if let Some(s_id) = season {
+        let season_maps = series_map::table
+            .inner_join(matches::table)
+            .filter(matches::season_id.eq(s_id))
+            .select(series_map::id);
+        query = query.filter(player_map_stats::series_map_id.eq_any(season_maps));
+    }
+    query
+}
+
+/// The player's stats in the season, or of all time without one
+pub fn get_player_stats(
+    conn: &PgConnection,
+    s_id: &str,
+    season: Option<i32>,
+) -> Vec<PlayerMapStats> {
+    use crate::schema::player_map_stats::steam_id;
+    season_player_stats(season)
+        .filter(steam_id.eq(s_id))
+        .load::<PlayerMapStats>(conn)
+        .expect("Expected player stats result")
 }
 
-pub fn update_token(conn: &PgConnection, token: GsltToken) -> GsltToken {
-    diesel::update(gslt_tokens.find(&token.token))
-        .set(in_use.eq(token.in_use))
-        .get_result::<GsltToken>(conn)
-        .unwrap_or_else(|_| panic!("unable to find gslt token: {}", token.token))
+/// Stats of the team's players in the season, or of all time without one
+pub fn get_team_player_stats(
+    conn: &PgConnection,
+    role_id: i64,
+    season: Option<i32>,
+) -> Vec<PlayerMapStats> {
+    use crate::schema::player_map_stats::team_role_id;
+    season_player_stats(season)
+        .filter(team_role_id.eq(role_id))
+        .load::<PlayerMapStats>(conn)
+        .expect("Expected player stats result")
 }
@@ -1,8 +1,10 @@
 use diesel::PgConnection;
 use std::env;
 use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{debug, error, info};
+use tracing_subscriber::EnvFilter;
 
-use serde::{Deserialize, Serialize};
 use serenity::async_trait;
 use serenity::client::Context;
 use serenity::framework::standard::StandardFramework;
@@ -12,7 +14,12 @@ use serenity::model::prelude::GuildId;
 use serenity::model::prelude::Ready;
 use serenity::prelude::{EventHandler, GatewayIntents, TypeMapKey};
 
-use csgo_matchbot::models::{Match, SeriesType, StepType};
+use crate::metrics::Metrics;
+use crate::utils::{get_config, get_metrics};
+use crate::webhooks::LiveMatches;
+use csgo_matchbot::config::{load_config, Config, ConfigError};
+use csgo_matchbot::crypto::Keyring;
+use csgo_matchbot::{pending_migrations, reencrypt_secrets, run_migrations};
 use r2d2::Pool;
 use r2d2_diesel::ConnectionManager;
 use serenity::model::application::command::CommandOptionType;
@@ -21,118 +28,48 @@ use serenity::model::application::interaction::{Interaction, InteractionResponse
 
 mod commands;
 mod dathost_models;
+mod demos;
+mod metrics;
+mod scoreboard;
 mod utils;
-
-#[derive(Clone, Serialize, Deserialize)]
-pub struct Config {
-    pub discord: DiscordConfig,
-    pub dathost: DathostConfig,
-}
-
-#[derive(Clone, Serialize, Deserialize)]
-pub struct DathostConfig {
-    pub user: String,
-    pub password: String,
-}
-
-#[derive(Clone, Serialize, Deserialize)]
-pub struct DiscordConfig {
-    pub token: String,
-    pub admin_role_id: u64,
-    pub application_id: u64,
-    pub guild_id: u64,
-}
-
-#[derive(PartialEq)]
-struct StateContainer {
-    state: State,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Veto {
-    map: String,
-    vetoed_by: u64,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SetupStep {
-    pub match_id: i32,
-    pub step_type: StepType,
-    pub team_role_id: i64,
-    pub map: Option<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SetupMap {
-    pub match_id: i32,
-    pub map: String,
-    pub picked_by: i64,
-    pub start_attack_team_role_id: Option<i64>,
-    pub start_defense_team_role_id: Option<i64>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Setup {
-    team_one: Option<i64>,
-    team_two: Option<i64>,
-    team_one_name: String,
-    team_two_name: String,
-    team_one_conn_str: Option<String>,
-    team_two_conn_str: Option<String>,
-    maps_remaining: Vec<String>,
-    maps: Vec<SetupMap>,
-    vetoes: Vec<Veto>,
-    series_type: SeriesType,
-    match_id: Option<i32>,
-    veto_pick_order: Vec<SetupStep>,
-    current_step: usize,
-    current_phase: State,
-    server_id: Option<String>,
-}
-
-#[derive(Debug, Copy, PartialEq, Eq, Serialize, Deserialize, Clone)]
-pub enum State {
-    MapVeto,
-    SidePick,
-    ServerPick,
-}
+mod webhooks;
 
 struct Handler;
 
-struct Maps;
-
-struct Matches;
-
 struct DBConnectionPool;
 
-impl TypeMapKey for Config {
-    type Value = Config;
-}
-
-impl TypeMapKey for Maps {
-    type Value = Vec<String>;
-}
-
-impl TypeMapKey for Setup {
-    type Value = Setup;
-}
-
-impl TypeMapKey for Matches {
-    type Value = Vec<Match>;
-}
+struct SecretKeys;
 
 impl TypeMapKey for DBConnectionPool {
     type Value = Pool<ConnectionManager<PgConnection>>;
 }
 
+impl TypeMapKey for SecretKeys {
+    type Value = Arc<Keyring>;
+}
+
 enum Command {
     SteamId,
     Schedule,
     Addmatch,
     Deletematch,
+    Restorematch,
+    Editmatch,
+    Fixtures,
+    Result,
+    Bracket,
+    Standings,
+    Season,
     Match,
     Matches,
     Maps,
+    Settings,
+    Captain,
+    Stats,
+    Demo,
+    Servers,
+    Gslt,
+    Dathost,
 }
 
 impl FromStr for Command {
@@ -143,9 +80,23 @@ impl FromStr for Command {
             "schedule" => Ok(Command::Schedule),
             "addmatch" => Ok(Command::Addmatch),
             "deletematch" => Ok(Command::Deletematch),
+            "restorematch" => Ok(Command::Restorematch),
+            "editmatch" => Ok(Command::Editmatch),
+            "fixtures" => Ok(Command::Fixtures),
+            "result" => Ok(Command::Result),
+            "bracket" => Ok(Command::Bracket),
+            "standings" => Ok(Command::Standings),
+            "season" => Ok(Command::Season),
             "match" => Ok(Command::Match),
             "matches" => Ok(Command::Matches),
             "maps" => Ok(Command::Maps),
+            "settings" => Ok(Command::Settings),
+            "captain" => Ok(Command::Captain),
+            "stats" => Ok(Command::Stats),
+            "demo" => Ok(Command::Demo),
+            "servers" => Ok(Command::Servers),
+            "gslt" => Ok(Command::Gslt),
+            "dathost" => Ok(Command::Dathost),
             _ => Err(()),
         }
     }
@@ -154,10 +105,10 @@ impl FromStr for Command {
 #[async_trait]
 impl EventHandler for Handler {
     async fn ready(&self, context: Context, ready: Ready) {
-        let config = load_config().await.unwrap();
+        let config = get_config(&context).await;
         let guild_id = GuildId(config.discord.guild_id);
         let commands = GuildId::set_application_commands(&guild_id, &context.http, |commands| {
-            return commands
+            commands
                 .create_application_command(|command| {
                     command
                         .name("maps")
@@ -198,11 +149,419 @@ impl EventHandler for Handler {
                                 .kind(CommandOptionType::Boolean)
                                 .required(false)
                         })
+                        .create_option(|option| {
+                            option
+                                .name("showarchived")
+                                .description("Shows only archived matches")
+                                .kind(CommandOptionType::Boolean)
+                                .required(false)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("season")
+                                .description("Season name, defaults to the active season")
+                                .kind(CommandOptionType::String)
+                                .required(false)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("division")
+                                .description("Division name, defaults to your team's division")
+                                .kind(CommandOptionType::String)
+                                .required(false)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("alldivisions")
+                                .description("Shows every division of the season")
+                                .kind(CommandOptionType::Boolean)
+                                .required(false)
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("standings")
+                        .description("Show the standings of the reported results")
+                        .create_option(|option| {
+                            option
+                                .name("season")
+                                .description("Season name, defaults to the active season")
+                                .kind(CommandOptionType::String)
+                                .required(false)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("division")
+                                .description("Division name, defaults to your team's division")
+                                .kind(CommandOptionType::String)
+                                .required(false)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("alldivisions")
+                                .description("Shows every division of the season")
+                                .kind(CommandOptionType::Boolean)
+                                .required(false)
+                        })
                 })
                 .create_application_command(|command| {
                     command
                         .name("deletematch")
-                        .description("Delete match (admin required)")
+                        .description("Archive match (admin required)")
+                        .create_option(|option| {
+                            option
+                                .name("matchid")
+                                .description("Match ID")
+                                .kind(CommandOptionType::Integer)
+                                .required(true)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("reason")
+                                .description("Why the match is archived")
+                                .kind(CommandOptionType::String)
+                                .required(false)
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("editmatch")
+                        .description("Edit a match, every change is logged (admin required)")
+                        .create_option(|option| {
+                            option
+                                .name("matchid")
+                                .description("Match ID")
+                                .kind(CommandOptionType::Integer)
+                                .required(true)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("teamone")
+                                .description("Team 1 (Home)")
+                                .kind(CommandOptionType::Role)
+                                .required(false)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("teamtwo")
+                                .description("Team 2 (Away)")
+                                .kind(CommandOptionType::Role)
+                                .required(false)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("type")
+                                .description("Series Type, fixed once the veto is completed")
+                                .kind(CommandOptionType::String)
+                                .required(false)
+                                .add_string_choice("Best of 1", "bo1")
+                                .add_string_choice("Best of 3", "bo3")
+                                .add_string_choice("Best of 5", "bo5")
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("note")
+                                .description("Note")
+                                .kind(CommandOptionType::String)
+                                .required(false)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("scheduled")
+                                .description("Date (Month/Day/Year) @ Time <Timezone>")
+                                .kind(CommandOptionType::String)
+                                .required(false)
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("fixtures")
+                        .description("Add a season of matches (admin required)")
+                        .create_option(|option| {
+                            option
+                                .name("generate")
+                                .description("Round robin of the registered teams")
+                                .kind(CommandOptionType::SubCommand)
+                                .create_sub_option(|o| {
+                                    o.name("double")
+                                        .description("Teams play each other home & away")
+                                        .kind(CommandOptionType::Boolean)
+                                        .required(false)
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("type")
+                                        .description("Series type of matches without one")
+                                        .kind(CommandOptionType::String)
+                                        .required(false)
+                                        .add_string_choice("Best of 1", "bo1")
+                                        .add_string_choice("Best of 3", "bo3")
+                                        .add_string_choice("Best of 5", "bo5")
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("start")
+                                        .description("Start date (YYYY-MM-DD), defaults to today")
+                                        .kind(CommandOptionType::String)
+                                        .required(false)
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("days")
+                                        .description("Days per round, defaults to 7")
+                                        .kind(CommandOptionType::Integer)
+                                        .required(false)
+                                })
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("import")
+                                .description("Import a CSV or JSON fixture list")
+                                .kind(CommandOptionType::SubCommand)
+                                .create_sub_option(|o| {
+                                    o.name("file")
+                                        .description("Fixture list")
+                                        .kind(CommandOptionType::Attachment)
+                                        .required(true)
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("type")
+                                        .description("Series type of matches without one")
+                                        .kind(CommandOptionType::String)
+                                        .required(false)
+                                        .add_string_choice("Best of 1", "bo1")
+                                        .add_string_choice("Best of 3", "bo3")
+                                        .add_string_choice("Best of 5", "bo5")
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("start")
+                                        .description("Start date (YYYY-MM-DD), defaults to today")
+                                        .kind(CommandOptionType::String)
+                                        .required(false)
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("days")
+                                        .description("Days per round, defaults to 7")
+                                        .kind(CommandOptionType::Integer)
+                                        .required(false)
+                                })
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("result")
+                        .description("Report a match's winner (admin required)")
+                        .create_option(|option| {
+                            option
+                                .name("matchid")
+                                .description("Match ID")
+                                .kind(CommandOptionType::Integer)
+                                .required(true)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("winner")
+                                .description("Winning team")
+                                .kind(CommandOptionType::Role)
+                                .required(true)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("teamonescore")
+                                .description("Maps won by team 1")
+                                .kind(CommandOptionType::Integer)
+                                .min_int_value(0)
+                                .required(false)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("teamtwoscore")
+                                .description("Maps won by team 2")
+                                .kind(CommandOptionType::Integer)
+                                .min_int_value(0)
+                                .required(false)
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("bracket")
+                        .description("Show or create a playoff bracket")
+                        .create_option(|option| {
+                            option
+                                .name("show")
+                                .description("Show a bracket")
+                                .kind(CommandOptionType::SubCommand)
+                                .create_sub_option(|o| {
+                                    o.name("name")
+                                        .description("Bracket name, defaults to the newest bracket")
+                                        .kind(CommandOptionType::String)
+                                        .required(false)
+                                })
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("create")
+                                .description("Create a bracket (admin required)")
+                                .kind(CommandOptionType::SubCommand)
+                                .create_sub_option(|o| {
+                                    o.name("name")
+                                        .description("Bracket name")
+                                        .kind(CommandOptionType::String)
+                                        .required(true)
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("format")
+                                        .description("Bracket format")
+                                        .kind(CommandOptionType::String)
+                                        .required(true)
+                                        .add_string_choice("Single elimination", "single")
+                                        .add_string_choice("Double elimination", "double")
+                                        .add_string_choice("GSL groups", "gsl")
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("type")
+                                        .description("Series Type, defaults to Bo3")
+                                        .kind(CommandOptionType::String)
+                                        .required(false)
+                                        .add_string_choice("Best of 1", "bo1")
+                                        .add_string_choice("Best of 3", "bo3")
+                                        .add_string_choice("Best of 5", "bo5")
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("seeds")
+                                        .description("Team roles by seed, defaults to standings")
+                                        .kind(CommandOptionType::String)
+                                        .required(false)
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("teams")
+                                        .description("Number of teams seeded from the standings")
+                                        .kind(CommandOptionType::Integer)
+                                        .min_int_value(2)
+                                        .required(false)
+                                })
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("season")
+                        .description("Show or manage seasons & their divisions")
+                        .create_option(|option| {
+                            option
+                                .name("list")
+                                .description("List seasons & their divisions")
+                                .kind(CommandOptionType::SubCommand)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("create")
+                                .description("Create a season (admin required)")
+                                .kind(CommandOptionType::SubCommand)
+                                .create_sub_option(|o| {
+                                    o.name("name")
+                                        .description("Season name")
+                                        .kind(CommandOptionType::String)
+                                        .required(true)
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("activate")
+                                        .description("Make it the active season")
+                                        .kind(CommandOptionType::Boolean)
+                                        .required(false)
+                                })
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("activate")
+                                .description("Make a season the active one (admin required)")
+                                .kind(CommandOptionType::SubCommand)
+                                .create_sub_option(|o| {
+                                    o.name("name")
+                                        .description("Season name")
+                                        .kind(CommandOptionType::String)
+                                        .required(true)
+                                })
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("maps")
+                                .description("Set a season's map pool (admin required)")
+                                .kind(CommandOptionType::SubCommand)
+                                .create_sub_option(|o| {
+                                    o.name("maps")
+                                        .description("Map names, leave out to use every map")
+                                        .kind(CommandOptionType::String)
+                                        .required(false)
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("season")
+                                        .description("Season name, defaults to the active season")
+                                        .kind(CommandOptionType::String)
+                                        .required(false)
+                                })
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("division")
+                                .description("Add a division to a season (admin required)")
+                                .kind(CommandOptionType::SubCommand)
+                                .create_sub_option(|o| {
+                                    o.name("name")
+                                        .description("Division name")
+                                        .kind(CommandOptionType::String)
+                                        .required(true)
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("season")
+                                        .description("Season name, defaults to the active season")
+                                        .kind(CommandOptionType::String)
+                                        .required(false)
+                                })
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("team")
+                                .description("Add a team to a season (admin required)")
+                                .kind(CommandOptionType::SubCommand)
+                                .create_sub_option(|o| {
+                                    o.name("team")
+                                        .description("Team role")
+                                        .kind(CommandOptionType::Role)
+                                        .required(true)
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("division")
+                                        .description("Division the team plays in")
+                                        .kind(CommandOptionType::String)
+                                        .required(false)
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("season")
+                                        .description("Season name, defaults to the active season")
+                                        .kind(CommandOptionType::String)
+                                        .required(false)
+                                })
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("removeteam")
+                                .description("Remove a team from a season (admin required)")
+                                .kind(CommandOptionType::SubCommand)
+                                .create_sub_option(|o| {
+                                    o.name("team")
+                                        .description("Team role")
+                                        .kind(CommandOptionType::Role)
+                                        .required(true)
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("season")
+                                        .description("Season name, defaults to the active season")
+                                        .kind(CommandOptionType::String)
+                                        .required(false)
+                                })
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("restorematch")
+                        .description("Restore an archived match (admin required)")
                         .create_option(|option| {
                             option
                                 .name("matchid")
@@ -250,6 +609,229 @@ impl EventHandler for Handler {
                                 .required(false)
                         })
                 })
+                .create_application_command(|command| {
+                    command
+                        .name("settings")
+                        .description("Show or change guild settings (admin required)")
+                        .create_option(|option| {
+                            option
+                                .name("confirmveto")
+                                .description("Require captains to confirm map bans & picks")
+                                .kind(CommandOptionType::Boolean)
+                                .required(false)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("adminchannel")
+                                .description("Channel admin warnings are posted in")
+                                .kind(CommandOptionType::Channel)
+                                .required(false)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("gsltthreshold")
+                                .description("Warn when free GSLT tokens fall below this count")
+                                .kind(CommandOptionType::Integer)
+                                .min_int_value(0)
+                                .required(false)
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("captain")
+                        .description("Set a team's captain & vice-captain (admin required)")
+                        .create_option(|option| {
+                            option
+                                .name("team")
+                                .description("Team")
+                                .kind(CommandOptionType::Role)
+                                .required(true)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("captain")
+                                .description("Captain")
+                                .kind(CommandOptionType::User)
+                                .required(true)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("vicecaptain")
+                                .description("Vice-captain")
+                                .kind(CommandOptionType::User)
+                                .required(false)
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("stats")
+                        .description("Show player or team stats, defaults to your own")
+                        .create_option(|option| {
+                            option
+                                .name("user")
+                                .description("Player")
+                                .kind(CommandOptionType::User)
+                                .required(false)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("team")
+                                .description("Team")
+                                .kind(CommandOptionType::Role)
+                                .required(false)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("season")
+                                .description("Season name, defaults to the active season")
+                                .kind(CommandOptionType::String)
+                                .required(false)
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("demo")
+                        .description("Get the demos of a match")
+                        .create_option(|option| {
+                            option
+                                .name("matchid")
+                                .description("Match ID")
+                                .kind(CommandOptionType::Integer)
+                                .required(true)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("map")
+                                .description("Only this map's demo")
+                                .kind(CommandOptionType::String)
+                                .required(false)
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("servers")
+                        .description("Manage match servers (admin required)")
+                        .create_option(|option| {
+                            option
+                                .name("add")
+                                .description("Add or update & enable a server")
+                                .kind(CommandOptionType::SubCommand)
+                                .create_sub_option(|o| {
+                                    o.name("region")
+                                        .description("Region label")
+                                        .kind(CommandOptionType::String)
+                                        .required(true)
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("serverid")
+                                        .description("Dathost ID of the server to duplicate")
+                                        .kind(CommandOptionType::String)
+                                        .required(true)
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("location")
+                                        .description("Where the server is hosted")
+                                        .kind(CommandOptionType::String)
+                                        .required(false)
+                                })
+                                .create_sub_option(|o| {
+                                    o.name("capacity")
+                                        .description("Player slots")
+                                        .kind(CommandOptionType::Integer)
+                                        .required(false)
+                                })
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("remove")
+                                .description("Remove a server")
+                                .kind(CommandOptionType::SubCommand)
+                                .create_sub_option(|o| {
+                                    o.name("region")
+                                        .description("Region label")
+                                        .kind(CommandOptionType::String)
+                                        .required(true)
+                                })
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("disable")
+                                .description("Stop offering a server during setup")
+                                .kind(CommandOptionType::SubCommand)
+                                .create_sub_option(|o| {
+                                    o.name("region")
+                                        .description("Region label")
+                                        .kind(CommandOptionType::String)
+                                        .required(true)
+                                })
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("gslt")
+                        .description("Manage the GSLT token pool (admin required)")
+                        .create_option(|option| {
+                            option
+                                .name("add")
+                                .description("Add a token to the pool")
+                                .kind(CommandOptionType::SubCommand)
+                                .create_sub_option(|o| {
+                                    o.name("token")
+                                        .description("Game server login token")
+                                        .kind(CommandOptionType::String)
+                                        .required(true)
+                                })
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("remove")
+                                .description("Remove a token from the pool")
+                                .kind(CommandOptionType::SubCommand)
+                                .create_sub_option(|o| {
+                                    o.name("token")
+                                        .description("Game server login token")
+                                        .kind(CommandOptionType::String)
+                                        .required(true)
+                                })
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("list")
+                                .description("List the pool's tokens & the matches holding them")
+                                .kind(CommandOptionType::SubCommand)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("release")
+                                .description("Free a token for the next match")
+                                .kind(CommandOptionType::SubCommand)
+                                .create_sub_option(|o| {
+                                    o.name("token")
+                                        .description("Game server login token")
+                                        .kind(CommandOptionType::String)
+                                        .required(true)
+                                })
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("dathost")
+                        .description("Set the Dathost account servers run on (admin required)")
+                        .create_option(|option| {
+                            option
+                                .name("user")
+                                .description("Dathost account email")
+                                .kind(CommandOptionType::String)
+                                .required(true)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("password")
+                                .description("Dathost account password")
+                                .kind(CommandOptionType::String)
+                                .required(true)
+                        })
+                })
                 .create_application_command(|command| {
                     command
                         .name("schedule")
@@ -261,11 +843,11 @@ impl EventHandler for Handler {
                                 .kind(CommandOptionType::String)
                                 .required(true)
                         })
-                });
+                })
         })
         .await;
-        println!("{} is connected!", ready.user.name);
-        log::debug!("Added these guild slash commands: {:#?}", commands);
+        info!(user = %ready.user.name, "connected");
+        debug!(commands = ?commands, "added guild slash commands");
     }
     async fn interaction_create(&self, context: Context, interaction: Interaction) {
         if let Interaction::ApplicationCommand(inc_command) = interaction {
@@ -277,13 +859,30 @@ impl EventHandler for Handler {
                     Command::Deletematch => {
                         commands::handle_delete_match(&context, &inc_command).await
                     }
+                    Command::Restorematch => {
+                        commands::handle_restore_match(&context, &inc_command).await
+                    }
+                    Command::Editmatch => commands::handle_edit_match(&context, &inc_command).await,
+                    Command::Fixtures => commands::handle_fixtures(&context, &inc_command).await,
+                    Command::Result => commands::handle_result(&context, &inc_command).await,
+                    Command::Bracket => commands::handle_bracket(&context, &inc_command).await,
+                    Command::Standings => commands::handle_standings(&context, &inc_command).await,
+                    Command::Season => commands::handle_season(&context, &inc_command).await,
                     Command::Schedule => commands::handle_schedule(&context, &inc_command).await,
                     Command::Match => commands::handle_match(&context, &inc_command).await,
                     Command::Matches => commands::handle_matches(&context, &inc_command).await,
                     Command::Maps => commands::handle_map_list(&context).await,
+                    Command::Settings => commands::handle_settings(&context, &inc_command).await,
+                    Command::Captain => commands::handle_captain(&context, &inc_command).await,
+                    Command::Stats => commands::handle_stats(&context, &inc_command).await,
+                    Command::Demo => commands::handle_demo(&context, &inc_command).await,
+                    Command::Servers => commands::handle_servers(&context, &inc_command).await,
+                    Command::Gslt => commands::handle_gslt(&context, &inc_command).await,
+                    Command::Dathost => commands::handle_dathost(&context, &inc_command).await,
                 };
                 if let Err(why) = create_int_resp(&context, &inc_command, content).await {
-                    eprintln!("Cannot respond to slash command: {}", why);
+                    error!(error = %why, "cannot respond to slash command");
+                    get_metrics(&context).await.interaction_error(&command);
                 }
             }
             if command == "setup" {
@@ -309,25 +908,109 @@ async fn create_int_resp(
 
 #[tokio::main]
 async fn main() {
-    env_logger::init();
-    let config = load_config().await.unwrap();
+    init_tracing();
+    let migrate_only = env::args().any(|arg| arg == "--migrate-only");
+    let auto_migrate = !env::args().any(|arg| arg == "--no-migrate");
+    let pool = get_connection_pool();
+    if let Err(err) = prepare_database(&pool, auto_migrate || migrate_only) {
+        error!("{}", err);
+        std::process::exit(1);
+    }
+    if migrate_only {
+        return;
+    }
+    let config = match load_config() {
+        Ok(config) => config,
+        Err(ConfigError::Invalid(errors)) => {
+            for err in errors {
+                error!("invalid config: {}", err);
+            }
+            std::process::exit(1);
+        }
+        Err(err) => {
+            error!("{}", err);
+            std::process::exit(1);
+        }
+    };
     let token = &config.discord.token;
     let framework = StandardFramework::new();
     let intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
-    let mut client = Client::builder(&token, intents)
+    let mut client = Client::builder(token, intents)
         .event_handler(Handler {})
         .framework(framework)
         .application_id(config.discord.application_id)
         .await
         .expect("Error creating client");
+    let keyring = config.keyring();
+    {
+        let conn = pool.get().expect("Expected db connection");
+        let encrypted = reencrypt_secrets(&conn, &keyring).expect("Unable to encrypt secrets");
+        if encrypted > 0 {
+            info!(encrypted, "encrypted secrets with the current key");
+        }
+    }
+    let live_matches = Arc::new(LiveMatches::default());
+    if let Some(webhook_config) = config.webhooks.clone() {
+        tokio::spawn(webhooks::serve(webhook_config, live_matches.clone()));
+    }
+    let metrics = Arc::new(Metrics::default());
+    if let Some(metrics_config) = config.metrics.clone() {
+        tokio::spawn(metrics::serve(
+            metrics_config,
+            metrics.clone(),
+            pool.clone(),
+        ));
+    }
     {
         let mut data = client.data.write().await;
         data.insert::<Config>(config);
-        data.insert::<DBConnectionPool>(get_connection_pool());
+        data.insert::<DBConnectionPool>(pool);
+        data.insert::<SecretKeys>(Arc::new(keyring));
+        data.insert::<LiveMatches>(live_matches);
+        data.insert::<Metrics>(metrics);
     }
     if let Err(why) = client.start().await {
-        println!("Client error: {:?}", why);
+        error!(error = ?why, "client error");
+    }
+}
+
+/// Logs to stdout, filtered by `RUST_LOG` & formatted as JSON lines with `LOG_FORMAT=json`
+fn init_tracing() {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new("warn,csgo_matchbot=info"));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    match env::var("LOG_FORMAT").as_deref() {
+        Ok("json") => subscriber.json().init(),
+        _ => subscriber.init(),
+    }
+}
+
+/// Applies the embedded migrations, or with `--no-migrate` only checks that none are pending
+fn prepare_database(
+    pool: &Pool<ConnectionManager<PgConnection>>,
+    migrate: bool,
+) -> Result<(), String> {
+    let conn = pool
+        .get()
+        .map_err(|err| format!("unable to connect to the database: {}", err))?;
+    if migrate {
+        let mut out = Vec::new();
+        run_migrations(&conn, &mut out)
+            .map_err(|err| format!("unable to run migrations: {}", err))?;
+        for line in String::from_utf8_lossy(&out).lines() {
+            info!("{}", line.to_lowercase());
+        }
+        return Ok(());
+    }
+    let pending = pending_migrations(&conn)
+        .map_err(|err| format!("unable to check migrations: {}", err))?;
+    if !pending.is_empty() {
+        return Err(format!(
+            "database is missing migrations {}, run them with --migrate-only",
+            pending.join(", ")
+        ));
     }
+    Ok(())
 }
 
 pub fn get_connection_pool() -> Pool<ConnectionManager<PgConnection>> {
@@ -339,34 +1022,3 @@ pub fn get_connection_pool() -> Pool<ConnectionManager<PgConnection>> {
         .build(manager)
         .expect("Could not build connection pool")
 }
-
-async fn load_config() -> Result<Config, serde_yaml::Error> {
-    let config: Config = Config {
-        discord: DiscordConfig {
-            token: env::var("DISCORD_TOKEN").expect("DISCORD_TOKEN not defined"),
-            admin_role_id: env::var("DISCORD_ADMIN_ROLE_ID")
-                .expect("DISCORD_ADMIN_ROLE_ID not defined")
-                .parse()
-                .unwrap(),
-            application_id: env::var("DISCORD_APPLICATION_ID")
-                .expect("DISCORD_APPLICATION_ID not defined")
-                .parse()
-                .unwrap(),
-            guild_id: env::var("DISCORD_GUILD_ID")
-                .expect("DISCORD_GUILD_ID not defined")
-                .parse()
-                .unwrap(),
-        },
-        dathost: DathostConfig {
-            user: env::var("DATHOST_USER")
-                .expect("DATHOST_USER not defined")
-                .parse()
-                .unwrap(),
-            password: env::var("DATHOST_PASSWORD")
-                .expect("DATHOST_PASSWORD not defined")
-                .parse()
-                .unwrap(),
-        },
-    };
-    Ok(config)
-}
@@ -0,0 +1,215 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::Router;
+use csgo_matchbot::config::MetricsConfig;
+use csgo_matchbot::veto::Event;
+use csgo_matchbot::{count_free_tokens, count_tokens};
+use diesel::PgConnection;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use r2d2::Pool;
+use r2d2_diesel::ConnectionManager;
+use reqwest::{Error, Response};
+use serenity::prelude::TypeMapKey;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Bot health & match throughput, the GSLT pool & DB pool gauges are read on every scrape
+pub struct Metrics {
+    registry: Registry,
+    active_setups: IntGauge,
+    setup_steps: IntCounterVec,
+    provider_latency: HistogramVec,
+    provider_errors: IntCounterVec,
+    interaction_errors: IntCounterVec,
+    gslt_tokens: IntGaugeVec,
+    db_connections: IntGaugeVec,
+}
+
+impl TypeMapKey for Metrics {
+    type Value = Arc<Metrics>;
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        let metrics = Metrics {
+            registry: Registry::new_custom(Some(String::from("matchbot")), None)
+                .expect("Expected metrics registry"),
+            active_setups: IntGauge::new("active_setups", "Match setups in progress")
+                .expect("Expected metric"),
+            setup_steps: IntCounterVec::new(
+                Opts::new("setup_steps_total", "Setup steps processed, by event"),
+                &["event"],
+            )
+            .expect("Expected metric"),
+            provider_latency: HistogramVec::new(
+                HistogramOpts::new(
+                    "provider_request_duration_seconds",
+                    "Dathost API request latency, by endpoint",
+                ),
+                &["endpoint"],
+            )
+            .expect("Expected metric"),
+            provider_errors: IntCounterVec::new(
+                Opts::new(
+                    "provider_errors_total",
+                    "Failed or unsuccessful Dathost API requests, by endpoint",
+                ),
+                &["endpoint"],
+            )
+            .expect("Expected metric"),
+            interaction_errors: IntCounterVec::new(
+                Opts::new(
+                    "interaction_errors_total",
+                    "Discord interaction responses that failed, by interaction",
+                ),
+                &["interaction"],
+            )
+            .expect("Expected metric"),
+            gslt_tokens: IntGaugeVec::new(
+                Opts::new("gslt_tokens", "GSLT tokens in the pool, by state"),
+                &["state"],
+            )
+            .expect("Expected metric"),
+            db_connections: IntGaugeVec::new(
+                Opts::new("db_connections", "Database pool connections, by state"),
+                &["state"],
+            )
+            .expect("Expected metric"),
+        };
+        for collector in [
+            Box::new(metrics.active_setups.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(metrics.setup_steps.clone()),
+            Box::new(metrics.provider_latency.clone()),
+            Box::new(metrics.provider_errors.clone()),
+            Box::new(metrics.interaction_errors.clone()),
+            Box::new(metrics.gslt_tokens.clone()),
+            Box::new(metrics.db_connections.clone()),
+        ] {
+            metrics
+                .registry
+                .register(collector)
+                .expect("Expected metric to register");
+        }
+        metrics
+    }
+}
+
+/// Counts a setup as active until dropped
+pub struct ActiveSetup(IntGauge);
+
+impl Drop for ActiveSetup {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+impl Metrics {
+    pub fn setup_started(&self) -> ActiveSetup {
+        self.active_setups.inc();
+        ActiveSetup(self.active_setups.clone())
+    }
+
+    pub fn setup_step(&self, event: &Event) {
+        let event = match event {
+            Event::ServerRanked { .. } => "server_ranked",
+            Event::ServerPicked { .. } => "server_picked",
+            Event::MapBanned { .. } => "map_banned",
+            Event::MapPicked { .. } => "map_picked",
+            Event::SidePicked { .. } => "side_picked",
+            Event::Undone { .. } => "undone",
+        };
+        self.setup_steps.with_label_values(&[event]).inc();
+    }
+
+    pub fn interaction_error(&self, interaction: &str) {
+        self.interaction_errors
+            .with_label_values(&[interaction])
+            .inc();
+    }
+
+    /// Times the Dathost request, counting errors & unsuccessful statuses against the endpoint
+    pub async fn provider_request(
+        &self,
+        endpoint: &str,
+        request: impl Future<Output = Result<Response, Error>>,
+    ) -> Result<Response, Error> {
+        let timer = self
+            .provider_latency
+            .with_label_values(&[endpoint])
+            .start_timer();
+        let resp = request.await;
+        timer.observe_duration();
+        match &resp {
+            Ok(resp) if resp.status().is_success() => {}
+            _ => self.provider_errors.with_label_values(&[endpoint]).inc(),
+        }
+        resp
+    }
+
+    fn encode(&self, pool: &Pool<ConnectionManager<PgConnection>>) -> Result<String, String> {
+        let state = pool.state();
+        self.db_connections
+            .with_label_values(&["idle"])
+            .set(state.idle_connections as i64);
+        self.db_connections
+            .with_label_values(&["in_use"])
+            .set((state.connections - state.idle_connections) as i64);
+        self.db_connections
+            .with_label_values(&["max"])
+            .set(pool.max_size() as i64);
+        let conn = pool.get().map_err(|err| err.to_string())?;
+        let total = count_tokens(&conn);
+        let free = count_free_tokens(&conn);
+        self.gslt_tokens.with_label_values(&["free"]).set(free);
+        self.gslt_tokens
+            .with_label_values(&["in_use"])
+            .set(total - free);
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|err| err.to_string())?;
+        String::from_utf8(buffer).map_err(|err| err.to_string())
+    }
+}
+
+type MetricsState = (Arc<Metrics>, Pool<ConnectionManager<PgConnection>>);
+
+/// Serves the metrics for Prometheus to scrape until the bot shuts down
+pub async fn serve(
+    config: MetricsConfig,
+    metrics: Arc<Metrics>,
+    pool: Pool<ConnectionManager<PgConnection>>,
+) {
+    let addr: SocketAddr = config
+        .bind_addr
+        .parse()
+        .expect("METRICS_BIND_ADDR is not a valid socket address");
+    let app = Router::new()
+        .route("/metrics", get(scrape))
+        .with_state((metrics, pool));
+    info!(%addr, "serving metrics");
+    if let Err(err) = axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await
+    {
+        error!(error = %err, "metrics server error");
+    }
+}
+
+async fn scrape(State((metrics, pool)): State<MetricsState>) -> Result<String, StatusCode> {
+    // checking out a connection & counting the tokens blocks
+    tokio::task::spawn_blocking(move || metrics.encode(&pool))
+        .await
+        .map_err(|err| err.to_string())
+        .and_then(|encoded| encoded)
+        .map_err(|err| {
+            error!(error = %err, "unable to gather metrics");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
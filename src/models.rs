@@ -1,8 +1,16 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::str::FromStr;
 
-use super::schema::{match_setup_step, matches, series_map, users};
+use super::schema::{
+    bracket_matches, bracket_teams, brackets, demos, divisions, gslt_tokens, guild_settings, maps,
+    match_edits, match_servers, match_setup_step, matches, player_map_stats, provider_credentials,
+    season_maps, season_teams, seasons, series_map, teams, users,
+};
+use crate::bracket::BracketError;
+use crate::models::BracketFormat::{Double, Gsl, Single};
+use crate::models::MatchState::{Archived, Completed, Entered, Scheduled};
 use crate::models::SeriesType::{Bo1, Bo3, Bo5};
 
 pub use enums::*;
@@ -32,6 +40,10 @@ pub struct NewMatch<'a> {
     pub series_type: &'a SeriesType,
     pub date_added: &'a NaiveDateTime,
     pub match_state: &'a MatchState,
+    pub round: Option<i32>,
+    pub deadline: Option<NaiveDateTime>,
+    pub season_id: Option<i32>,
+    pub division_id: Option<i32>,
 }
 
 #[derive(Queryable, Clone, Serialize, Deserialize)]
@@ -46,6 +58,307 @@ pub struct Match {
     pub match_state: MatchState,
     pub scheduled_time_str: Option<String>,
     pub series_type: SeriesType,
+    pub archived_at: Option<NaiveDateTime>,
+    /// Discord ID of the admin who archived the match
+    pub archived_by: Option<i64>,
+    pub archive_reason: Option<String>,
+    /// State the match is restored to
+    pub archived_from_state: Option<MatchState>,
+    /// Week of the season the match was generated or imported for
+    pub round: Option<i32>,
+    /// When the match has to be played by
+    pub deadline: Option<NaiveDateTime>,
+    pub winner_role_id: Option<i64>,
+    /// Maps won by team one, if the result was reported with a score
+    pub team_one_score: Option<i32>,
+    pub team_two_score: Option<i32>,
+    /// Season & division the match is played in
+    pub season_id: Option<i32>,
+    pub division_id: Option<i32>,
+}
+
+/// Edits to a match, `None` fields are left unchanged
+#[derive(AsChangeset, Default)]
+#[table_name = "matches"]
+pub struct MatchChanges {
+    pub team_one_role_id: Option<i64>,
+    pub team_one_name: Option<String>,
+    pub team_two_role_id: Option<i64>,
+    pub team_two_name: Option<String>,
+    pub note: Option<String>,
+    pub scheduled_time_str: Option<String>,
+    pub series_type: Option<SeriesType>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MatchEditError {
+    Archived,
+    NoChanges,
+    SameTeams,
+    /// The field is fixed once the match's veto is completed
+    SetupCompleted(&'static str),
+    /// The field is fixed while the match's veto is in progress
+    SetupInProgress(&'static str),
+}
+
+impl fmt::Display for MatchEditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatchEditError::Archived => write!(f, "archived matches have to be restored first"),
+            MatchEditError::NoChanges => write!(f, "nothing to change"),
+            MatchEditError::SameTeams => write!(f, "a team cannot play itself"),
+            MatchEditError::SetupCompleted(field) => {
+                write!(f, "the {} cannot change after the veto is completed", field)
+            }
+            MatchEditError::SetupInProgress(field) => {
+                write!(
+                    f,
+                    "the {} cannot change while the veto is in progress",
+                    field
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for MatchEditError {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ResultError {
+    Archived,
+    AlreadyReported,
+    /// The winner is not one of the match's teams
+    NotInMatch,
+    /// The winner has to win more maps than the loser
+    ScoreMismatch,
+    Bracket(BracketError),
+}
+
+impl fmt::Display for ResultError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResultError::Archived => write!(f, "archived matches have to be restored first"),
+            ResultError::AlreadyReported => write!(f, "the result was already reported"),
+            ResultError::NotInMatch => write!(f, "the winner does not play in this match"),
+            ResultError::ScoreMismatch => write!(f, "the winner has to win more maps"),
+            ResultError::Bracket(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ResultError {}
+
+impl Match {
+    /// Entered match of the teams, every other field unset
+    pub fn new(
+        id: i32,
+        team_one: (i64, &str),
+        team_two: (i64, &str),
+        series_type: SeriesType,
+        date_added: NaiveDateTime,
+    ) -> Self {
+        Match {
+            id,
+            team_one_role_id: team_one.0,
+            team_one_name: String::from(team_one.1),
+            team_two_role_id: team_two.0,
+            team_two_name: String::from(team_two.1),
+            note: None,
+            date_added,
+            match_state: Entered,
+            scheduled_time_str: None,
+            series_type,
+            archived_at: None,
+            archived_by: None,
+            archive_reason: None,
+            archived_from_state: None,
+            round: None,
+            deadline: None,
+            winner_role_id: None,
+            team_one_score: None,
+            team_two_score: None,
+            season_id: None,
+            division_id: None,
+        }
+    }
+
+    /// Checks the result can be reported, `score` is the maps won by team one & team two
+    pub fn check_result(&self, winner: i64, score: Option<(i32, i32)>) -> Result<(), ResultError> {
+        if self.match_state == MatchState::Archived {
+            return Err(ResultError::Archived);
+        }
+        if self.winner_role_id.is_some() {
+            return Err(ResultError::AlreadyReported);
+        }
+        if winner != self.team_one_role_id && winner != self.team_two_role_id {
+            return Err(ResultError::NotInMatch);
+        }
+        let winner_score = |(one, two)| {
+            if winner == self.team_one_role_id {
+                (one, two)
+            } else {
+                (two, one)
+            }
+        };
+        if let Some((won, lost)) = score.map(winner_score) {
+            if won <= lost {
+                return Err(ResultError::ScoreMismatch);
+            }
+        }
+        Ok(())
+    }
+
+    /// Team that lost the match, once its result is reported
+    pub fn loser_role_id(&self) -> Option<i64> {
+        self.winner_role_id.map(|winner| {
+            if winner == self.team_one_role_id {
+                self.team_two_role_id
+            } else {
+                self.team_one_role_id
+            }
+        })
+    }
+}
+
+/// Field of a match changed by an edit, with its previous & new value
+#[derive(Debug, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+impl MatchChanges {
+    /// Checks the edits are allowed in the match's state & returns the fields they change,
+    /// `setup_started` is whether the match has setup steps
+    pub fn field_changes(
+        &self,
+        m: &Match,
+        setup_started: bool,
+    ) -> Result<Vec<FieldChange>, MatchEditError> {
+        if m.match_state == MatchState::Archived {
+            return Err(MatchEditError::Archived);
+        }
+        let team = |id: i64, name: &str| Some(format!("{} ({})", name, id));
+        let mut changes = Vec::new();
+        let mut push = |field, old_value: Option<String>, new_value: Option<String>| {
+            if new_value.is_some() && old_value != new_value {
+                changes.push(FieldChange {
+                    field,
+                    old_value,
+                    new_value,
+                });
+            }
+        };
+        push(
+            "team one",
+            team(m.team_one_role_id, &m.team_one_name),
+            self.team_one_role_id
+                .and_then(|id| team(id, self.team_one_name.as_deref().unwrap_or_default())),
+        );
+        push(
+            "team two",
+            team(m.team_two_role_id, &m.team_two_name),
+            self.team_two_role_id
+                .and_then(|id| team(id, self.team_two_name.as_deref().unwrap_or_default())),
+        );
+        push(
+            "series type",
+            Some(m.series_type.to_string()),
+            self.series_type.map(|s| s.to_string()),
+        );
+        push("note", m.note.clone(), self.note.clone());
+        push(
+            "scheduled time",
+            m.scheduled_time_str.clone(),
+            self.scheduled_time_str.clone(),
+        );
+        let team_one = self.team_one_role_id.unwrap_or(m.team_one_role_id);
+        let team_two = self.team_two_role_id.unwrap_or(m.team_two_role_id);
+        if team_one == team_two {
+            return Err(MatchEditError::SameTeams);
+        }
+        if m.match_state == MatchState::Completed {
+            // teams can still be renamed, their role IDs are part of the veto history
+            if team_one != m.team_one_role_id || team_two != m.team_two_role_id {
+                return Err(MatchEditError::SetupCompleted("teams"));
+            }
+            if changes.iter().any(|c| c.field == "series type") {
+                return Err(MatchEditError::SetupCompleted("series type"));
+            }
+        } else if setup_started {
+            if team_one != m.team_one_role_id || team_two != m.team_two_role_id {
+                return Err(MatchEditError::SetupInProgress("teams"));
+            }
+            if changes.iter().any(|c| c.field == "series type") {
+                return Err(MatchEditError::SetupInProgress("series type"));
+            }
+        }
+        if changes.is_empty() {
+            return Err(MatchEditError::NoChanges);
+        }
+        Ok(changes)
+    }
+}
+
+/// Parses a schedule given as `Month/Day/Year @ Time <Timezone>`, the time in 24 or 12 hour
+/// format & the timezone optional, into its normalized form, e.g. `08/30/2022 @ 18:00 CET`
+pub fn parse_schedule(value: &str) -> Option<String> {
+    let (date, time) = value.split_once('@')?;
+    let date = NaiveDate::parse_from_str(date.trim(), "%m/%d/%Y").ok()?;
+    let mut parts: Vec<&str> = time.split_whitespace().collect();
+    let mut clock = parts.first()?.to_uppercase();
+    parts.remove(0);
+    if let Some(meridiem) = parts
+        .first()
+        .filter(|p| ["AM", "PM"].contains(&&*p.to_uppercase()))
+    {
+        clock.push_str(&meridiem.to_uppercase());
+        parts.remove(0);
+    }
+    let time = NaiveTime::parse_from_str(&clock, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(&clock, "%I:%M%p"))
+        .ok()?;
+    let zone = match parts.as_slice() {
+        [] => String::new(),
+        [zone]
+            if zone
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "+-:".contains(c)) =>
+        {
+            format!(" {}", zone.to_uppercase())
+        }
+        _ => return None,
+    };
+    Some(format!(
+        "{} @ {}{}",
+        date.format("%m/%d/%Y"),
+        time.format("%H:%M"),
+        zone
+    ))
+}
+
+/// Audit log entry of a match edit, `edited_by` is empty for edits made with `matchbot-admin`
+#[derive(Queryable, Clone, Debug)]
+pub struct MatchEdit {
+    pub id: i32,
+    pub match_id: i32,
+    pub edited_by: Option<i64>,
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub edited_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "match_edits"]
+pub struct NewMatchEdit {
+    pub match_id: i32,
+    pub edited_by: Option<i64>,
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub edited_at: NaiveDateTime,
 }
 
 #[derive(Queryable, Clone, Serialize, Deserialize, Debug)]
@@ -86,23 +399,321 @@ pub struct NewSeriesMap {
     pub start_defense_team_role_id: Option<i64>,
 }
 
-#[derive(Queryable, Clone, Serialize, Deserialize)]
+#[derive(Queryable, Insertable, AsChangeset, Clone, Debug, Serialize, Deserialize)]
+#[table_name = "demos"]
+pub struct Demo {
+    pub series_map_id: i32,
+    pub location: String,
+    pub size_bytes: i64,
+    pub archived_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Insertable, AsChangeset, Clone, Serialize, Deserialize)]
+#[table_name = "match_servers"]
 pub struct MatchServer {
     pub region_label: String,
     pub server_id: String,
+    /// Where the server is hosted, shown to teams as a latency hint
+    pub location: Option<String>,
+    /// Player slots of the server
+    pub capacity: Option<i32>,
+    /// Disabled servers are kept but not offered during setup
+    pub enabled: bool,
 }
 
-#[derive(Queryable, Clone, Serialize, Deserialize)]
+/// Pooled token, `token` is stored encrypted & only decrypted by the token queries
+#[derive(Queryable, Clone)]
 pub struct GsltToken {
     pub token: String,
     pub in_use: bool,
+    /// Match whose server the token was assigned to
+    pub match_id: Option<i32>,
+    pub assigned_at: Option<NaiveDateTime>,
+    pub id: i32,
+    pub token_hash: String,
 }
 
-#[derive(Queryable, Clone, Serialize, Deserialize)]
+#[derive(Insertable)]
+#[table_name = "gslt_tokens"]
+pub struct NewGsltToken {
+    pub token: String,
+    pub token_hash: String,
+    pub in_use: bool,
+}
+
+impl GsltToken {
+    /// The token's last characters, enough to tell tokens apart without revealing them
+    pub fn masked(&self) -> String {
+        let shown = self.token.len().saturating_sub(4);
+        format!("****{}", self.token.get(shown..).unwrap_or_default())
+    }
+}
+
+/// Guild's own Dathost account, `dathost_password` is stored encrypted
+#[derive(Queryable, Insertable, AsChangeset, Clone)]
+#[table_name = "provider_credentials"]
+pub struct ProviderCredentials {
+    pub guild_id: i64,
+    pub dathost_user: String,
+    pub dathost_password: String,
+}
+
+#[derive(Queryable, Insertable, AsChangeset, Clone, Serialize, Deserialize)]
+#[table_name = "guild_settings"]
+pub struct GuildSettings {
+    pub guild_id: i64,
+    pub confirm_veto: bool,
+    /// Channel admin warnings, i.e. a low GSLT pool, are posted in
+    pub admin_channel_id: Option<i64>,
+    /// Free GSLT count below which admins are warned
+    pub gslt_warn_threshold: i32,
+}
+
+impl GuildSettings {
+    pub fn new(guild_id: i64) -> Self {
+        Self {
+            guild_id,
+            confirm_veto: false,
+            admin_channel_id: None,
+            gslt_warn_threshold: 2,
+        }
+    }
+}
+
+#[derive(Queryable, Insertable, AsChangeset, Clone, Serialize, Deserialize)]
+#[table_name = "teams"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct Team {
+    pub role_id: i64,
+    pub name: String,
+    pub captain_discord_id: Option<i64>,
+    pub vice_captain_discord_id: Option<i64>,
+}
+
+impl Team {
+    /// Checks if the discord user is this team's captain or vice-captain
+    pub fn is_captain(&self, discord_id: i64) -> bool {
+        self.captain_discord_id == Some(discord_id)
+            || self.vice_captain_discord_id == Some(discord_id)
+    }
+
+    pub fn has_captains(&self) -> bool {
+        self.captain_discord_id.is_some() || self.vice_captain_discord_id.is_some()
+    }
+}
+
+#[derive(Queryable, Insertable, AsChangeset, Clone, Debug, Serialize, Deserialize)]
+#[table_name = "player_map_stats"]
+pub struct PlayerMapStats {
+    pub steam_id: String,
+    pub series_map_id: i32,
+    pub team_role_id: i64,
+    pub kills: i32,
+    pub deaths: i32,
+    pub assists: i32,
+    pub headshot_kills: i32,
+    pub damage: i32,
+    pub rounds_played: i32,
+}
+
+/// Player stats summed over any number of maps
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerStatsTotals {
+    pub maps: i64,
+    pub kills: i64,
+    pub deaths: i64,
+    pub assists: i64,
+    pub headshot_kills: i64,
+    pub damage: i64,
+    pub rounds_played: i64,
+}
+
+impl PlayerStatsTotals {
+    pub fn add(&mut self, stats: &PlayerMapStats) {
+        self.maps += 1;
+        self.kills += i64::from(stats.kills);
+        self.deaths += i64::from(stats.deaths);
+        self.assists += i64::from(stats.assists);
+        self.headshot_kills += i64::from(stats.headshot_kills);
+        self.damage += i64::from(stats.damage);
+        self.rounds_played += i64::from(stats.rounds_played);
+    }
+
+    /// Kills per death, counting no deaths as one
+    pub fn kd(&self) -> f64 {
+        self.kills as f64 / self.deaths.max(1) as f64
+    }
+
+    /// Average damage per round
+    pub fn adr(&self) -> f64 {
+        if self.rounds_played == 0 {
+            return 0.0;
+        }
+        self.damage as f64 / self.rounds_played as f64
+    }
+
+    /// Percentage of kills that were headshots
+    pub fn hs_percent(&self) -> f64 {
+        if self.kills == 0 {
+            return 0.0;
+        }
+        self.headshot_kills as f64 * 100.0 / self.kills as f64
+    }
+}
+
+impl<'a> FromIterator<&'a PlayerMapStats> for PlayerStatsTotals {
+    fn from_iter<I: IntoIterator<Item = &'a PlayerMapStats>>(iter: I) -> Self {
+        let mut totals = PlayerStatsTotals::default();
+        for stats in iter {
+            totals.add(stats);
+        }
+        totals
+    }
+}
+
+#[derive(Queryable, Clone, Debug, Serialize, Deserialize)]
+pub struct Bracket {
+    pub id: i32,
+    pub name: String,
+    pub format: BracketFormat,
+    /// Series type of the matches the bracket starts
+    pub series_type: SeriesType,
+    pub created_at: NaiveDateTime,
+    pub season_id: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[table_name = "brackets"]
+pub struct NewBracket<'a> {
+    pub name: &'a str,
+    pub format: BracketFormat,
+    pub series_type: SeriesType,
+    pub created_at: NaiveDateTime,
+    pub season_id: Option<i32>,
+}
+
+#[derive(Queryable, Insertable, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[table_name = "bracket_teams"]
+pub struct BracketTeam {
+    pub bracket_id: i32,
+    pub role_id: i64,
+    pub name: String,
+    pub seed: i32,
+}
+
+/// Match of a bracket, its teams are known once the matches they come from are decided. Teams
+/// missing from a bracket are byes, a team facing a bye moves on without playing.
+#[derive(
+    Queryable, Insertable, AsChangeset, Clone, Debug, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[table_name = "bracket_matches"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct BracketMatch {
+    pub bracket_id: i32,
+    /// Index of the match in its bracket
+    pub seq: i32,
+    pub section: BracketSection,
+    /// GSL group, counting from 0
+    pub group_number: Option<i32>,
+    pub round: i32,
+    pub position: i32,
+    pub team_one_role_id: Option<i64>,
+    pub team_two_role_id: Option<i64>,
+    pub team_one_bye: bool,
+    pub team_two_bye: bool,
+    /// Bracket match & slot the winner moves on to, none when the winner leaves the bracket
+    pub winner_to: Option<i32>,
+    pub winner_slot: Option<i32>,
+    /// Bracket match & slot the loser moves on to, none when the loser is eliminated
+    pub loser_to: Option<i32>,
+    pub loser_slot: Option<i32>,
+    pub winner_role_id: Option<i64>,
+    /// Match started once both teams are known
+    pub match_id: Option<i32>,
+}
+
+#[derive(Queryable, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "maps"]
 pub struct Map {
     pub name: String,
 }
 
+#[derive(Queryable, Clone, Debug, Serialize, Deserialize)]
+pub struct Season {
+    pub id: i32,
+    pub name: String,
+    /// Season new matches are added to & commands default to
+    pub active: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "seasons"]
+pub struct NewSeason<'a> {
+    pub name: &'a str,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Clone, Debug, Serialize, Deserialize)]
+pub struct Division {
+    pub id: i32,
+    pub season_id: i32,
+    pub name: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "divisions"]
+pub struct NewDivision<'a> {
+    pub season_id: i32,
+    pub name: &'a str,
+}
+
+/// Team playing in a season, in one of its divisions or none
+#[derive(
+    Queryable, Insertable, AsChangeset, Clone, Debug, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[table_name = "season_teams"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct SeasonTeam {
+    pub season_id: i32,
+    pub role_id: i64,
+    pub division_id: Option<i32>,
+}
+
+impl SeasonTeam {
+    /// Division both teams play in, none when either team has no division or they differ
+    pub fn shared_division(
+        season_teams: &[SeasonTeam],
+        team_one: i64,
+        team_two: i64,
+    ) -> Option<i32> {
+        let division = |role_id| {
+            season_teams
+                .iter()
+                .find(|t| t.role_id == role_id)
+                .and_then(|t| t.division_id)
+        };
+        match (division(team_one), division(team_two)) {
+            (Some(one), Some(two)) if one == two => Some(one),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Queryable, Insertable, Clone, Debug, Serialize, Deserialize)]
+#[table_name = "season_maps"]
+pub struct SeasonMap {
+    pub season_id: i32,
+    pub map: String,
+}
+
+/// Season & division matches are listed from, `None` includes every season or division
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchScope {
+    pub season_id: Option<i32>,
+    pub division_id: Option<i32>,
+}
+
 mod enums {
     // The DbEnum derive macro has some clippy issues, and isn't something we can fix here.
     #![allow(clippy::from_over_into)]
@@ -135,6 +746,20 @@ mod enums {
         Pick,
     }
 
+    impl fmt::Display for SeriesType {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "{}",
+                match self {
+                    SeriesType::Bo1 => "bo1",
+                    SeriesType::Bo3 => "bo3",
+                    SeriesType::Bo5 => "bo5",
+                }
+            )
+        }
+    }
+
     impl fmt::Display for StepType {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             write!(
@@ -159,6 +784,50 @@ mod enums {
         Entered,
         Scheduled,
         Completed,
+        /// Deleted by an admin, kept with its history until restored
+        Archived,
+    }
+
+    #[derive(
+        Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow, DbEnum, Serialize, Deserialize,
+    )]
+    #[sql_type = "VarChar"]
+    #[error_fn = "CustomError::not_found"]
+    #[error_type = "CustomError"]
+    pub enum BracketFormat {
+        /// Single elimination
+        Single,
+        /// Double elimination with a grand final
+        Double,
+        /// GSL groups of four, the top two teams of each group advance
+        Gsl,
+    }
+
+    #[derive(
+        Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow, DbEnum, Serialize, Deserialize,
+    )]
+    #[sql_type = "VarChar"]
+    #[error_fn = "CustomError::not_found"]
+    #[error_type = "CustomError"]
+    pub enum BracketSection {
+        Upper,
+        Lower,
+        Final,
+        Group,
+    }
+
+    impl fmt::Display for BracketFormat {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "{}",
+                match self {
+                    BracketFormat::Single => "single elimination",
+                    BracketFormat::Double => "double elimination",
+                    BracketFormat::Gsl => "GSL groups",
+                }
+            )
+        }
     }
 
     #[derive(Debug)]
@@ -185,3 +854,28 @@ impl FromStr for SeriesType {
         }
     }
 }
+
+impl FromStr for MatchState {
+    type Err = ();
+    fn from_str(input: &str) -> Result<MatchState, Self::Err> {
+        match input {
+            "entered" => Ok(Entered),
+            "scheduled" => Ok(Scheduled),
+            "completed" => Ok(Completed),
+            "archived" => Ok(Archived),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromStr for BracketFormat {
+    type Err = ();
+    fn from_str(input: &str) -> Result<BracketFormat, Self::Err> {
+        match input {
+            "single" => Ok(Single),
+            "double" => Ok(Double),
+            "gsl" => Ok(Gsl),
+            _ => Err(()),
+        }
+    }
+}
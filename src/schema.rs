@@ -1,7 +1,78 @@
 table! {
-    gslt_tokens (token) {
+    bracket_matches (bracket_id, seq) {
+        bracket_id -> Int4,
+        seq -> Int4,
+        section -> Varchar,
+        group_number -> Nullable<Int4>,
+        round -> Int4,
+        position -> Int4,
+        team_one_role_id -> Nullable<Int8>,
+        team_two_role_id -> Nullable<Int8>,
+        team_one_bye -> Bool,
+        team_two_bye -> Bool,
+        winner_to -> Nullable<Int4>,
+        winner_slot -> Nullable<Int4>,
+        loser_to -> Nullable<Int4>,
+        loser_slot -> Nullable<Int4>,
+        winner_role_id -> Nullable<Int8>,
+        match_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    bracket_teams (bracket_id, role_id) {
+        bracket_id -> Int4,
+        role_id -> Int8,
+        name -> Varchar,
+        seed -> Int4,
+    }
+}
+
+table! {
+    brackets (id) {
+        id -> Int4,
+        name -> Varchar,
+        format -> Varchar,
+        series_type -> Varchar,
+        created_at -> Timestamp,
+        season_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    demos (series_map_id) {
+        series_map_id -> Int4,
+        location -> Varchar,
+        size_bytes -> Int8,
+        archived_at -> Timestamp,
+    }
+}
+
+table! {
+    divisions (id) {
+        id -> Int4,
+        season_id -> Int4,
+        name -> Varchar,
+    }
+}
+
+table! {
+    gslt_tokens (id) {
         token -> Varchar,
         in_use -> Bool,
+        match_id -> Nullable<Int4>,
+        assigned_at -> Nullable<Timestamp>,
+        id -> Int4,
+        token_hash -> Varchar,
+    }
+}
+
+table! {
+    guild_settings (guild_id) {
+        guild_id -> Int8,
+        confirm_veto -> Bool,
+        admin_channel_id -> Nullable<Int8>,
+        gslt_warn_threshold -> Int4,
     }
 }
 
@@ -11,10 +82,25 @@ table! {
     }
 }
 
+table! {
+    match_edits (id) {
+        id -> Int4,
+        match_id -> Int4,
+        edited_by -> Nullable<Int8>,
+        field -> Varchar,
+        old_value -> Nullable<Varchar>,
+        new_value -> Nullable<Varchar>,
+        edited_at -> Timestamp,
+    }
+}
+
 table! {
     match_servers (region_label) {
         region_label -> Varchar,
         server_id -> Varchar,
+        location -> Nullable<Varchar>,
+        capacity -> Nullable<Int4>,
+        enabled -> Bool,
     }
 }
 
@@ -40,6 +126,63 @@ table! {
         match_state -> Varchar,
         scheduled_time_str -> Nullable<Varchar>,
         series_type -> Varchar,
+        archived_at -> Nullable<Timestamp>,
+        archived_by -> Nullable<Int8>,
+        archive_reason -> Nullable<Varchar>,
+        archived_from_state -> Nullable<Varchar>,
+        round -> Nullable<Int4>,
+        deadline -> Nullable<Timestamp>,
+        winner_role_id -> Nullable<Int8>,
+        team_one_score -> Nullable<Int4>,
+        team_two_score -> Nullable<Int4>,
+        season_id -> Nullable<Int4>,
+        division_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    player_map_stats (steam_id, series_map_id) {
+        steam_id -> Varchar,
+        series_map_id -> Int4,
+        team_role_id -> Int8,
+        kills -> Int4,
+        deaths -> Int4,
+        assists -> Int4,
+        headshot_kills -> Int4,
+        damage -> Int4,
+        rounds_played -> Int4,
+    }
+}
+
+table! {
+    provider_credentials (guild_id) {
+        guild_id -> Int8,
+        dathost_user -> Varchar,
+        dathost_password -> Varchar,
+    }
+}
+
+table! {
+    season_maps (season_id, map) {
+        season_id -> Int4,
+        map -> Varchar,
+    }
+}
+
+table! {
+    season_teams (season_id, role_id) {
+        season_id -> Int4,
+        role_id -> Int8,
+        division_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    seasons (id) {
+        id -> Int4,
+        name -> Varchar,
+        active -> Bool,
+        created_at -> Timestamp,
     }
 }
 
@@ -54,6 +197,15 @@ table! {
     }
 }
 
+table! {
+    teams (role_id) {
+        role_id -> Int8,
+        name -> Varchar,
+        captain_discord_id -> Nullable<Int8>,
+        vice_captain_discord_id -> Nullable<Int8>,
+    }
+}
+
 table! {
     users (id) {
         id -> Int4,
@@ -62,15 +214,44 @@ table! {
     }
 }
 
+joinable!(bracket_matches -> brackets (bracket_id));
+joinable!(bracket_matches -> matches (match_id));
+joinable!(bracket_teams -> brackets (bracket_id));
+joinable!(brackets -> seasons (season_id));
+joinable!(demos -> series_map (series_map_id));
+joinable!(divisions -> seasons (season_id));
+joinable!(gslt_tokens -> matches (match_id));
+joinable!(match_edits -> matches (match_id));
 joinable!(match_setup_step -> matches (match_id));
+joinable!(matches -> divisions (division_id));
+joinable!(matches -> seasons (season_id));
+joinable!(player_map_stats -> series_map (series_map_id));
+joinable!(season_maps -> maps (map));
+joinable!(season_maps -> seasons (season_id));
+joinable!(season_teams -> divisions (division_id));
+joinable!(season_teams -> seasons (season_id));
+joinable!(season_teams -> teams (role_id));
 joinable!(series_map -> matches (match_id));
 
 allow_tables_to_appear_in_same_query!(
+    bracket_matches,
+    bracket_teams,
+    brackets,
+    demos,
+    divisions,
     gslt_tokens,
+    guild_settings,
     maps,
+    match_edits,
     match_servers,
     match_setup_step,
     matches,
+    player_map_stats,
+    provider_credentials,
+    season_maps,
+    season_teams,
+    seasons,
     series_map,
+    teams,
     users,
 );
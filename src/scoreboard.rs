@@ -0,0 +1,442 @@
+use crate::dathost_models::{DathostMatch, DathostMatchSeries};
+use crate::demos::archive_demo;
+use crate::metrics::Metrics;
+use crate::utils::{
+    get_config, get_live_matches, get_metrics, get_pg_conn, parse_steam_id_64,
+    print_started_matches,
+};
+use csgo_matchbot::config::DathostConfig;
+use csgo_matchbot::models::{PlayerMapStats, SeriesMap};
+use csgo_matchbot::veto::Setup;
+use csgo_matchbot::{
+    find_match, get_series_maps, get_users, release_match_tokens, report_match_result,
+    update_player_map_stats,
+};
+use reqwest::{Client, Error};
+use serenity::builder::CreateEmbed;
+use serenity::model::channel::Message;
+use serenity::model::id::ChannelId;
+use serenity::prelude::Context;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tracing::{error, info, instrument, warn};
+use urlencoding::encode;
+
+/// Consecutive failed polls after which the scoreboard stops updating
+const MAX_POLL_FAILURES: u32 = 20;
+const HALF_ROUNDS: i64 = 15;
+const OVERTIME_HALF_ROUNDS: i64 = 3;
+
+/// Dathost resource backing a started match, Bo1s run as a single match & longer series as a
+/// match series
+#[derive(Debug, Clone)]
+pub enum TrackedMatch {
+    Match(String),
+    Series(String),
+}
+
+/// Polls Dathost for the match, or refreshes on its webhook events, & keeps a scoreboard embed
+/// in the channel up to date, posting a summary whenever a map ends
+#[instrument(skip_all, fields(tracked = ?tracked))]
+pub async fn track_match(
+    context: Context,
+    channel_id: ChannelId,
+    setup: Setup,
+    tracked: TrackedMatch,
+    dathost_config: DathostConfig,
+) {
+    let live_matches = get_live_matches(&context).await;
+    let events = live_matches.events(setup.match_id);
+    update_scoreboard(
+        &context,
+        channel_id,
+        &setup,
+        &tracked,
+        &dathost_config,
+        events,
+    )
+    .await;
+    live_matches.remove(setup.match_id);
+    info!("stopped tracking match");
+}
+
+async fn update_scoreboard(
+    context: &Context,
+    channel_id: ChannelId,
+    setup: &Setup,
+    tracked: &TrackedMatch,
+    dathost_config: &DathostConfig,
+    events: Option<Arc<Notify>>,
+) {
+    let client = Client::new();
+    let metrics = get_metrics(context).await;
+    let timers = get_config(context).await.timers;
+    let team_names = dathost_team_names(setup);
+    let mut scoreboard: Option<Message> = None;
+    let mut maps_finished = 0;
+    let mut failures = 0;
+    loop {
+        match &events {
+            Some(events) => {
+                tokio::select! {
+                    _ = tokio::time::sleep(timers.webhook_poll_interval()) => {}
+                    _ = events.notified() => {}
+                }
+            }
+            None => tokio::time::sleep(timers.poll_interval()).await,
+        }
+        let series = match fetch_series(&client, &metrics, dathost_config, tracked).await {
+            Ok(series) => {
+                failures = 0;
+                series
+            }
+            Err(err) => {
+                warn!(error = %err, failures = failures + 1, "failed to poll match");
+                failures += 1;
+                if failures >= MAX_POLL_FAILURES {
+                    error!("giving up on scoreboard");
+                    return;
+                }
+                continue;
+            }
+        };
+        while maps_finished < series.matches.len() && series.matches[maps_finished].finished {
+            let finished = &series.matches[maps_finished];
+            match find_series_map(context, setup, finished).await {
+                Some(series_map) => {
+                    save_map_stats(context, setup, finished, &series_map).await;
+                    tokio::spawn(archive_demo(
+                        context.clone(),
+                        dathost_config.clone(),
+                        series_map,
+                        finished.game_server_id.clone(),
+                        finished.id.clone(),
+                    ));
+                }
+                None => warn!(map = %finished.map, "no series map, skipping stats & demo"),
+            }
+            let summary = map_summary(&series.matches[..=maps_finished], setup, &team_names);
+            if let Err(err) = channel_id.say(context, summary).await {
+                error!(error = %err, "unable to post map summary");
+            }
+            maps_finished += 1;
+        }
+        let current = series
+            .matches
+            .get(maps_finished)
+            .or_else(|| series.matches.last());
+        let current = match current {
+            Some(current) => current,
+            None => continue,
+        };
+        let embed = scoreboard_embed(
+            &series,
+            current,
+            maps_finished.min(series.matches.len() - 1),
+            setup,
+            &team_names,
+        );
+        match scoreboard.as_mut() {
+            Some(msg) => {
+                if let Err(err) = msg.edit(context, |m| m.set_embed(embed.clone())).await {
+                    error!(error = %err, "unable to update scoreboard");
+                }
+            }
+            None => match channel_id
+                .send_message(context, |m| m.set_embed(embed.clone()))
+                .await
+            {
+                Ok(msg) => scoreboard = Some(msg),
+                Err(err) => error!(error = %err, "unable to post scoreboard"),
+            },
+        }
+        if let Some(reason) = series
+            .cancel_reason
+            .as_ref()
+            .or(current.cancel_reason.as_ref())
+        {
+            if let Err(err) = channel_id
+                .say(context, format!("Match cancelled: `{}`", reason))
+                .await
+            {
+                error!(error = %err, "unable to post match cancellation");
+            }
+            info!(reason = %reason, "match cancelled");
+            release_match_tokens(&*get_pg_conn(context).await, setup.match_id);
+            return;
+        }
+        if series.finished {
+            info!("match finished");
+            report_series_result(context, channel_id, setup, &series).await;
+            return;
+        }
+    }
+}
+
+async fn fetch_series(
+    client: &Client,
+    metrics: &Metrics,
+    dathost_config: &DathostConfig,
+    tracked: &TrackedMatch,
+) -> Result<DathostMatchSeries, Error> {
+    match tracked {
+        TrackedMatch::Match(id) => {
+            let dathost_match = metrics
+                .provider_request(
+                    "get_match",
+                    client
+                        .get(format!(
+                            "https://dathost.net/api/0.1/matches/{}",
+                            encode(id)
+                        ))
+                        .basic_auth(&dathost_config.user, Some(&dathost_config.password))
+                        .send(),
+                )
+                .await?
+                .error_for_status()?
+                .json::<DathostMatch>()
+                .await?;
+            Ok(DathostMatchSeries {
+                id: dathost_match.id.clone(),
+                finished: dathost_match.finished,
+                cancel_reason: None,
+                matches: vec![dathost_match],
+            })
+        }
+        TrackedMatch::Series(id) => {
+            metrics
+                .provider_request(
+                    "get_match_series",
+                    client
+                        .get(format!(
+                            "https://dathost.net/api/0.1/match-series/{}",
+                            encode(id)
+                        ))
+                        .basic_auth(&dathost_config.user, Some(&dathost_config.password))
+                        .send(),
+                )
+                .await?
+                .error_for_status()?
+                .json::<DathostMatchSeries>()
+                .await
+        }
+    }
+}
+
+async fn find_series_map(
+    context: &Context,
+    setup: &Setup,
+    finished: &DathostMatch,
+) -> Option<SeriesMap> {
+    let conn = get_pg_conn(context).await;
+    get_series_maps(&conn, setup.match_id)
+        .into_iter()
+        .find(|m| m.map.eq_ignore_ascii_case(&finished.map))
+}
+
+/// Stores the player stats of a finished map, players without a registered Steam ID are skipped
+async fn save_map_stats(
+    context: &Context,
+    setup: &Setup,
+    finished: &DathostMatch,
+    series_map: &SeriesMap,
+) {
+    let conn = get_pg_conn(context).await;
+    let steam_ids: HashMap<u64, String> = get_users(&conn)
+        .into_iter()
+        .filter_map(|u| parse_steam_id_64(&u.steam_id).map(|id| (id, u.steam_id)))
+        .collect();
+    let team_one = team_steam_ids(&setup.team_one_conn_str);
+    let team_two = team_steam_ids(&setup.team_two_conn_str);
+    let stats: Vec<PlayerMapStats> = finished
+        .player_stats
+        .iter()
+        .filter_map(|p| {
+            let id = parse_steam_id_64(&p.steam_id)?;
+            let team_role_id = if team_one.contains(&id) {
+                setup.team_one
+            } else if team_two.contains(&id) {
+                setup.team_two
+            } else {
+                return None;
+            };
+            Some(PlayerMapStats {
+                steam_id: steam_ids.get(&id)?.clone(),
+                series_map_id: series_map.id,
+                team_role_id,
+                kills: p.kills,
+                deaths: p.deaths,
+                assists: p.assists,
+                headshot_kills: p.kills_with_headshot,
+                damage: p.damage_dealt,
+                rounds_played: finished.rounds_played as i32,
+            })
+        })
+        .collect();
+    update_player_map_stats(&conn, stats);
+}
+
+fn team_steam_ids(conn_str: &Option<String>) -> HashSet<u64> {
+    conn_str
+        .iter()
+        .flat_map(|s| s.split(','))
+        .filter_map(parse_steam_id_64)
+        .collect()
+}
+
+/// Bo1s start team one as Dathost's team2 when it starts CT
+fn dathost_teams_swapped(setup: &Setup) -> bool {
+    let team_one_starts_ct = setup
+        .maps
+        .first()
+        .map(|m| m.start_defense_team_role_id == Some(setup.team_one))
+        .unwrap_or(false);
+    setup.maps.len() == 1 && team_one_starts_ct
+}
+
+/// Names of Dathost's `team1` & `team2`, single matches put the T side team first
+fn dathost_team_names(setup: &Setup) -> (String, String) {
+    if dathost_teams_swapped(setup) {
+        (setup.team_two_name.clone(), setup.team_one_name.clone())
+    } else {
+        (setup.team_one_name.clone(), setup.team_two_name.clone())
+    }
+}
+
+/// Reports the winner & map score of a finished series, announcing the bracket matches it starts.
+/// Drawn series are left for an admin to report.
+async fn report_series_result(
+    context: &Context,
+    channel_id: ChannelId,
+    setup: &Setup,
+    series: &DathostMatchSeries,
+) {
+    let (team1, team2) = series_score(&series.matches);
+    let (team1, team2) = (team1 as i32, team2 as i32);
+    let (team1_role_id, team2_role_id, score) = if dathost_teams_swapped(setup) {
+        (setup.team_two, setup.team_one, (team2, team1))
+    } else {
+        (setup.team_one, setup.team_two, (team1, team2))
+    };
+    let winner = match team1.cmp(&team2) {
+        Ordering::Greater => team1_role_id,
+        Ordering::Less => team2_role_id,
+        Ordering::Equal => {
+            warn!("series drawn, leaving the result to an admin");
+            release_match_tokens(&*get_pg_conn(context).await, setup.match_id);
+            return;
+        }
+    };
+    let reported = {
+        let conn = get_pg_conn(context).await;
+        find_match(&conn, setup.match_id)
+            .map(|m| report_match_result(&conn, &m, winner, Some(score)))
+    };
+    match reported {
+        Some(Ok((_, started))) => {
+            info!(winner, "reported match result");
+            if !started.is_empty() {
+                if let Err(err) = channel_id
+                    .say(context, print_started_matches(&started))
+                    .await
+                {
+                    error!(error = %err, "unable to post started bracket matches");
+                }
+            }
+        }
+        Some(Err(err)) => warn!(error = %err, "unable to report match result"),
+        None => warn!("match no longer exists, result not reported"),
+    }
+}
+
+/// Maps won by `team1` & `team2`, only counting finished maps
+fn series_score(matches: &[DathostMatch]) -> (usize, usize) {
+    matches
+        .iter()
+        .filter(|m| m.finished)
+        .fold((0, 0), |(one, two), m| {
+            match m.team1_stats.score.cmp(&m.team2_stats.score) {
+                Ordering::Greater => (one + 1, two),
+                Ordering::Less => (one, two + 1),
+                Ordering::Equal => (one, two),
+            }
+        })
+}
+
+fn half_label(rounds_played: i64) -> String {
+    if rounds_played < HALF_ROUNDS {
+        return String::from("First half");
+    }
+    if rounds_played < HALF_ROUNDS * 2 {
+        return String::from("Second half");
+    }
+    let overtime_rounds = rounds_played - HALF_ROUNDS * 2;
+    let overtime = overtime_rounds / (OVERTIME_HALF_ROUNDS * 2) + 1;
+    let half = if overtime_rounds % (OVERTIME_HALF_ROUNDS * 2) < OVERTIME_HALF_ROUNDS {
+        "first"
+    } else {
+        "second"
+    };
+    format!("Overtime {} ({} half)", overtime, half)
+}
+
+fn map_summary(matches: &[DathostMatch], setup: &Setup, team_names: &(String, String)) -> String {
+    let finished = matches.last().unwrap();
+    let mut summary = format!(
+        "**{}** finished: {} **{}** - **{}** {}",
+        finished.map,
+        team_names.0,
+        finished.team1_stats.score,
+        finished.team2_stats.score,
+        team_names.1
+    );
+    if setup.maps.len() > 1 {
+        let (one, two) = series_score(matches);
+        summary.push_str(format!("\nSeries: {} - {}", one, two).as_str());
+    }
+    summary
+}
+
+fn scoreboard_embed(
+    series: &DathostMatchSeries,
+    current: &DathostMatch,
+    map_index: usize,
+    setup: &Setup,
+    team_names: &(String, String),
+) -> CreateEmbed {
+    let status = if series.finished {
+        "Finished"
+    } else if current.started {
+        "Live"
+    } else {
+        "Warmup"
+    };
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(format!("{} vs {}", team_names.0, team_names.1))
+        .description(status)
+        .field(
+            "Map",
+            format!("{} ({}/{})", current.map, map_index + 1, setup.maps.len()),
+            true,
+        )
+        .field(
+            "Score",
+            format!(
+                "{} **{}** - **{}** {}",
+                team_names.0, current.team1_stats.score, current.team2_stats.score, team_names.1
+            ),
+            true,
+        );
+    if setup.maps.len() > 1 {
+        let (one, two) = series_score(&series.matches);
+        embed.field("Series", format!("{} - {}", one, two), true);
+    }
+    if current.started && !current.finished {
+        embed.field("Half", half_label(current.rounds_played), true);
+    }
+    embed.footer(|f| f.text(format!("Match #{}", setup.match_id)));
+    embed
+}
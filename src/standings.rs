@@ -0,0 +1,89 @@
+//! League table of the reported match results
+
+use crate::models::Match;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Standing {
+    pub role_id: i64,
+    pub name: String,
+    pub played: i32,
+    pub wins: i32,
+    pub losses: i32,
+    pub maps_won: i32,
+    pub maps_lost: i32,
+}
+
+impl Standing {
+    fn new(role_id: i64, name: &str) -> Self {
+        Standing {
+            role_id,
+            name: String::from(name),
+            played: 0,
+            wins: 0,
+            losses: 0,
+            maps_won: 0,
+            maps_lost: 0,
+        }
+    }
+
+    pub fn map_difference(&self) -> i32 {
+        self.maps_won - self.maps_lost
+    }
+}
+
+/// Standings of the teams of matches with a result, by wins, map difference & maps won. Matches
+/// reported without a score count as a one map win.
+pub fn standings(matches: &[Match]) -> Vec<Standing> {
+    let mut table: HashMap<i64, Standing> = HashMap::new();
+    for m in matches {
+        let winner = match m.winner_role_id {
+            Some(winner) => winner,
+            None => continue,
+        };
+        let won_by_team_one = winner == m.team_one_role_id;
+        let (one, two) = match (m.team_one_score, m.team_two_score) {
+            (Some(one), Some(two)) => (one, two),
+            _ if won_by_team_one => (1, 0),
+            _ => (0, 1),
+        };
+        let teams = [
+            (
+                m.team_one_role_id,
+                &m.team_one_name,
+                one,
+                two,
+                won_by_team_one,
+            ),
+            (
+                m.team_two_role_id,
+                &m.team_two_name,
+                two,
+                one,
+                !won_by_team_one,
+            ),
+        ];
+        for (role_id, name, maps_won, maps_lost, won) in teams {
+            let standing = table
+                .entry(role_id)
+                .or_insert_with(|| Standing::new(role_id, name));
+            standing.played += 1;
+            if won {
+                standing.wins += 1;
+            } else {
+                standing.losses += 1;
+            }
+            standing.maps_won += maps_won;
+            standing.maps_lost += maps_lost;
+        }
+    }
+    let mut standings: Vec<Standing> = table.into_values().collect();
+    standings.sort_by(|a, b| {
+        b.wins
+            .cmp(&a.wins)
+            .then(b.map_difference().cmp(&a.map_difference()))
+            .then(b.maps_won.cmp(&a.maps_won))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    standings
+}
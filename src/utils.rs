@@ -1,35 +1,65 @@
-use crate::dathost_models::DathostServerDuplicateResponse;
-use crate::{Config, DBConnectionPool, DathostConfig, Setup, SetupStep};
+use crate::dathost_models::{DathostMatch, DathostMatchSeries, DathostServerDuplicateResponse};
+use crate::metrics::Metrics;
+use crate::scoreboard::TrackedMatch;
+use crate::webhooks::{LiveMatches, MatchWebhooks};
+use crate::{DBConnectionPool, SecretKeys};
+use chrono::Utc;
+use csgo_matchbot::bracket::{advancing, Slot};
+use csgo_matchbot::config::{Config, DathostConfig};
+use csgo_matchbot::crypto::Keyring;
 use csgo_matchbot::models::SeriesType::Bo5;
 use csgo_matchbot::models::StepType::{Pick, Veto};
 use csgo_matchbot::models::{
-    Match, MatchServer, MatchSetupStep, MatchState, NewMatchSetupStep, NewSeriesMap, SeriesType,
-    StepType,
+    Bracket, BracketFormat, BracketMatch, BracketTeam, Division, Match, MatchScope, MatchServer,
+    MatchSetupStep, MatchState, NewMatchSetupStep, NewSeriesMap, PlayerStatsTotals, Season,
+    SeriesType, StepType,
 };
+use csgo_matchbot::standings::Standing;
+use csgo_matchbot::veto::State::{Completed, MapVeto, ServerPick, SidePick};
+use csgo_matchbot::veto::{Action, MapId, MapPool, Setup, SetupError, Side};
 use csgo_matchbot::{
-    create_match_setup_steps, create_series_maps, get_fresh_token, get_map_pool, get_match_servers,
+    count_free_tokens, create_match_setup_steps, create_series_maps, find_division, find_season,
+    find_season_team, get_active_season, get_division, get_enabled_match_servers, get_fresh_token,
+    get_guild_settings, get_provider_credentials, get_season_map_pool, get_team,
     get_user_by_discord_id, update_match_state, update_token,
 };
-use std::time::Duration;
 use diesel::PgConnection;
 use r2d2::PooledConnection;
 use r2d2_diesel::ConnectionManager;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
 use reqwest::{Client, Error, Response};
 use serenity::builder::{CreateActionRow, CreateButton, CreateSelectMenu, CreateSelectMenuOption};
+use serenity::collector::{ComponentInteractionCollector, ComponentInteractionCollectorBuilder};
 use serenity::futures::StreamExt;
 use serenity::model::application::component::ButtonStyle;
 use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
 use serenity::model::application::interaction::message_component::MessageComponentInteraction;
 use serenity::model::application::interaction::InteractionResponseType;
 use serenity::model::channel::{Message, ReactionType};
-use serenity::model::id::GuildId;
+use serenity::model::id::{ChannelId, GuildId};
 use serenity::model::prelude::{GuildContainer, Role, RoleId, User};
 use serenity::prelude::Context;
 use serenity::utils::MessageBuilder;
 use std::collections::HashMap;
+use std::fmt;
 use std::sync::Arc;
+use tracing::{debug, error, info, warn};
 use urlencoding::encode;
 
+const PASSWORD_LENGTH: usize = 12;
+/// Longest message Discord accepts
+const MESSAGE_LIMIT: usize = 2000;
+
+/// Random alphanumeric secret, used for server passwords & webhook authorization
+pub(crate) fn random_secret(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
 pub(crate) fn convert_steamid_to_64(steamid: &str) -> u64 {
     let steamid_split: Vec<&str> = steamid.split(':').collect();
     let y = steamid_split[1].parse::<i64>().unwrap();
@@ -37,14 +67,29 @@ pub(crate) fn convert_steamid_to_64(steamid: &str) -> u64 {
     ((z * 2) + y + 76561197960265728) as u64
 }
 
+/// SteamID64 for either a `STEAM_X:Y:Z` ID or an ID that already is 64-bit
+pub(crate) fn parse_steam_id_64(steamid: &str) -> Option<u64> {
+    if let Ok(id) = steamid.parse::<u64>() {
+        return Some(id);
+    }
+    let steamid_split: Vec<&str> = steamid.split(':').collect();
+    if steamid_split.len() != 3 || !steamid_split[0].starts_with("STEAM_") {
+        return None;
+    }
+    let y = steamid_split[1].parse::<u64>().ok()?;
+    let z = steamid_split[2].parse::<u64>().ok()?;
+    Some((z * 2) + y + 76561197960265728)
+}
+
 pub(crate) async fn find_user_team_role(
     all_guild_roles: Vec<Role>,
     user: &User,
     context: &&Context,
 ) -> Result<Role, String> {
+    let prefix = get_config(context).await.discord.team_role_prefix;
     let team_roles: Vec<Role> = all_guild_roles
         .into_iter()
-        .filter(|r| r.name.starts_with("Team"))
+        .filter(|r| r.name.starts_with(&prefix))
         .collect();
     for team_role in team_roles {
         if let Ok(has_role) = user
@@ -65,8 +110,8 @@ pub(crate) async fn user_team_author(
     setup: &Setup,
     msg: &Arc<MessageComponentInteraction>,
 ) -> Result<u64, String> {
-    let role_one = RoleId::from(setup.clone().team_one.unwrap() as u64).0;
-    let role_two = RoleId::from(setup.clone().team_two.unwrap() as u64).0;
+    let role_one = RoleId::from(setup.team_one as u64).0;
+    let role_two = RoleId::from(setup.team_two as u64).0;
     if let Ok(has_role_one) = msg
         .user
         .has_role(&context.http, msg.guild_id.unwrap(), role_one)
@@ -125,20 +170,189 @@ pub(crate) async fn admin_check(
     };
 }
 
-pub(crate) async fn get_maps(context: &Context) -> Vec<String> {
+pub(crate) async fn user_is_admin(
+    context: &Context,
+    mci: &Arc<MessageComponentInteraction>,
+) -> bool {
+    let config = get_config(context).await;
+    mci.user
+        .has_role(
+            &context.http,
+            mci.guild_id.unwrap(),
+            RoleId::from(config.discord.admin_role_id),
+        )
+        .await
+        .unwrap_or(false)
+}
+
+/// Checks if the user is a member of either team of the setup, or an admin
+pub(crate) async fn user_in_match(
+    context: &Context,
+    mci: &Arc<MessageComponentInteraction>,
+    setup: &Setup,
+) -> bool {
+    for team_role_id in [setup.team_one, setup.team_two] {
+        if mci
+            .user
+            .has_role(context, mci.guild_id.unwrap(), team_role_id as u64)
+            .await
+            .unwrap_or(false)
+        {
+            return true;
+        }
+    }
+    user_is_admin(context, mci).await
+}
+
+/// Checks if the user may make setup selections for their team. If the team has a captain or
+/// vice-captain set, only they may act, otherwise the captain role is required when configured.
+pub(crate) async fn user_is_captain(
+    context: &Context,
+    mci: &Arc<MessageComponentInteraction>,
+    team_role_id: i64,
+) -> bool {
+    let team = {
+        let conn = get_pg_conn(context).await;
+        get_team(&conn, team_role_id)
+    };
+    if let Some(team) = team {
+        if team.has_captains() {
+            return team.is_captain(mci.user.id.0 as i64);
+        }
+    }
+    match get_config(context).await.discord.captain_role_id {
+        Some(captain_role_id) => mci
+            .user
+            .has_role(&context.http, mci.guild_id.unwrap(), captain_role_id)
+            .await
+            .unwrap_or(false),
+        None => true,
+    }
+}
+
+/// Resolves the team role a setup selection is made for, responding to the user if they are not
+/// allowed to make it. Admins act on behalf of the team whose turn it is.
+pub(crate) async fn setup_actor(
+    context: &Context,
+    setup: &Setup,
+    mci: &Arc<MessageComponentInteraction>,
+    turn_role_id: i64,
+) -> Option<u64> {
+    if user_is_admin(context, mci).await {
+        return Some(turn_role_id as u64);
+    }
+    let role_id = match user_team_author(context, setup, mci).await {
+        Ok(role_id) => role_id,
+        Err(_) => {
+            no_team_resp(context, mci).await;
+            return None;
+        }
+    };
+    if !user_is_captain(context, mci, role_id as i64).await {
+        mci.create_interaction_response(context, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.ephemeral(true).content(format!(
+                        "Only the captain or vice-captain of <@&{}> can make selections during setup",
+                        role_id
+                    ))
+                })
+        })
+        .await
+        .unwrap();
+        return None;
+    }
+    Some(role_id)
+}
+
+/// Map pool of the season, every map when it has no pool of its own
+pub(crate) async fn get_maps(context: &Context, season: Option<i32>) -> Vec<String> {
     let conn = get_pg_conn(context).await;
-    let map_pool = get_map_pool(&conn);
+    let map_pool = get_season_map_pool(&conn, season);
     map_pool.into_iter().map(|m| m.name).collect()
 }
 
+/// Season & division a command lists, the named ones or by default the active season & the
+/// division of the user's team in it. Every division is listed when `all_divisions` is set.
+pub(crate) async fn resolve_scope(
+    context: &Context,
+    msg: &ApplicationCommandInteraction,
+    season_name: Option<&str>,
+    division_name: Option<&str>,
+    all_divisions: bool,
+) -> Result<(Option<Season>, Option<Division>), String> {
+    let season = {
+        let conn = get_pg_conn(context).await;
+        match season_name {
+            Some(name) => match find_season(&conn, name) {
+                Some(season) => Some(season),
+                None => return Err(format!("Season `{}` does not exist", name)),
+            },
+            None => get_active_season(&conn),
+        }
+    };
+    let season = match season {
+        Some(season) => season,
+        None if division_name.is_some() => {
+            return Err(String::from(
+                "There is no active season, name the season too",
+            ))
+        }
+        None => return Ok((None, None)),
+    };
+    if let Some(name) = division_name {
+        let conn = get_pg_conn(context).await;
+        return match find_division(&conn, season.id, name) {
+            Some(division) => Ok((Some(season), Some(division))),
+            None => Err(format!(
+                "Division `{}` does not exist in `{}`",
+                name, season.name
+            )),
+        };
+    }
+    if all_divisions {
+        return Ok((Some(season), None));
+    }
+    let team_role = match context
+        .http
+        .get_guild_roles(*msg.guild_id.unwrap().as_u64())
+        .await
+    {
+        Ok(roles) => find_user_team_role(roles, &msg.user, &context).await.ok(),
+        Err(_) => None,
+    };
+    let conn = get_pg_conn(context).await;
+    let division = team_role
+        .and_then(|role| find_season_team(&conn, season.id, role.id.0 as i64))
+        .and_then(|season_team| season_team.division_id)
+        .and_then(|d_id| get_division(&conn, d_id));
+    Ok((Some(season), division))
+}
+
+pub(crate) fn match_scope(season: Option<&Season>, division: Option<&Division>) -> MatchScope {
+    MatchScope {
+        season_id: season.map(|s| s.id),
+        division_id: division.map(|d| d.id),
+    }
+}
+
+pub(crate) fn print_scope(season: Option<&Season>, division: Option<&Division>) -> String {
+    match (season, division) {
+        (Some(season), Some(division)) => format!("{} - {}", season.name, division.name),
+        (Some(season), None) => season.name.clone(),
+        (None, _) => String::from("All seasons"),
+    }
+}
+
+/// Servers offered to teams during setup
 pub(crate) async fn get_servers(context: &Context) -> Vec<MatchServer> {
     let conn = get_pg_conn(context).await;
-    get_match_servers(&conn)
+    get_enabled_match_servers(&conn)
 }
 
 pub(crate) async fn finish_setup(context: &Context, setup_final: &Setup) {
     let mut match_setup_steps: Vec<NewMatchSetupStep> = Vec::new();
-    let match_id = setup_final.match_id.unwrap();
+    let match_id = setup_final.match_id;
     let conn = get_pg_conn(context).await;
     for v in &setup_final.veto_pick_order {
         let step = NewMatchSetupStep {
@@ -150,7 +364,6 @@ pub(crate) async fn finish_setup(context: &Context, setup_final: &Setup) {
         match_setup_steps.push(step);
     }
     let mut series_maps: Vec<NewSeriesMap> = Vec::new();
-    let match_id = setup_final.match_id.unwrap();
     for m in &setup_final.maps {
         let step = NewSeriesMap {
             match_id,
@@ -166,13 +379,12 @@ pub(crate) async fn finish_setup(context: &Context, setup_final: &Setup) {
     update_match_state(&conn, match_id, MatchState::Completed);
 }
 
-pub(crate) fn print_veto_info(setup_info: &Vec<MatchSetupStep>, m: &Match) -> String {
+pub(crate) fn print_veto_info(setup_info: &[MatchSetupStep], m: &Match) -> String {
     if setup_info.is_empty() {
         return String::from("_This match has no veto info yet_");
     }
     let mut resp = String::from("```diff\n");
     let veto: String = setup_info
-        .clone()
         .iter()
         .map(|v| {
             let mut veto_str = String::new();
@@ -231,9 +443,167 @@ pub(crate) fn print_match_info(m: &Match, show_id: bool) -> String {
     if show_id {
         row.push_str(format!("    _Match ID:_ `{}\n`", m.id).as_str())
     }
+    if let Some(round) = m.round {
+        row.push_str(format!("    _Round:_ `{}`", round).as_str());
+        if let Some(deadline) = m.deadline {
+            row.push_str(format!(" _Deadline:_ `{}`", deadline.format("%Y-%m-%d %H:%M")).as_str());
+        }
+        row.push('\n');
+    }
+    if let Some(winner) = m.winner_role_id {
+        row.push_str(format!("    _Winner:_ <@&{}>", winner).as_str());
+        if let (Some(one), Some(two)) = (m.team_one_score, m.team_two_score) {
+            row.push_str(format!(" `{}-{}`", one, two).as_str());
+        }
+        row.push('\n');
+    }
+    if let (MatchState::Archived, Some(admin)) = (m.match_state, m.archived_by) {
+        row.push_str(format!("    _Archived by_ <@{}>", admin).as_str());
+        if let Some(reason) = &m.archive_reason {
+            row.push_str(format!(": `{}`", reason).as_str());
+        }
+        row.push('\n');
+    }
     row
 }
 
+/// Bracket matches started by a reported result, with the IDs teams set them up with
+pub(crate) fn print_started_matches(started: &[Match]) -> String {
+    let mut resp = String::new();
+    for m in started {
+        resp.push_str(&format!(
+            "Next up: <@&{}> vs <@&{}> `{}`, match ID `{}`\n",
+            m.team_one_role_id,
+            m.team_two_role_id,
+            m.note.as_deref().unwrap_or_default(),
+            m.id
+        ));
+    }
+    resp
+}
+
+/// Bracket matches by round, decided matches show their winner in bold. Cut off at Discord's
+/// message length.
+pub(crate) fn print_bracket(
+    bracket: &Bracket,
+    matches: &[BracketMatch],
+    teams: &[BracketTeam],
+) -> String {
+    let team = |slot: Slot| match slot {
+        Slot::Team(role_id) => teams
+            .iter()
+            .find(|t| t.role_id == role_id)
+            .map(|t| t.name.clone())
+            .unwrap_or_else(|| format!("<@&{}>", role_id)),
+        Slot::Pending => String::from("_TBD_"),
+        Slot::Bye => String::from("_bye_"),
+    };
+    let mut resp = format!(
+        "**{}** - {}, {}\n",
+        bracket.name,
+        bracket.format,
+        bracket.series_type.to_string().to_uppercase()
+    );
+    let mut heading = String::new();
+    for m in matches {
+        if m.slot(0) == Slot::Bye && m.slot(1) == Slot::Bye {
+            continue;
+        }
+        let (m_heading, prefix) = match m.group_name() {
+            Some(group) => (group, format!("{}: ", m.round_name(bracket.format))),
+            None => (m.round_name(bracket.format), String::new()),
+        };
+        if m_heading != heading {
+            resp.push_str(&format!("__{}__\n", m_heading));
+            heading = m_heading;
+        }
+        let winner = m.outcome().map(|(winner, _)| winner);
+        let side = |slot: Slot| match winner {
+            Some(winner) if winner == slot => format!("**{}**", team(slot)),
+            _ => team(slot),
+        };
+        resp.push_str(&format!(
+            "- {}{} vs {}",
+            prefix,
+            side(m.slot(0)),
+            side(m.slot(1))
+        ));
+        if let (Some(match_id), None) = (m.match_id, m.winner_role_id) {
+            resp.push_str(&format!(" `match {}`", match_id));
+        }
+        resp.push('\n');
+    }
+    let advancing: Vec<String> = advancing(matches)
+        .into_iter()
+        .map(|role_id| team(Slot::Team(role_id)))
+        .collect();
+    if !advancing.is_empty() {
+        let label = match bracket.format {
+            BracketFormat::Gsl => "Advancing",
+            _ => "Champion",
+        };
+        resp.push_str(&format!("{}: **{}**\n", label, advancing.join("**, **")));
+    }
+    if resp.len() > MESSAGE_LIMIT {
+        let cut = resp
+            .match_indices('\n')
+            .map(|(i, _)| i)
+            .take_while(|&i| i < MESSAGE_LIMIT - 4)
+            .last()
+            .unwrap_or_default();
+        resp.truncate(cut);
+        resp.push_str("\n…");
+    }
+    resp
+}
+
+/// League table of the standings, cut off at Discord's message length
+pub(crate) fn print_standings(scope: &str, standings: &[Standing]) -> String {
+    let width = standings
+        .iter()
+        .map(|s| s.name.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max(4);
+    let mut resp = format!(
+        "**Standings** - {}
+```
+",
+        scope
+    );
+    resp.push_str(&format!(
+        "{:>2}  {:<width$}  {:>2} {:>2} {:>2} {:>5} {:>4}\n",
+        "#",
+        "Team",
+        "P",
+        "W",
+        "L",
+        "Maps",
+        "+/-",
+        width = width
+    ));
+    for (i, s) in standings.iter().enumerate() {
+        let row = format!(
+            "{:>2}  {:<width$}  {:>2} {:>2} {:>2} {:>5} {:>+4}\n",
+            i + 1,
+            s.name,
+            s.played,
+            s.wins,
+            s.losses,
+            format!("{}-{}", s.maps_won, s.maps_lost),
+            s.map_difference(),
+            width = width
+        );
+        if resp.len() + row.len() > MESSAGE_LIMIT - 8 {
+            resp.push_str("…\n");
+            break;
+        }
+        resp.push_str(&row);
+    }
+    resp.push_str("```");
+    resp
+}
+
 pub(crate) fn eos_printout(setup: &Setup) -> String {
     let mut resp = String::from("\n\nSetup is completed. GLHF!\n\n");
     for (i, el) in setup.maps.iter().enumerate() {
@@ -252,165 +622,270 @@ pub(crate) fn eos_printout(setup: &Setup) -> String {
     resp
 }
 
-pub async fn no_team_resp(context: &Context, mci: &Arc<MessageComponentInteraction>) {
-    mci.create_interaction_response(&context, |r| {
+pub(crate) fn print_player_stats(totals: &PlayerStatsTotals) -> String {
+    format!(
+        "K/D/A: {}/{}/{} ({:.2}) | ADR: {:.1} | HS: {:.1}%",
+        totals.kills,
+        totals.deaths,
+        totals.assists,
+        totals.kd(),
+        totals.adr(),
+        totals.hs_percent()
+    )
+}
+
+/// Selections on the setup message & answers to the setup's confirmation prompts, which are
+/// ephemeral messages of their own
+pub(crate) fn setup_interactions(
+    context: &Context,
+    setup_msg: &Message,
+    match_id: i32,
+) -> ComponentInteractionCollector {
+    let setup_msg_id = setup_msg.id;
+    ComponentInteractionCollectorBuilder::new(context)
+        .channel_id(setup_msg.channel_id)
+        .filter(move |mci| {
+            let confirmation = setup_confirmation(&mci.data.custom_id);
+            mci.message.id == setup_msg_id || confirmation.is_some_and(|(_, id, _)| id == match_id)
+        })
+        .build()
+}
+
+/// Asks the user to confirm their selection with an ephemeral prompt, its buttons are answered
+/// through the setup's interactions while the selection is kept pending as confirmation `seq`
+pub(crate) async fn confirm_setup_step(
+    context: &Context,
+    mci: &Arc<MessageComponentInteraction>,
+    prompt: String,
+    match_id: i32,
+    seq: u32,
+) -> serenity::Result<()> {
+    mci.create_interaction_response(context, |r| {
         r.kind(InteractionResponseType::ChannelMessageWithSource)
             .interaction_response_data(|d| {
                 d.ephemeral(true)
-                    .content("You are not part of either team currently setting up a match")
+                    .content(prompt)
+                    .components(|c| c.add_action_row(create_confirm_action_row(match_id, seq)))
             })
     })
     .await
-    .unwrap();
 }
 
-pub(crate) async fn handle_bo1_setup(setup: Setup) -> (Vec<SetupStep>, String) {
-    let match_id = setup.match_id.unwrap();
-    (
-        vec![
-            SetupStep {
-                match_id,
-                step_type: Veto,
-                team_role_id: setup.team_two.unwrap() as i64,
-                map: None,
-            },
-            SetupStep {
-                match_id,
-                step_type: Veto,
-                team_role_id: setup.team_one.unwrap() as i64,
-                map: None,
-            },
-            SetupStep {
-                match_id,
-                step_type: Veto,
-                team_role_id: setup.team_two.unwrap() as i64,
-                map: None,
-            },
-            SetupStep {
-                match_id,
-                step_type: Veto,
-                team_role_id: setup.team_one.unwrap() as i64,
-                map: None,
-            },
-            SetupStep {
-                match_id,
-                step_type: Veto,
-                team_role_id: setup.team_two.unwrap() as i64,
-                map: None,
-            },
-            SetupStep {
-                match_id,
-                step_type: Pick,
-                team_role_id: setup.team_one.unwrap() as i64,
-                map: None,
-            },
-        ],
-        format!(
-            "Best of 1 option selected. Starting map veto. <@&{}> bans first.\n",
-            &setup.team_two.unwrap()
-        ),
-    )
+/// Replaces a confirmation prompt's buttons with the outcome of the confirmation
+pub(crate) async fn answer_setup_confirmation(
+    context: &Context,
+    mci: &Arc<MessageComponentInteraction>,
+    outcome: &str,
+) -> serenity::Result<()> {
+    mci.create_interaction_response(context, |r| {
+        r.kind(InteractionResponseType::UpdateMessage)
+            .interaction_response_data(|d| d.content(outcome).components(|c| c))
+    })
+    .await
 }
 
-pub(crate) async fn handle_bo3_setup(setup: Setup) -> (Vec<SetupStep>, String) {
-    let match_id = setup.match_id.unwrap();
-    (
-        vec![
-            SetupStep {
-                match_id,
-                step_type: Veto,
-                team_role_id: setup.team_one.unwrap() as i64,
-                map: None,
-            },
-            SetupStep {
-                match_id,
-                step_type: Veto,
-                team_role_id: setup.team_two.unwrap() as i64,
-                map: None,
-            },
-            SetupStep {
-                match_id,
-                step_type: Pick,
-                team_role_id: setup.team_one.unwrap() as i64,
-                map: None,
-            },
-            SetupStep {
-                match_id,
-                step_type: Pick,
-                team_role_id: setup.team_two.unwrap() as i64,
-                map: None,
-            },
-            SetupStep {
-                match_id,
-                step_type: Veto,
-                team_role_id: setup.team_two.unwrap() as i64,
-                map: None,
-            },
-            SetupStep {
-                match_id,
-                step_type: Pick,
-                team_role_id: setup.team_one.unwrap() as i64,
-                map: None,
-            },
-        ],
-        format!(
-            "Best of 3 option selected. Starting map veto. <@&{}> bans first.\n",
-            &setup.team_one.unwrap()
-        ),
-    )
+/// Whether a confirmation prompt button confirms or cancels, with the match id & confirmation
+/// sequence number of the prompt
+pub(crate) fn setup_confirmation(custom_id: &str) -> Option<(bool, i32, u32)> {
+    let mut parts = custom_id.split(':');
+    let confirmed = match parts.next()? {
+        "confirm" => true,
+        "cancel" => false,
+        _ => return None,
+    };
+    let match_id = parts.next()?.parse().ok()?;
+    let seq = parts.next()?.parse().ok()?;
+    Some((confirmed, match_id, seq))
 }
 
-pub(crate) async fn handle_bo5_setup(setup: Setup) -> (Vec<SetupStep>, String) {
-    let match_id = setup.match_id.unwrap();
-    (
-        vec![
-            SetupStep {
-                match_id,
-                step_type: Veto,
-                team_role_id: setup.team_one.unwrap() as i64,
-                map: None,
-            },
-            SetupStep {
-                match_id,
-                step_type: Veto,
-                team_role_id: setup.team_two.unwrap() as i64,
-                map: None,
-            },
-            SetupStep {
-                match_id,
-                step_type: Pick,
-                team_role_id: setup.team_one.unwrap() as i64,
-                map: None,
-            },
-            SetupStep {
-                match_id,
-                step_type: Pick,
-                team_role_id: setup.team_two.unwrap() as i64,
-                map: None,
-            },
-            SetupStep {
-                match_id,
-                step_type: Pick,
-                team_role_id: setup.team_one.unwrap() as i64,
-                map: None,
-            },
-            SetupStep {
-                match_id,
-                step_type: Pick,
-                team_role_id: setup.team_two.unwrap() as i64,
-                map: None,
-            },
-            SetupStep {
-                match_id,
-                step_type: Pick,
-                team_role_id: setup.team_one.unwrap() as i64,
-                map: None,
-            },
-        ],
-        format!(
-            "Best of 5 option selected. Starting map veto. <@&{}> bans first.\n",
-            &setup.team_one.unwrap()
-        ),
+/// Shows the setup's current phase & step on the setup message, for interactions that were
+/// answered elsewhere
+pub(crate) async fn edit_setup_message(
+    context: &Context,
+    setup_msg: &mut Message,
+    setup: &Setup,
+    init_veto_msg: &str,
+    match_servers: &[MatchServer],
+) -> serenity::Result<()> {
+    setup_msg
+        .edit(context, |e| {
+            e.content(setup_prompt(setup, init_veto_msg))
+                .components(|c| {
+                    c.add_action_row(setup_action_row(setup, match_servers))
+                        .add_action_row(create_setup_admin_action_row())
+                })
+        })
+        .await
+}
+
+/// Shows the setup's current phase & step on the setup message the interaction belongs to
+pub(crate) async fn update_setup_message(
+    context: &Context,
+    mci: &Arc<MessageComponentInteraction>,
+    setup: &Setup,
+    init_veto_msg: &str,
+    match_servers: &[MatchServer],
+) -> serenity::Result<()> {
+    mci.create_interaction_response(context, |r| {
+        r.kind(InteractionResponseType::UpdateMessage)
+            .interaction_response_data(|d| {
+                d.content(setup_prompt(setup, init_veto_msg))
+                    .components(|c| {
+                        c.add_action_row(setup_action_row(setup, match_servers))
+                            .add_action_row(create_setup_admin_action_row())
+                    })
+            })
+    })
+    .await
+}
+
+pub(crate) fn setup_veto_info(setup: &Setup) -> String {
+    let setup_info: Vec<MatchSetupStep> = setup
+        .veto_pick_order
+        .iter()
+        .map(|v| MatchSetupStep {
+            id: 0,
+            match_id: 0,
+            step_type: v.step_type,
+            team_role_id: v.team_role_id,
+            map: v.map.clone(),
+        })
+        .collect();
+    let m = Match::new(
+        0,
+        (setup.team_one, &setup.team_one_name),
+        (setup.team_two, &setup.team_two_name),
+        setup.series_type,
+        Utc::now().naive_local(),
+    );
+    print_veto_info(&setup_info, &m)
+}
+
+/// Builds the setup message content for the current phase & step
+pub(crate) fn setup_prompt(setup: &Setup, init_veto_msg: &str) -> String {
+    match setup.current_phase {
+        ServerPick => {
+            let team_role_id = setup.turn_role_id().unwrap();
+            format!(
+                "<@&{}> ranks preferred servers, choice {} of {}.",
+                team_role_id,
+                setup.team_servers(team_role_id).len() + 1,
+                setup.servers_to_rank()
+            )
+        }
+        MapVeto => {
+            if setup.current_step == 0 {
+                return String::from(init_veto_msg);
+            }
+            let step = setup.veto_step().unwrap();
+            format!(
+                "{}\nIt is <@&{}> turn to {}",
+                setup_veto_info(setup),
+                step.team_role_id,
+                step.step_type
+            )
+        }
+        SidePick => {
+            let map = setup.side_pick_map().unwrap();
+            let other_role_id = setup.other_team(map.picked_by);
+            if setup.current_step == 0 {
+                format!(
+                    "Map veto completed.\nIt is <@&{}> turn to pick starting side for `{}`",
+                    other_role_id, map.map
+                )
+            } else {
+                format!(
+                    "It is <@&{}> turn to pick starting side on {}",
+                    other_role_id, map.map
+                )
+            }
+        }
+        Completed => eos_printout(setup),
+    }
+}
+
+/// Builds the select menu for the current setup phase & step
+pub(crate) fn setup_action_row(setup: &Setup, server_list: &[MatchServer]) -> CreateActionRow {
+    match setup.current_phase {
+        ServerPick => {
+            let ranked = setup
+                .turn_role_id()
+                .map(|team_role_id| setup.team_servers(team_role_id))
+                .unwrap_or_default();
+            create_server_action_row(server_list, ranked)
+        }
+        MapVeto => create_map_action_row(&setup.map_pool, &setup.veto_step().unwrap().step_type),
+        SidePick | Completed => create_sidepick_action_row(),
+    }
+}
+
+/// Maps a setup component selection to the setup action it represents
+pub(crate) fn setup_action(
+    setup: &Setup,
+    team_role_id: i64,
+    custom_id: &str,
+    value: &str,
+) -> Option<Action> {
+    match custom_id {
+        // Menu option values are lowercased, so the server id is looked up
+        "server_select" => Some(Action::RankServer {
+            team_role_id,
+            server_id: setup
+                .servers
+                .iter()
+                .find(|server_id| server_id.eq_ignore_ascii_case(value))
+                .cloned()
+                .unwrap_or_else(|| String::from(value)),
+        }),
+        "map_select" => {
+            let map = MapId::new(value);
+            match setup.veto_step().map(|s| s.step_type) {
+                Some(Pick) => Some(Action::Pick { team_role_id, map }),
+                _ => Some(Action::Ban { team_role_id, map }),
+            }
+        }
+        "side_pick" => value
+            .parse::<Side>()
+            .ok()
+            .map(|side| Action::PickSide { team_role_id, side }),
+        _ => None,
+    }
+}
+
+pub(crate) async fn setup_error_resp(
+    context: &Context,
+    mci: &Arc<MessageComponentInteraction>,
+    err: SetupError,
+) {
+    mci.create_interaction_response(context, |r| {
+        r.kind(InteractionResponseType::ChannelMessageWithSource)
+            .interaction_response_data(|d| d.ephemeral(true).content(err.to_string()))
+    })
+    .await
+    .unwrap();
+}
+
+pub async fn no_team_resp(context: &Context, mci: &Arc<MessageComponentInteraction>) {
+    mci.create_interaction_response(&context, |r| {
+        r.kind(InteractionResponseType::ChannelMessageWithSource)
+            .interaction_response_data(|d| {
+                d.ephemeral(true)
+                    .content("You are not part of either team currently setting up a match")
+            })
+    })
+    .await
+    .unwrap();
+}
+
+pub(crate) fn veto_start_message(setup: &Setup) -> String {
+    let series = match setup.series_type {
+        SeriesType::Bo1 => "Best of 1",
+        SeriesType::Bo3 => "Best of 3",
+        SeriesType::Bo5 => "Best of 5",
+    };
+    format!(
+        "{} option selected. Starting map veto. <@&{}> bans first.\n",
+        series, setup.veto_pick_order[0].team_role_id
     )
 }
 
@@ -422,6 +897,40 @@ pub(crate) async fn get_pg_conn(
     pool.get().unwrap()
 }
 
+pub(crate) async fn get_live_matches(context: &Context) -> Arc<LiveMatches> {
+    let data = context.data.read().await;
+    data.get::<LiveMatches>().unwrap().clone()
+}
+
+pub(crate) async fn get_metrics(context: &Context) -> Arc<Metrics> {
+    let data = context.data.read().await;
+    data.get::<Metrics>().unwrap().clone()
+}
+
+pub(crate) async fn get_keyring(context: &Context) -> Arc<Keyring> {
+    let data = context.data.read().await;
+    data.get::<SecretKeys>().unwrap().clone()
+}
+
+/// The guild's own Dathost account, falling back to the account from the environment
+pub(crate) async fn get_dathost_config(
+    context: &Context,
+    guild_id: GuildId,
+) -> Option<DathostConfig> {
+    let keyring = get_keyring(context).await;
+    let credentials = {
+        let conn = get_pg_conn(context).await;
+        get_provider_credentials(&conn, &keyring, guild_id.0 as i64)
+    };
+    match credentials {
+        Some(credentials) => Some(DathostConfig {
+            user: credentials.dathost_user,
+            password: credentials.dathost_password,
+        }),
+        None => get_config(context).await.dathost,
+    }
+}
+
 pub fn create_sidepick_action_row() -> CreateActionRow {
     let mut ar = CreateActionRow::default();
     let mut menu = CreateSelectMenu::default();
@@ -435,14 +944,20 @@ pub fn create_sidepick_action_row() -> CreateActionRow {
     ar
 }
 
-pub fn create_server_conn_button_row(url: &String, gotv_url: &String, show_cmds: bool) -> CreateActionRow {
+/// Connect & GOTV link buttons, along with the console commands button while `show_cmds` is set
+pub fn create_server_conn_button_row(
+    links: &Option<(String, String)>,
+    show_cmds: bool,
+) -> CreateActionRow {
     let mut ar = CreateActionRow::default();
-    let mut conn_button = CreateButton::default();
-    conn_button.label("Connect");
-    conn_button.style(ButtonStyle::Link);
-    conn_button.emoji(ReactionType::Unicode("🛰".parse().unwrap()));
-    conn_button.url(&url);
-    ar.add_button(conn_button);
+    if let Some((url, _)) = links {
+        let mut conn_button = CreateButton::default();
+        conn_button.label("Connect");
+        conn_button.style(ButtonStyle::Link);
+        conn_button.emoji(ReactionType::Unicode("🛰".parse().unwrap()));
+        conn_button.url(url);
+        ar.add_button(conn_button);
+    }
     if show_cmds {
         let mut console_button = CreateButton::default();
         console_button.custom_id("console");
@@ -451,40 +966,79 @@ pub fn create_server_conn_button_row(url: &String, gotv_url: &String, show_cmds:
         console_button.emoji(ReactionType::Unicode("🧾".parse().unwrap()));
         ar.add_button(console_button);
     }
-    let mut gotv_button = CreateButton::default();
-    gotv_button.label("GOTV");
-    gotv_button.style(ButtonStyle::Link);
-    gotv_button.emoji(ReactionType::Unicode("📺".parse().unwrap()));
-    gotv_button.url(gotv_url);
-    ar.add_button(gotv_button);
+    if let Some((_, gotv_url)) = links {
+        let mut gotv_button = CreateButton::default();
+        gotv_button.label("GOTV");
+        gotv_button.style(ButtonStyle::Link);
+        gotv_button.emoji(ReactionType::Unicode("📺".parse().unwrap()));
+        gotv_button.url(gotv_url);
+        ar.add_button(gotv_button);
+    }
+    ar
+}
+
+pub fn create_confirm_action_row(match_id: i32, seq: u32) -> CreateActionRow {
+    let mut ar = CreateActionRow::default();
+    let mut confirm_button = CreateButton::default();
+    confirm_button.custom_id(format!("confirm:{}:{}", match_id, seq));
+    confirm_button.label("Confirm");
+    confirm_button.style(ButtonStyle::Success);
+    ar.add_button(confirm_button);
+    let mut cancel_button = CreateButton::default();
+    cancel_button.custom_id(format!("cancel:{}:{}", match_id, seq));
+    cancel_button.label("Cancel");
+    cancel_button.style(ButtonStyle::Secondary);
+    ar.add_button(cancel_button);
+    ar
+}
+
+pub fn create_setup_admin_action_row() -> CreateActionRow {
+    let mut ar = CreateActionRow::default();
+    let mut undo_button = CreateButton::default();
+    undo_button.custom_id("undo");
+    undo_button.label("Undo (admin)");
+    undo_button.style(ButtonStyle::Danger);
+    undo_button.emoji(ReactionType::Unicode("↩".parse().unwrap()));
+    ar.add_button(undo_button);
     ar
 }
 
-pub fn create_map_action_row(map_list: Vec<String>, step_type: &StepType) -> CreateActionRow {
+pub fn create_map_action_row(map_pool: &MapPool, step_type: &StepType) -> CreateActionRow {
     let mut ar = CreateActionRow::default();
     let mut menu = CreateSelectMenu::default();
     menu.custom_id("map_select");
     menu.placeholder(format!("Select map to {}", step_type));
     let mut options = Vec::new();
-    for map_name in map_list {
-        options.push(create_menu_option(
-            &map_name,
-            &map_name.to_ascii_lowercase(),
-        ))
+    for map in map_pool.available() {
+        options.push(create_menu_option(&map.name, map.id.as_str()))
     }
     menu.options(|f| f.set_options(options));
     ar.add_select_menu(menu);
     ar
 }
 
-pub fn create_server_action_row(server_list: &Vec<MatchServer>) -> CreateActionRow {
+/// Servers the team has not ranked yet, described by location & capacity as a latency hint
+pub fn create_server_action_row(server_list: &[MatchServer], ranked: &[String]) -> CreateActionRow {
     let mut ar = CreateActionRow::default();
     let mut menu = CreateSelectMenu::default();
     menu.custom_id("server_select");
-    menu.placeholder("Select server");
+    menu.placeholder("Select preferred server");
     let mut options = Vec::new();
     for server in server_list {
-        options.push(create_menu_option(&server.region_label, &server.server_id))
+        if ranked.contains(&server.server_id) {
+            continue;
+        }
+        let mut option = create_menu_option(&server.region_label, &server.server_id);
+        let description = match (&server.location, server.capacity) {
+            (Some(location), Some(capacity)) => Some(format!("{}, {} slots", location, capacity)),
+            (Some(location), None) => Some(location.clone()),
+            (None, Some(capacity)) => Some(format!("{} slots", capacity)),
+            (None, None) => None,
+        };
+        if let Some(description) = description {
+            option.description(description);
+        }
+        options.push(option)
     }
     menu.options(|f| f.set_options(options));
     ar.add_select_menu(menu);
@@ -500,98 +1054,280 @@ pub fn create_menu_option(label: &str, value: &str) -> CreateSelectMenuOption {
     opt
 }
 
+/// Duplicated game server a match was started on, along with its passwords
+pub struct StartedServer {
+    pub server: DathostServerDuplicateResponse,
+    pub password: String,
+    pub gotv_password: String,
+    pub tracked: Option<TrackedMatch>,
+}
+
+#[derive(Debug)]
+pub enum StartServerError {
+    Provider(Error),
+    Discord(serenity::Error),
+}
+
+impl fmt::Display for StartServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartServerError::Provider(err) => write!(f, "Dathost request failed: {}", err),
+            StartServerError::Discord(err) => write!(f, "Discord request failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for StartServerError {}
+
+impl From<Error> for StartServerError {
+    fn from(err: Error) -> Self {
+        StartServerError::Provider(err)
+    }
+}
+
+impl From<serenity::Error> for StartServerError {
+    fn from(err: serenity::Error) -> Self {
+        StartServerError::Discord(err)
+    }
+}
+
 pub async fn start_server(
     context: &Context,
     guild_id: GuildId,
     setup: &mut Setup,
-) -> Result<DathostServerDuplicateResponse, Error> {
-    println!("{:#?}", setup);
-    let dathost_config = get_config(context).await.dathost;
-    let conn = get_pg_conn(context).await;
+    dathost_config: &DathostConfig,
+) -> Result<StartedServer, StartServerError> {
+    let config = get_config(context).await;
+    let keyring = get_keyring(context).await;
+    let metrics = get_metrics(context).await;
     let client = Client::new();
-    println!("duplicating server");
-    let dupl_url = format!(
-        "https://dathost.net/api/0.1/game-servers/{}/duplicate",
-        encode(&setup.server_id.clone().unwrap())
-    );
-    let resp = client
-        .post(dupl_url)
-        .basic_auth(&dathost_config.user, Some(&dathost_config.password))
-        .send()
-        .await
-        .unwrap()
-        .json::<DathostServerDuplicateResponse>()
-        .await;
-    let resp = resp?;
+    let mut candidates = setup.server_ranking.clone();
+    if candidates.is_empty() {
+        candidates.extend(setup.server_id.clone());
+    }
+    let mut duplicated = None;
+    for template_id in candidates {
+        info!(template_id = %template_id, "duplicating server");
+        match duplicate_server(&client, &metrics, dathost_config, &template_id).await {
+            Ok(resp) => {
+                setup.server_id = Some(template_id);
+                duplicated = Some(Ok(resp));
+                break;
+            }
+            Err(err) => {
+                warn!(template_id = %template_id, error = %err, "unable to duplicate server");
+                duplicated = Some(Err(err));
+            }
+        }
+    }
+    let resp = duplicated.expect("Expected a server to duplicate")?;
     let server_id = resp.id.clone();
 
-    let mut gslt = get_fresh_token(&conn);
-    println!("setting gslt '{}'", &gslt.token);
-    let gslt_resp = client
-        .put(format!(
-            "https://dathost.net/api/0.1/game-servers/{}",
-            encode(&server_id.to_string())
-        ))
-        .form(&[
-            ("name", format!("match-server-{}", setup.match_id.unwrap())),
-            (
+    let password = random_secret(PASSWORD_LENGTH);
+    let gotv_password = random_secret(PASSWORD_LENGTH);
+    let gslt = {
+        let conn = get_pg_conn(context).await;
+        get_fresh_token(&conn, &keyring)
+    };
+    let mut server_settings = vec![
+        ("name", format!("match-server-{}", setup.match_id)),
+        ("csgo_settings.password", password.clone()),
+    ];
+    match &gslt {
+        Some(gslt) => {
+            info!(gslt_id = gslt.id, "setting gslt");
+            server_settings.push((
                 "csgo_settings.steam_game_server_login_token",
                 gslt.token.clone(),
-            ),
-        ])
-        .basic_auth(&dathost_config.user, Some(&dathost_config.password))
-        .send()
-        .await
-        .unwrap();
-    if gslt_resp.status() == 200 {
-        gslt.in_use = true;
-        update_token(&conn, gslt);
+            ));
+        }
+        None => warn!("no free gslt"),
+    }
+    let gslt_resp = metrics
+        .provider_request(
+            "update_server",
+            client
+                .put(format!(
+                    "https://dathost.net/api/0.1/game-servers/{}",
+                    encode(&server_id.to_string())
+                ))
+                .form(&server_settings)
+                .basic_auth(&dathost_config.user, Some(&dathost_config.password))
+                .send(),
+        )
+        .await?;
+    if let Some(mut gslt) = gslt {
+        if gslt_resp.status() == 200 {
+            gslt.in_use = true;
+            gslt.match_id = Some(setup.match_id);
+            gslt.assigned_at = Some(Utc::now().naive_utc());
+            let conn = get_pg_conn(context).await;
+            update_token(&conn, gslt);
+        }
+    }
+    warn_low_gslt_pool(context, guild_id).await;
+    let tv_resp = metrics
+        .provider_request(
+            "console",
+            client
+                .post(format!(
+                    "https://dathost.net/api/0.1/game-servers/{}/console",
+                    encode(&server_id)
+                ))
+                .form(&[("line", format!("tv_password {}", gotv_password))])
+                .basic_auth(&dathost_config.user, Some(&dathost_config.password))
+                .send(),
+        )
+        .await?;
+    if let Err(err) = tv_resp.error_for_status() {
+        error!(error = %err, "unable to set GOTV password");
     }
     let users: Vec<User> = context
         .http
         .get_guild_members(*guild_id.as_u64(), None, None)
-        .await
-        .unwrap()
+        .await?
         .iter()
         .map(|u| u.user.clone())
         .collect();
     let mut team_one_users = Vec::new();
     let mut team_two_users = Vec::new();
     for u in users {
-        if u.has_role(&context, guild_id, setup.team_one.unwrap() as u64)
-            .await
-            .unwrap()
+        if u.has_role(&context, guild_id, setup.team_one as u64)
+            .await?
         {
             team_one_users.push(u.clone());
         }
-        if u.has_role(&context, guild_id, setup.team_two.unwrap() as u64)
-            .await
-            .unwrap()
+        if u.has_role(&context, guild_id, setup.team_two as u64)
+            .await?
         {
             team_two_users.push(u.clone());
         }
     }
-    println!("1: {:#?}", team_one_users);
-    println!("2: {:#?}", team_two_users);
-    let conn = get_pg_conn(context).await;
-    setup.team_one_conn_str = Some(map_steamid_strings(team_one_users, &conn));
-    setup.team_two_conn_str = Some(map_steamid_strings(team_two_users, &conn));
-    println!(
-        "starting match\nteam1 '{}'\nteam2: '{}'",
-        setup.clone().team_one_conn_str.unwrap(),
-        setup.clone().team_two_conn_str.unwrap()
+    debug!(
+        team_one = team_one_users.len(),
+        team_two = team_two_users.len(),
+        "found team members"
     );
+    {
+        let conn = get_pg_conn(context).await;
+        setup.team_one_conn_str = Some(map_steamid_strings(team_one_users, &conn));
+        setup.team_two_conn_str = Some(map_steamid_strings(team_two_users, &conn));
+    }
+    info!(server_id = %server_id, "starting match");
+    let webhooks = match &config.webhooks {
+        Some(webhook_config) => Some(
+            get_live_matches(context)
+                .await
+                .register(webhook_config, setup.match_id),
+        ),
+        None => None,
+    };
     let start_resp = match setup.series_type {
-        SeriesType::Bo1 => start_match(server_id, setup, client, &dathost_config).await,
-        SeriesType::Bo3 => start_series_match(server_id, setup, client, &dathost_config).await,
-        SeriesType::Bo5 => start_series_match(server_id, setup, client, &dathost_config).await,
+        SeriesType::Bo1 => {
+            metrics
+                .provider_request(
+                    "start_match",
+                    start_match(server_id, setup, client, dathost_config, &webhooks),
+                )
+                .await
+        }
+        SeriesType::Bo3 | SeriesType::Bo5 => {
+            metrics
+                .provider_request(
+                    "start_match_series",
+                    start_series_match(server_id, setup, client, dathost_config, &webhooks),
+                )
+                .await
+        }
+    };
+    let start_resp = match start_resp {
+        Ok(start_resp) => start_resp,
+        Err(err) => {
+            error!(error = %err, "unable to start match");
+            get_live_matches(context).await.remove(setup.match_id);
+            return Err(err.into());
+        }
     };
-    if let Err(err) = start_resp {
-        eprintln!("{:#?}", err);
-        return Err(err);
+    let text = start_resp.text().await?;
+    let tracked = match setup.series_type {
+        SeriesType::Bo1 => {
+            serde_json::from_str::<DathostMatch>(&text).map(|m| TrackedMatch::Match(m.id))
+        }
+        SeriesType::Bo3 | SeriesType::Bo5 => {
+            serde_json::from_str::<DathostMatchSeries>(&text).map(|s| TrackedMatch::Series(s.id))
+        }
+    };
+    let tracked = match tracked {
+        Ok(TrackedMatch::Match(id)) | Ok(TrackedMatch::Series(id)) if id.is_empty() => None,
+        Ok(tracked) => Some(tracked),
+        Err(err) => {
+            warn!(error = %err, "unable to read started match");
+            None
+        }
+    };
+    info!(tracked = ?tracked, "started match");
+    if tracked.is_none() {
+        get_live_matches(context).await.remove(setup.match_id);
     }
-    println!("{:#?}", start_resp.unwrap().text().await.unwrap());
-    Ok(resp)
+    Ok(StartedServer {
+        server: resp,
+        password,
+        gotv_password,
+        tracked,
+    })
+}
+
+/// Warns in the guild's admin channel when the free GSLT count falls below its threshold
+pub(crate) async fn warn_low_gslt_pool(context: &Context, guild_id: GuildId) {
+    let fallback_channel_id = get_config(context).await.discord.admin_channel_id;
+    let (channel_id, free) = {
+        let conn = get_pg_conn(context).await;
+        let settings = get_guild_settings(&conn, guild_id.0 as i64);
+        let channel_id = match settings
+            .admin_channel_id
+            .map(|id| id as u64)
+            .or(fallback_channel_id)
+        {
+            Some(channel_id) => ChannelId(channel_id),
+            None => return,
+        };
+        let free = count_free_tokens(&conn);
+        if free >= settings.gslt_warn_threshold as i64 {
+            return;
+        }
+        (channel_id, free)
+    };
+    let warning = format!(
+        "Only {} free GSLT token(s) left, add more with `/gslt add`",
+        free
+    );
+    if let Err(err) = channel_id.say(&context.http, warning).await {
+        error!(error = %err, "unable to warn about the gslt pool");
+    }
+}
+
+/// Duplicates the template server into a fresh game server for the match
+async fn duplicate_server(
+    client: &Client,
+    metrics: &Metrics,
+    dathost_config: &DathostConfig,
+    template_id: &str,
+) -> Result<DathostServerDuplicateResponse, Error> {
+    metrics
+        .provider_request(
+            "duplicate_server",
+            client
+                .post(format!(
+                    "https://dathost.net/api/0.1/game-servers/{}/duplicate",
+                    encode(template_id)
+                ))
+                .basic_auth(&dathost_config.user, Some(&dathost_config.password))
+                .send(),
+        )
+        .await?
+        .error_for_status()?
+        .json::<DathostServerDuplicateResponse>()
+        .await
 }
 
 pub async fn start_match(
@@ -599,6 +1335,7 @@ pub async fn start_match(
     setup: &Setup,
     client: Client,
     dathost_config: &DathostConfig,
+    webhooks: &Option<MatchWebhooks>,
 ) -> Result<Response, Error> {
     let start_match_url = String::from("https://dathost.net/api/0.1/matches");
     let team_ct: String;
@@ -606,7 +1343,7 @@ pub async fn start_match(
     let team_ct_name: String;
     let team_t_name: String;
     let new_match = setup.maps[0].clone();
-    if setup.maps[0].start_defense_team_role_id == setup.team_one {
+    if setup.maps[0].start_defense_team_role_id == Some(setup.team_one) {
         team_ct = setup.team_one_conn_str.clone().unwrap();
         team_ct_name = setup.team_one_name.clone();
         team_t = setup.team_two_conn_str.clone().unwrap();
@@ -617,19 +1354,25 @@ pub async fn start_match(
         team_t = setup.team_one_conn_str.clone().unwrap();
         team_t_name = setup.team_one_name.clone();
     }
-    println!("starting match request...");
+    let mut params = vec![
+        ("game_server_id", &server_id),
+        ("map", &new_match.map),
+        ("team1_name", &team_t_name),
+        ("team2_name", &team_ct_name),
+        ("team1_steam_ids", &team_t),
+        ("team2_steam_ids", &team_ct),
+    ];
+    if let Some(webhooks) = webhooks {
+        params.push(("round_end_webhook_url", &webhooks.round_end_url));
+        params.push(("match_end_webhook_url", &webhooks.match_end_url));
+        params.push(("webhook_authorization_header", &webhooks.authorization));
+    }
+    let enabled = String::from("true");
+    params.push(("enable_pause", &enabled));
+    params.push(("enable_tech_pause", &enabled));
     client
         .post(&start_match_url)
-        .form(&[
-            ("game_server_id", &&server_id),
-            ("map", &&new_match.map),
-            ("team1_name", &&team_t_name),
-            ("team2_name", &&team_ct_name),
-            ("team1_steam_ids", &&team_t),
-            ("team2_steam_ids", &&team_ct),
-            ("enable_pause", &&String::from("true")),
-            ("enable_tech_pause", &&String::from("true")),
-        ])
+        .form(&params)
         .basic_auth(&dathost_config.user, Some(&dathost_config.password))
         .send()
         .await
@@ -640,6 +1383,7 @@ pub async fn start_series_match(
     setup: &mut Setup,
     client: Client,
     dathost_config: &DathostConfig,
+    webhooks: &Option<MatchWebhooks>,
 ) -> Result<Response, Error> {
     let start_match_url = String::from("https://dathost.net/api/0.1/match-series");
     let team_one = setup.team_one_conn_str.clone().unwrap();
@@ -647,10 +1391,7 @@ pub async fn start_series_match(
     let team_two = setup.team_two_conn_str.clone().unwrap();
     let team_two_name = setup.team_two_name.clone();
     let mut params: HashMap<&str, &str> = HashMap::new();
-    let team_map = HashMap::from([
-        (setup.team_one.unwrap(), "team1"),
-        (setup.team_two.unwrap(), "team2"),
-    ]);
+    let team_map = HashMap::from([(setup.team_one, "team1"), (setup.team_two, "team2")]);
     let mut num_maps = "3";
     params.insert("game_server_id", server_id.as_str());
     params.insert("enable_pause", "true");
@@ -698,7 +1439,14 @@ pub async fn start_series_match(
         );
     }
     params.insert("number_of_maps", num_maps);
-    println!("{:#?}", params);
+    if let Some(webhooks) = webhooks {
+        params.insert("round_end_webhook_url", webhooks.round_end_url.as_str());
+        params.insert("match_end_webhook_url", webhooks.match_end_url.as_str());
+        params.insert(
+            "webhook_authorization_header",
+            webhooks.authorization.as_str(),
+        );
+    }
     client
         .post(&start_match_url)
         .form(&params)
@@ -724,70 +1472,127 @@ pub fn map_steamid_strings(
     str
 }
 
-pub async fn create_conn_message(
+/// Connect & GOTV links for the server, `game_url` & `gotv_url` include the password, i.e.
+/// `ip:port/password`. The embedded HTTP server's redirects are used when it is enabled, otherwise
+/// TinyURL if configured as a fallback.
+async fn create_server_links(
     context: &Context,
-    msg: &Message,
-    server: DathostServerDuplicateResponse,
-    setup: &Setup,
-) {
+    match_id: i32,
+    game_url: &str,
+    gotv_url: &str,
+) -> Option<(String, String)> {
+    let config = get_config(context).await;
+    if let Some(webhook_config) = &config.webhooks {
+        return Some(get_live_matches(context).await.set_server(
+            webhook_config,
+            match_id,
+            String::from(game_url),
+            String::from(gotv_url),
+        ));
+    }
+    if !config.tinyurl_fallback {
+        return None;
+    }
     let client = Client::new();
-    let game_url = format!("{}:{}", server.ip, server.ports.game);
-    let gotv_url = format!("{}:{}", server.ip, server.ports.gotv);
-    let url_link = format!("steam://connect/{}", &game_url);
-    let gotv_link = format!("steam://connect/{}", &gotv_url);
-    let resp = client
-        .get(format!(
-            "https://tinyurl.com/api-create.php?url={}",
-            encode(&url_link)
-        ))
-        .send()
-        .await
-        .unwrap();
-    let t_url = resp.text_with_charset("utf-8").await.unwrap();
-    let resp = client
+    let links = (
+        shorten_url(&client, &format!("steam://connect/{}", game_url)).await,
+        shorten_url(&client, &format!("steam://connect/{}", gotv_url)).await,
+    );
+    match links {
+        (Ok(url), Ok(gotv_url)) => Some((url, gotv_url)),
+        (Err(err), _) | (_, Err(err)) => {
+            // the links carry the server password, so the url is left out
+            warn!(error = %err.without_url(), "unable to shorten server links");
+            None
+        }
+    }
+}
+
+async fn shorten_url(client: &Client, url: &str) -> Result<String, Error> {
+    client
         .get(format!(
             "https://tinyurl.com/api-create.php?url={}",
-            encode(&gotv_link)
+            encode(url)
         ))
         .send()
+        .await?
+        .error_for_status()?
+        .text_with_charset("utf-8")
         .await
-        .unwrap();
-    let t_gotv_url = resp.text_with_charset("utf-8").await.unwrap();
+}
 
-    let mut m = msg.channel_id
-        .send_message(&context, |m| m.content(eos_printout(setup))
-        .components(|c|
-            c.add_action_row(
-                create_server_conn_button_row(&t_url, &t_gotv_url, true)
-            )),
-    ).await.unwrap();
+pub async fn create_conn_message(
+    context: &Context,
+    msg: &Message,
+    started: StartedServer,
+    setup: &Setup,
+) {
+    let server = started.server;
+    let game_url = format!("{}:{}", server.ip, server.ports.game);
+    let gotv_url = format!("{}:{}", server.ip, server.ports.gotv);
+    let links = create_server_links(
+        context,
+        setup.match_id,
+        &format!("{}/{}", game_url, started.password),
+        &format!("{}/{}", gotv_url, started.gotv_password),
+    )
+    .await;
+    let m = msg
+        .channel_id
+        .send_message(context, |m| {
+            m.content(eos_printout(setup))
+                .components(|c| c.add_action_row(create_server_conn_button_row(&None, true)))
+        })
+        .await;
+    let mut m = match m {
+        Ok(m) => m,
+        Err(err) => {
+            error!(error = %err, "unable to send server connect message");
+            return;
+        }
+    };
     let mut cib = m
-        .await_component_interactions(&context)
-        .timeout(Duration::from_secs(60 * 5))
+        .await_component_interactions(context)
+        .timeout(get_config(context).await.timers.conn_info_timeout())
         .build();
-    loop {
-        let opt = cib.next().await;
-        match opt {
-            Some(mci) => {
-                mci.create_interaction_response(&context, |r| {
-                    r.kind(InteractionResponseType::ChannelMessageWithSource).interaction_response_data(|d| {
-                        d.ephemeral(true).content(format!("Console: ||`connect {}`||\nGOTV: ||`connect {}`||", &game_url, &gotv_url))
+    while let Some(mci) = cib.next().await {
+        let allowed = user_in_match(context, &mci, setup).await;
+        let resp = mci
+            .create_interaction_response(context, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| {
+                        if !allowed {
+                            return d.ephemeral(true).content(
+                                "Only members of the playing teams & admins can see the server's connection info",
+                            );
+                        }
+                        d.ephemeral(true).content(format!(
+                            "Console: ||`connect {}; password {}`||\nGOTV: ||`connect {}; password {}`||",
+                            &game_url, &started.password, &gotv_url, &started.gotv_password
+                        ));
+                        if links.is_some() {
+                            d.components(|c| {
+                                c.add_action_row(create_server_conn_button_row(&links, false))
+                            });
+                        }
+                        d
                     })
-                }).await.unwrap();
-            }
-            None => {
-                // remove console cmds interaction on timeout
-                m.edit(&context, |m|
-                    m.content(eos_printout(&setup))
-                        .components(|c|
-                            c.add_action_row(create_server_conn_button_row(&t_url, &t_gotv_url, false)
-                            )
-                        ),
-                ).await.unwrap();
-                return;
-            }
+            })
+            .await;
+        if let Err(err) = resp {
+            error!(error = %err, "unable to send server connection info");
+            get_metrics(context).await.interaction_error("connect_info");
         }
     }
+    // remove console cmds interaction on timeout
+    let resp = m
+        .edit(context, |m| {
+            m.content(eos_printout(setup)).components(|c| c)
+        })
+        .await;
+    if let Err(err) = resp {
+        error!(error = %err, "unable to remove server connect buttons");
+    }
 }
 
 pub async fn get_config(context: &Context) -> Config {
@@ -0,0 +1,704 @@
+use crate::models::StepType::{Pick, Veto};
+use crate::models::{SeriesType, StepType};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Case-insensitive map identifier, `de_Nuke` and `de_nuke` refer to the same map
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MapId(String);
+
+impl MapId {
+    pub fn new(name: &str) -> Self {
+        MapId(name.trim().to_ascii_lowercase())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for MapId {
+    fn from(name: &str) -> Self {
+        MapId::new(name)
+    }
+}
+
+impl fmt::Display for MapId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MapState {
+    Available,
+    Banned {
+        by: i64,
+    },
+    Picked {
+        by: i64,
+    },
+    /// The last available map, picked by elimination rather than by a team
+    Decider,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolMap {
+    pub id: MapId,
+    /// Map name as it is stored in the map pool
+    pub name: String,
+    pub state: MapState,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VetoError {
+    UnknownMap(MapId),
+    MapUnavailable(MapId, MapState),
+    MapNotVetoed(MapId),
+}
+
+impl fmt::Display for VetoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VetoError::UnknownMap(id) => write!(f, "`{}` is not in the map pool", id),
+            VetoError::MapUnavailable(id, _) => write!(f, "`{}` has already been vetoed", id),
+            VetoError::MapNotVetoed(id) => write!(f, "`{}` has not been vetoed", id),
+        }
+    }
+}
+
+impl std::error::Error for VetoError {}
+
+/// The maps of a match setup & their veto state, in map pool order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MapPool {
+    maps: Vec<PoolMap>,
+}
+
+impl MapPool {
+    /// Creates a pool with every map available, duplicate names are ignored
+    pub fn new<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut maps: Vec<PoolMap> = Vec::new();
+        for name in names {
+            let name = name.into();
+            let id = MapId::new(&name);
+            if maps.iter().any(|m| m.id == id) {
+                continue;
+            }
+            maps.push(PoolMap {
+                id,
+                name,
+                state: MapState::Available,
+            });
+        }
+        MapPool { maps }
+    }
+
+    pub fn maps(&self) -> &[PoolMap] {
+        &self.maps
+    }
+
+    pub fn get(&self, id: &MapId) -> Option<&PoolMap> {
+        self.maps.iter().find(|m| &m.id == id)
+    }
+
+    pub fn state(&self, id: &MapId) -> Option<MapState> {
+        self.get(id).map(|m| m.state)
+    }
+
+    pub fn available(&self) -> impl Iterator<Item = &PoolMap> {
+        self.maps.iter().filter(|m| m.state == MapState::Available)
+    }
+
+    pub fn available_count(&self) -> usize {
+        self.available().count()
+    }
+
+    /// Looks up a map that is still available to be banned or picked
+    pub fn available_map(&self, id: &MapId) -> Result<&PoolMap, VetoError> {
+        let map = self
+            .get(id)
+            .ok_or_else(|| VetoError::UnknownMap(id.clone()))?;
+        if map.state != MapState::Available {
+            return Err(VetoError::MapUnavailable(id.clone(), map.state));
+        }
+        Ok(map)
+    }
+
+    pub fn ban(&mut self, id: &MapId, team_role_id: i64) -> Result<&PoolMap, VetoError> {
+        self.transition(id, MapState::Banned { by: team_role_id })
+    }
+
+    /// Picks the map for the team, picking the last available map makes it the decider
+    pub fn pick(&mut self, id: &MapId, team_role_id: i64) -> Result<&PoolMap, VetoError> {
+        if self.available_count() == 1 {
+            return self.transition(id, MapState::Decider);
+        }
+        self.transition(id, MapState::Picked { by: team_role_id })
+    }
+
+    /// Makes a banned or picked map available again
+    pub fn restore(&mut self, id: &MapId) -> Result<&PoolMap, VetoError> {
+        let map = self
+            .maps
+            .iter_mut()
+            .find(|m| &m.id == id)
+            .ok_or_else(|| VetoError::UnknownMap(id.clone()))?;
+        if map.state == MapState::Available {
+            return Err(VetoError::MapNotVetoed(id.clone()));
+        }
+        map.state = MapState::Available;
+        Ok(map)
+    }
+
+    fn transition(&mut self, id: &MapId, state: MapState) -> Result<&PoolMap, VetoError> {
+        let map = self
+            .maps
+            .iter_mut()
+            .find(|m| &m.id == id)
+            .ok_or_else(|| VetoError::UnknownMap(id.clone()))?;
+        if map.state != MapState::Available {
+            return Err(VetoError::MapUnavailable(id.clone(), map.state));
+        }
+        map.state = state;
+        Ok(map)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum State {
+    ServerPick,
+    MapVeto,
+    SidePick,
+    Completed,
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                State::ServerPick => "server pick",
+                State::MapVeto => "map veto",
+                State::SidePick => "side pick",
+                State::Completed => "completed setup",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    CT,
+    T,
+}
+
+impl FromStr for Side {
+    type Err = ();
+    fn from_str(input: &str) -> Result<Side, Self::Err> {
+        match input {
+            "ct" => Ok(Side::CT),
+            "t" => Ok(Side::T),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetupStep {
+    pub match_id: i32,
+    pub step_type: StepType,
+    pub team_role_id: i64,
+    pub map: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetupMap {
+    pub match_id: i32,
+    pub map: String,
+    pub picked_by: i64,
+    pub start_attack_team_role_id: Option<i64>,
+    pub start_defense_team_role_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    /// Adds the server to the team's ranking of preferred servers
+    RankServer {
+        team_role_id: i64,
+        server_id: String,
+    },
+    Ban {
+        team_role_id: i64,
+        map: MapId,
+    },
+    Pick {
+        team_role_id: i64,
+        map: MapId,
+    },
+    PickSide {
+        team_role_id: i64,
+        side: Side,
+    },
+    /// Rolls the setup back by a single step
+    Undo,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Event {
+    ServerRanked {
+        team_role_id: i64,
+        server_id: String,
+        rank: usize,
+    },
+    /// Both teams ranked their servers & the best mutual option was picked
+    ServerPicked {
+        server_id: String,
+    },
+    MapBanned {
+        team_role_id: i64,
+        map: String,
+    },
+    MapPicked {
+        team_role_id: i64,
+        map: String,
+        decider: bool,
+    },
+    SidePicked {
+        team_role_id: i64,
+        map: String,
+        side: Side,
+    },
+    Undone {
+        state: State,
+        step: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetupError {
+    /// The veto order needs more maps than there are in the pool
+    NotEnoughMaps {
+        needed: usize,
+        available: usize,
+    },
+    NoServers,
+    UnknownServer(String),
+    ServerAlreadyRanked(String),
+    NotInMatch(i64),
+    NotYourTurn(i64),
+    /// The action does not belong to the current phase or step
+    WrongAction(State),
+    Map(VetoError),
+    NothingToUndo,
+}
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetupError::NotEnoughMaps { needed, available } => write!(
+                f,
+                "The map pool has {} maps, but this series needs {}",
+                available, needed
+            ),
+            SetupError::NoServers => write!(f, "There are no match servers available"),
+            SetupError::UnknownServer(_) => write!(f, "That server is not available"),
+            SetupError::ServerAlreadyRanked(_) => {
+                write!(f, "Your team already ranked that server")
+            }
+            SetupError::NotInMatch(_) => {
+                write!(
+                    f,
+                    "You are not part of either team currently setting up a match"
+                )
+            }
+            SetupError::NotYourTurn(_) => write!(f, "It is not your team's turn"),
+            SetupError::WrongAction(state) => {
+                write!(f, "That selection is not possible during {}", state)
+            }
+            SetupError::Map(err) => write!(f, "{}", err),
+            SetupError::NothingToUndo => write!(f, "There is no setup step to undo"),
+        }
+    }
+}
+
+impl std::error::Error for SetupError {}
+
+impl From<VetoError> for SetupError {
+    fn from(err: VetoError) -> Self {
+        SetupError::Map(err)
+    }
+}
+
+/// Orders the servers by both teams' rankings, lowest combined rank first. Servers a team did not
+/// rank count as ranked last, ties go to the away team's preference & then to the server order.
+pub fn rank_servers(servers: &[String], team_one: &[String], team_two: &[String]) -> Vec<String> {
+    let rank = |ranking: &[String], server: &String| {
+        ranking
+            .iter()
+            .position(|s| s == server)
+            .unwrap_or(servers.len())
+    };
+    let mut ranked: Vec<String> = servers.to_vec();
+    ranked.sort_by_key(|server| {
+        (
+            rank(team_one, server) + rank(team_two, server),
+            rank(team_two, server),
+        )
+    });
+    ranked
+}
+
+/// Ban & pick order for the series, `team_two` is the away team
+pub fn veto_pick_order(
+    series_type: SeriesType,
+    match_id: i32,
+    team_one: i64,
+    team_two: i64,
+) -> Vec<SetupStep> {
+    let order: Vec<(StepType, i64)> = match series_type {
+        SeriesType::Bo1 => vec![
+            (Veto, team_two),
+            (Veto, team_one),
+            (Veto, team_two),
+            (Veto, team_one),
+            (Veto, team_two),
+            (Pick, team_one),
+        ],
+        SeriesType::Bo3 => vec![
+            (Veto, team_one),
+            (Veto, team_two),
+            (Pick, team_one),
+            (Pick, team_two),
+            (Veto, team_two),
+            (Pick, team_one),
+        ],
+        SeriesType::Bo5 => vec![
+            (Veto, team_one),
+            (Veto, team_two),
+            (Pick, team_one),
+            (Pick, team_two),
+            (Pick, team_one),
+            (Pick, team_two),
+            (Pick, team_one),
+        ],
+    };
+    order
+        .into_iter()
+        .map(|(step_type, team_role_id)| SetupStep {
+            match_id,
+            step_type,
+            team_role_id,
+            map: None,
+        })
+        .collect()
+}
+
+/// Match setup state machine, covering server pick, map veto & side picks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Setup {
+    pub team_one: i64,
+    pub team_two: i64,
+    pub team_one_name: String,
+    pub team_two_name: String,
+    pub team_one_conn_str: Option<String>,
+    pub team_two_conn_str: Option<String>,
+    pub map_pool: MapPool,
+    pub maps: Vec<SetupMap>,
+    pub series_type: SeriesType,
+    pub match_id: i32,
+    pub veto_pick_order: Vec<SetupStep>,
+    pub current_step: usize,
+    pub current_phase: State,
+    /// Servers the teams rank, by id
+    pub servers: Vec<String>,
+    pub team_one_servers: Vec<String>,
+    pub team_two_servers: Vec<String>,
+    /// Every server ordered by both teams' rankings, once both teams ranked their servers
+    pub server_ranking: Vec<String>,
+    pub server_id: Option<String>,
+}
+
+/// Number of servers each team ranks, when there are enough servers
+pub const MAX_RANKED_SERVERS: usize = 3;
+
+impl Setup {
+    pub fn new<I, S>(
+        match_id: i32,
+        series_type: SeriesType,
+        team_one: (i64, String),
+        team_two: (i64, String),
+        maps: I,
+        servers: Vec<String>,
+    ) -> Result<Self, SetupError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        if servers.is_empty() {
+            return Err(SetupError::NoServers);
+        }
+        let map_pool = MapPool::new(maps);
+        let veto_pick_order = veto_pick_order(series_type, match_id, team_one.0, team_two.0);
+        if veto_pick_order.len() > map_pool.maps().len() {
+            return Err(SetupError::NotEnoughMaps {
+                needed: veto_pick_order.len(),
+                available: map_pool.maps().len(),
+            });
+        }
+        Ok(Setup {
+            team_one: team_one.0,
+            team_two: team_two.0,
+            team_one_name: team_one.1,
+            team_two_name: team_two.1,
+            team_one_conn_str: None,
+            team_two_conn_str: None,
+            map_pool,
+            maps: vec![],
+            series_type,
+            match_id,
+            veto_pick_order,
+            current_step: 0,
+            current_phase: State::ServerPick,
+            servers,
+            team_one_servers: vec![],
+            team_two_servers: vec![],
+            server_ranking: vec![],
+            server_id: None,
+        })
+    }
+
+    /// Number of servers each team ranks
+    pub fn servers_to_rank(&self) -> usize {
+        self.servers.len().min(MAX_RANKED_SERVERS)
+    }
+
+    /// The team's ranking of preferred servers so far
+    pub fn team_servers(&self, team_role_id: i64) -> &[String] {
+        if team_role_id == self.team_one {
+            &self.team_one_servers
+        } else {
+            &self.team_two_servers
+        }
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.current_phase == State::Completed
+    }
+
+    pub fn other_team(&self, team_role_id: i64) -> i64 {
+        if team_role_id == self.team_one {
+            self.team_two
+        } else {
+            self.team_one
+        }
+    }
+
+    /// The veto step waiting to be made, if the setup is in the map veto phase
+    pub fn veto_step(&self) -> Option<&SetupStep> {
+        match self.current_phase {
+            State::MapVeto => self.veto_pick_order.get(self.current_step),
+            _ => None,
+        }
+    }
+
+    /// The map waiting for a starting side, if the setup is in the side pick phase
+    pub fn side_pick_map(&self) -> Option<&SetupMap> {
+        match self.current_phase {
+            State::SidePick => self.maps.get(self.current_step),
+            _ => None,
+        }
+    }
+
+    /// The team whose turn it is, the away team ranks its servers first & the team that did not
+    /// pick a map picks its starting side
+    pub fn turn_role_id(&self) -> Option<i64> {
+        match self.current_phase {
+            State::ServerPick if self.team_two_servers.len() < self.servers_to_rank() => {
+                Some(self.team_two)
+            }
+            State::ServerPick => Some(self.team_one),
+            State::MapVeto => self.veto_step().map(|s| s.team_role_id),
+            State::SidePick => self.side_pick_map().map(|m| self.other_team(m.picked_by)),
+            State::Completed => None,
+        }
+    }
+
+    /// Applies the action to the setup. The setup is left untouched if the action is rejected.
+    pub fn apply(&mut self, action: Action) -> Result<Event, SetupError> {
+        if let Action::Undo = action {
+            return self.undo();
+        }
+        let team_role_id = match &action {
+            Action::RankServer { team_role_id, .. }
+            | Action::Ban { team_role_id, .. }
+            | Action::Pick { team_role_id, .. }
+            | Action::PickSide { team_role_id, .. } => *team_role_id,
+            Action::Undo => unreachable!(),
+        };
+        if team_role_id != self.team_one && team_role_id != self.team_two {
+            return Err(SetupError::NotInMatch(team_role_id));
+        }
+        match (self.current_phase, action) {
+            (State::ServerPick, Action::RankServer { server_id, .. }) => {
+                self.check_turn(team_role_id)?;
+                if !self.servers.contains(&server_id) {
+                    return Err(SetupError::UnknownServer(server_id));
+                }
+                if self.team_servers(team_role_id).contains(&server_id) {
+                    return Err(SetupError::ServerAlreadyRanked(server_id));
+                }
+                let team_servers = if team_role_id == self.team_one {
+                    &mut self.team_one_servers
+                } else {
+                    &mut self.team_two_servers
+                };
+                team_servers.push(server_id.clone());
+                let rank = team_servers.len();
+                if self.team_one_servers.len() < self.servers_to_rank() {
+                    return Ok(Event::ServerRanked {
+                        team_role_id,
+                        server_id,
+                        rank,
+                    });
+                }
+                self.server_ranking = rank_servers(
+                    &self.servers,
+                    &self.team_one_servers,
+                    &self.team_two_servers,
+                );
+                let server_id = self.server_ranking[0].clone();
+                self.server_id = Some(server_id.clone());
+                self.current_phase = State::MapVeto;
+                self.current_step = 0;
+                Ok(Event::ServerPicked { server_id })
+            }
+            (State::MapVeto, Action::Ban { map, .. }) => {
+                self.check_step(Veto)?;
+                self.check_turn(team_role_id)?;
+                let name = self.map_pool.ban(&map, team_role_id)?.name.clone();
+                self.advance_veto(name.clone());
+                Ok(Event::MapBanned {
+                    team_role_id,
+                    map: name,
+                })
+            }
+            (State::MapVeto, Action::Pick { map, .. }) => {
+                self.check_step(Pick)?;
+                self.check_turn(team_role_id)?;
+                let picked = self.map_pool.pick(&map, team_role_id)?;
+                let name = picked.name.clone();
+                let decider = picked.state == MapState::Decider;
+                self.maps.push(SetupMap {
+                    match_id: self.match_id,
+                    map: name.clone(),
+                    picked_by: team_role_id,
+                    start_attack_team_role_id: None,
+                    start_defense_team_role_id: None,
+                });
+                self.advance_veto(name.clone());
+                Ok(Event::MapPicked {
+                    team_role_id,
+                    map: name,
+                    decider,
+                })
+            }
+            (State::SidePick, Action::PickSide { side, .. }) => {
+                self.check_turn(team_role_id)?;
+                let other_role_id = self.other_team(team_role_id);
+                let map = &mut self.maps[self.current_step];
+                let (ct, t) = match side {
+                    Side::CT => (team_role_id, other_role_id),
+                    Side::T => (other_role_id, team_role_id),
+                };
+                map.start_defense_team_role_id = Some(ct);
+                map.start_attack_team_role_id = Some(t);
+                let name = map.map.clone();
+                self.current_step += 1;
+                if self.current_step == self.maps.len() {
+                    self.current_phase = State::Completed;
+                }
+                Ok(Event::SidePicked {
+                    team_role_id,
+                    map: name,
+                    side,
+                })
+            }
+            (state, _) => Err(SetupError::WrongAction(state)),
+        }
+    }
+
+    fn check_turn(&self, team_role_id: i64) -> Result<(), SetupError> {
+        if self.turn_role_id() != Some(team_role_id) {
+            return Err(SetupError::NotYourTurn(team_role_id));
+        }
+        Ok(())
+    }
+
+    fn check_step(&self, step_type: StepType) -> Result<(), SetupError> {
+        match self.veto_step() {
+            Some(step) if step.step_type == step_type => Ok(()),
+            _ => Err(SetupError::WrongAction(self.current_phase)),
+        }
+    }
+
+    fn advance_veto(&mut self, map: String) {
+        self.veto_pick_order[self.current_step].map = Some(map);
+        if self.current_step + 1 == self.veto_pick_order.len() {
+            self.current_step = 0;
+            self.current_phase = State::SidePick;
+        } else {
+            self.current_step += 1;
+        }
+    }
+
+    fn undo(&mut self) -> Result<Event, SetupError> {
+        match self.current_phase {
+            State::ServerPick => {
+                if self.team_one_servers.pop().is_none() && self.team_two_servers.pop().is_none() {
+                    return Err(SetupError::NothingToUndo);
+                }
+            }
+            State::MapVeto if self.current_step == 0 => {
+                self.server_id = None;
+                self.server_ranking.clear();
+                self.team_one_servers.pop();
+                self.current_phase = State::ServerPick;
+            }
+            State::MapVeto => self.undo_veto_step(self.current_step - 1),
+            State::SidePick if self.current_step == 0 => {
+                self.current_phase = State::MapVeto;
+                self.undo_veto_step(self.veto_pick_order.len() - 1);
+            }
+            State::SidePick | State::Completed => {
+                self.current_phase = State::SidePick;
+                self.current_step -= 1;
+                let map = &mut self.maps[self.current_step];
+                map.start_attack_team_role_id = None;
+                map.start_defense_team_role_id = None;
+            }
+        }
+        Ok(Event::Undone {
+            state: self.current_phase,
+            step: self.current_step,
+        })
+    }
+
+    fn undo_veto_step(&mut self, step: usize) {
+        self.current_step = step;
+        if let Some(map) = self.veto_pick_order[step].map.take() {
+            if self.veto_pick_order[step].step_type == Pick {
+                self.maps.pop();
+            }
+            self.map_pool
+                .restore(&MapId::new(&map))
+                .expect("Expected vetoed map");
+        }
+    }
+}
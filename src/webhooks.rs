@@ -0,0 +1,197 @@
+use crate::utils::random_secret;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use csgo_matchbot::config::WebhookConfig;
+use csgo_matchbot::crypto::secret_hash;
+use serenity::prelude::TypeMapKey;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tracing::{error, info};
+
+const SECRET_LENGTH: usize = 32;
+/// Connect redirects outlive their match's tracking, untracked matches are never removed
+const SERVER_LINK_TTL: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Webhook URLs & authorization Dathost is given when starting a match
+pub struct MatchWebhooks {
+    pub round_end_url: String,
+    pub match_end_url: String,
+    pub authorization: String,
+}
+
+struct LiveMatch {
+    /// Only the hash is kept, so checking a request's secret does not depend on where it differs
+    secret_hash: String,
+    events: Arc<Notify>,
+}
+
+/// Game & GOTV `ip:port/password` addresses of a match's server, only served with its key
+struct ServerAddrs {
+    key_hash: String,
+    game: String,
+    gotv: String,
+    expires_at: Instant,
+}
+
+/// Matches currently being played, keyed by match id, that accept webhook events & have their
+/// server's connect redirects served
+#[derive(Default)]
+pub struct LiveMatches {
+    matches: Mutex<HashMap<i32, LiveMatch>>,
+    servers: Mutex<HashMap<i32, ServerAddrs>>,
+}
+
+impl TypeMapKey for LiveMatches {
+    type Value = Arc<LiveMatches>;
+}
+
+impl LiveMatches {
+    /// Starts accepting events for the match with a freshly generated secret
+    pub fn register(&self, config: &WebhookConfig, match_id: i32) -> MatchWebhooks {
+        let secret = random_secret(SECRET_LENGTH);
+        self.matches.lock().unwrap().insert(
+            match_id,
+            LiveMatch {
+                secret_hash: secret_hash(&secret),
+                events: Arc::new(Notify::new()),
+            },
+        );
+        let base_url = config.public_url.trim_end_matches('/');
+        MatchWebhooks {
+            round_end_url: format!("{}/webhooks/matches/{}/round-end", base_url, match_id),
+            match_end_url: format!("{}/webhooks/matches/{}/match-end", base_url, match_id),
+            authorization: secret,
+        }
+    }
+
+    /// Notified on every accepted event for the match
+    pub fn events(&self, match_id: i32) -> Option<Arc<Notify>> {
+        self.matches
+            .lock()
+            .unwrap()
+            .get(&match_id)
+            .map(|m| m.events.clone())
+    }
+
+    /// Serves the server's connect redirects until the match is removed or for at most
+    /// `SERVER_LINK_TTL`, returning the connect & GOTV links
+    pub fn set_server(
+        &self,
+        config: &WebhookConfig,
+        match_id: i32,
+        game: String,
+        gotv: String,
+    ) -> (String, String) {
+        let key = random_secret(SECRET_LENGTH);
+        let base_url = config.public_url.trim_end_matches('/');
+        let links = (
+            format!("{}/connect/{}/{}", base_url, match_id, key),
+            format!("{}/gotv/{}/{}", base_url, match_id, key),
+        );
+        let now = Instant::now();
+        let mut servers = self.servers.lock().unwrap();
+        servers.retain(|_, server| server.expires_at > now);
+        servers.insert(
+            match_id,
+            ServerAddrs {
+                key_hash: secret_hash(&key),
+                game,
+                gotv,
+                expires_at: now + SERVER_LINK_TTL,
+            },
+        );
+        links
+    }
+
+    pub fn remove(&self, match_id: i32) {
+        self.matches.lock().unwrap().remove(&match_id);
+        self.servers.lock().unwrap().remove(&match_id);
+    }
+
+    /// Wakes the match's tracker, which polls Dathost for the match's state. The event's payload is
+    /// deliberately not read: it only describes a single map, while the scoreboard & the reported
+    /// result need the series' state, which only the poll of the match series returns.
+    fn dispatch(&self, match_id: i32, authorization: Option<&str>) -> StatusCode {
+        let matches = self.matches.lock().unwrap();
+        let live_match = match matches.get(&match_id) {
+            Some(live_match) => live_match,
+            None => return StatusCode::NOT_FOUND,
+        };
+        if authorization.map(secret_hash).as_ref() != Some(&live_match.secret_hash) {
+            return StatusCode::UNAUTHORIZED;
+        }
+        live_match.events.notify_one();
+        StatusCode::NO_CONTENT
+    }
+}
+
+/// Runs the webhook receiver & connect redirects until the bot shuts down
+pub async fn serve(config: WebhookConfig, live_matches: Arc<LiveMatches>) {
+    let addr: SocketAddr = config
+        .bind_addr
+        .parse()
+        .expect("WEBHOOK_BIND_ADDR is not a valid socket address");
+    let app = Router::new()
+        .route("/webhooks/matches/:match_id/:event", post(match_event))
+        .route("/connect/:match_id/:key", get(connect))
+        .route("/gotv/:match_id/:key", get(gotv))
+        .with_state(live_matches);
+    info!(%addr, "receiving webhooks");
+    if let Err(err) = axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await
+    {
+        error!(error = %err, "webhook server error");
+    }
+}
+
+async fn match_event(
+    State(live_matches): State<Arc<LiveMatches>>,
+    Path((match_id, event)): Path<(i32, String)>,
+    headers: HeaderMap,
+) -> StatusCode {
+    if event != "round-end" && event != "match-end" {
+        return StatusCode::NOT_FOUND;
+    }
+    let authorization = headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok());
+    live_matches.dispatch(match_id, authorization)
+}
+
+async fn connect(
+    State(live_matches): State<Arc<LiveMatches>>,
+    Path((match_id, key)): Path<(i32, String)>,
+) -> Response {
+    redirect(&live_matches, match_id, &key, |server| &server.game)
+}
+
+async fn gotv(
+    State(live_matches): State<Arc<LiveMatches>>,
+    Path((match_id, key)): Path<(i32, String)>,
+) -> Response {
+    redirect(&live_matches, match_id, &key, |server| &server.gotv)
+}
+
+fn redirect(
+    live_matches: &LiveMatches,
+    match_id: i32,
+    key: &str,
+    addr: impl Fn(&ServerAddrs) -> &String,
+) -> Response {
+    let servers = live_matches.servers.lock().unwrap();
+    match servers.get(&match_id) {
+        Some(server)
+            if server.key_hash == secret_hash(key) && server.expires_at > Instant::now() =>
+        {
+            Redirect::temporary(&format!("steam://connect/{}", addr(server))).into_response()
+        }
+        _ => StatusCode::NOT_FOUND.into_response(),
+    }
+}
@@ -0,0 +1,158 @@
+use csgo_matchbot::bracket::{
+    advancing, build_bracket, report_winner, seed_order, BracketError, Slot,
+};
+use csgo_matchbot::models::{BracketFormat, BracketMatch, BracketSection};
+use std::collections::HashMap;
+
+fn seeds(count: i64) -> Vec<i64> {
+    (1..=count).collect()
+}
+
+/// Decides every match as it becomes ready, the team with the lower role id, i.e. the better
+/// seed, wins unless `upset` says otherwise
+fn play_out(matches: &mut [BracketMatch], upset: impl Fn(i64, i64) -> bool) -> usize {
+    let mut played = 0;
+    while let Some(seq) = matches.iter().position(|m| m.is_ready()) {
+        let (a, b) = (
+            matches[seq].team_one_role_id.unwrap(),
+            matches[seq].team_two_role_id.unwrap(),
+        );
+        let (better, worse) = (a.min(b), a.max(b));
+        let winner = if upset(better, worse) { worse } else { better };
+        report_winner(matches, seq, winner).unwrap();
+        played += 1;
+    }
+    played
+}
+
+#[test]
+fn seeds_are_spread_over_the_bracket() {
+    assert_eq!(seed_order(2), vec![1, 2]);
+    assert_eq!(seed_order(8), vec![1, 8, 4, 5, 2, 7, 3, 6]);
+}
+
+#[test]
+fn single_elimination_is_won_by_the_top_seed() {
+    let mut matches = build_bracket(1, BracketFormat::Single, &seeds(8)).unwrap();
+    assert_eq!(matches.len(), 7);
+    assert_eq!(matches.iter().filter(|m| m.is_ready()).count(), 4);
+    assert_eq!(play_out(&mut matches, |_, _| false), 7);
+    assert_eq!(advancing(&matches), vec![1]);
+}
+
+#[test]
+fn top_seeds_get_the_byes() {
+    let matches = build_bracket(1, BracketFormat::Single, &seeds(6)).unwrap();
+    let ready: Vec<(i64, i64)> = matches
+        .iter()
+        .filter(|m| m.is_ready())
+        .map(|m| (m.team_one_role_id.unwrap(), m.team_two_role_id.unwrap()))
+        .collect();
+    assert_eq!(ready, vec![(4, 5), (3, 6)]);
+    let semi_finals: Vec<Slot> = matches
+        .iter()
+        .filter(|m| m.round == 2)
+        .flat_map(|m| [m.slot(0), m.slot(1)])
+        .collect();
+    assert_eq!(
+        semi_finals,
+        vec![Slot::Team(1), Slot::Pending, Slot::Team(2), Slot::Pending]
+    );
+}
+
+#[test]
+fn upper_bracket_losers_get_a_second_chance() {
+    let mut matches = build_bracket(1, BracketFormat::Double, &seeds(4)).unwrap();
+    assert_eq!(matches.len(), 6);
+    // seed 4 upsets seed 1 in the first round & still wins the grand final
+    assert_eq!(play_out(&mut matches, |_, worse| worse == 4), 6);
+    let grand_final = matches.last().unwrap();
+    assert_eq!(grand_final.section, BracketSection::Final);
+    assert_eq!(advancing(&matches), vec![4]);
+    let lower_final = matches
+        .iter()
+        .rev()
+        .find(|m| m.section == BracketSection::Lower)
+        .unwrap();
+    assert_eq!(lower_final.winner_role_id, Some(1));
+}
+
+#[test]
+fn double_elimination_eliminates_teams_after_two_losses() {
+    for count in [5, 8, 11, 16] {
+        let mut matches = build_bracket(1, BracketFormat::Double, &seeds(count)).unwrap();
+        play_out(&mut matches, |better, worse| (better + worse) % 3 == 0);
+        let mut losses: HashMap<i64, i32> = HashMap::new();
+        for m in matches.iter().filter(|m| m.winner_role_id.is_some()) {
+            if let Some((_, Slot::Team(loser))) = m.outcome() {
+                *losses.entry(loser).or_default() += 1;
+            }
+        }
+        let champion = advancing(&matches);
+        assert_eq!(champion.len(), 1, "{} teams", count);
+        assert!(losses.get(&champion[0]).copied().unwrap_or_default() <= 1);
+        let grand_final_loser = match matches.last().unwrap().outcome() {
+            Some((_, Slot::Team(loser))) => loser,
+            _ => panic!("grand final not played with {} teams", count),
+        };
+        for team in seeds(count) {
+            if team != champion[0] && team != grand_final_loser {
+                assert_eq!(losses.get(&team), Some(&2), "team {} of {}", team, count);
+            }
+        }
+    }
+}
+
+#[test]
+fn gsl_groups_advance_two_teams_each() {
+    let mut matches = build_bracket(1, BracketFormat::Gsl, &seeds(8)).unwrap();
+    assert_eq!(matches.len(), 10);
+    let group_a: Vec<Slot> = matches
+        .iter()
+        .filter(|m| m.group_number == Some(0) && m.round == 1)
+        .flat_map(|m| [m.slot(0), m.slot(1)])
+        .collect();
+    assert_eq!(
+        group_a,
+        vec![Slot::Team(1), Slot::Team(8), Slot::Team(4), Slot::Team(5)]
+    );
+    assert_eq!(play_out(&mut matches, |_, _| false), 10);
+    assert_eq!(advancing(&matches), vec![1, 4, 2, 3]);
+    assert_eq!(matches[4].label(BracketFormat::Gsl), "Group A decider");
+}
+
+#[test]
+fn matches_are_only_decided_once_by_their_teams() {
+    let mut matches = build_bracket(1, BracketFormat::Single, &seeds(4)).unwrap();
+    assert_eq!(
+        report_winner(&mut matches, 2, 1),
+        Err(BracketError::NotReady)
+    );
+    assert_eq!(
+        report_winner(&mut matches, 0, 2),
+        Err(BracketError::NotInMatch)
+    );
+    assert_eq!(report_winner(&mut matches, 0, 1), Ok(vec![0, 2]));
+    assert_eq!(
+        report_winner(&mut matches, 0, 1),
+        Err(BracketError::AlreadyDecided)
+    );
+    // seed 1's bye is decided as soon as the bracket is built
+    let mut matches = build_bracket(1, BracketFormat::Single, &seeds(3)).unwrap();
+    assert_eq!(
+        report_winner(&mut matches, 0, 1),
+        Err(BracketError::AlreadyDecided)
+    );
+}
+
+#[test]
+fn brackets_need_distinct_teams() {
+    assert_eq!(
+        build_bracket(1, BracketFormat::Double, &[1]),
+        Err(BracketError::NotEnoughTeams)
+    );
+    assert_eq!(
+        build_bracket(1, BracketFormat::Single, &[1, 2, 1]),
+        Err(BracketError::DuplicateTeam(1))
+    );
+}
@@ -0,0 +1,193 @@
+use csgo_matchbot::config::{load_config_from, load_keyring_from, Config, ConfigError};
+use std::collections::HashMap;
+use std::fs;
+
+const YAML_CONFIG: &str = "
+discord:
+  token: file-token
+  admin_role_id: 1
+  application_id: 2
+  guild_id: 3
+timers:
+  poll_interval: 5
+secret_keys: 'test:AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE='
+";
+
+const TOML_CONFIG: &str = "
+secret_keys = 'test:AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE='
+
+[discord]
+token = 'file-token'
+admin_role_id = 1
+application_id = 2
+guild_id = 3
+
+[timers]
+poll_interval = 5
+";
+
+/// Writes the config to a file of its own in the temp dir, the extension decides its format
+fn config_file(name: &str, contents: &str) -> String {
+    let path = std::env::temp_dir().join(format!("matchbot-{}-{}", std::process::id(), name));
+    fs::write(&path, contents).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+fn load(path: Option<&str>, env: &[(&str, &str)]) -> Result<Config, ConfigError> {
+    let env: HashMap<&str, &str> = env.iter().copied().collect();
+    load_config_from(path, |var| env.get(var).map(|value| value.to_string()))
+}
+
+fn invalid<T>(result: Result<T, ConfigError>) -> Vec<String> {
+    match result {
+        Err(ConfigError::Invalid(errors)) => errors,
+        Err(err) => panic!("expected an invalid config, got: {}", err),
+        Ok(_) => panic!("expected an invalid config"),
+    }
+}
+
+#[test]
+fn yaml_and_toml_files_are_read_by_extension() {
+    for (name, contents) in [
+        ("yaml.yml", YAML_CONFIG),
+        ("yaml.yaml", YAML_CONFIG),
+        ("toml.toml", TOML_CONFIG),
+    ] {
+        let path = config_file(name, contents);
+        let config = load(Some(&path), &[]).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(config.discord.token, "file-token", "{}", name);
+        assert_eq!(config.discord.guild_id, 3, "{}", name);
+        assert_eq!(config.discord.team_role_prefix, "Team", "{}", name);
+        assert_eq!(config.timers.poll_interval, 5, "{}", name);
+        assert_eq!(config.timers.webhook_poll_interval, 60, "{}", name);
+    }
+    // toml isn't valid yaml, so the extension has to decide
+    let path = config_file("toml.yml", TOML_CONFIG);
+    let result = load(Some(&path), &[]);
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(ConfigError::Parse(..))));
+}
+
+#[test]
+fn env_vars_override_the_file() {
+    let path = config_file("override.yml", YAML_CONFIG);
+    let config = load(
+        Some(&path),
+        &[("DISCORD_TOKEN", "env-token"), ("DISCORD_GUILD_ID", " 4 ")],
+    )
+    .unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(config.discord.token, "env-token");
+    assert_eq!(config.discord.guild_id, 4);
+    assert_eq!(config.discord.admin_role_id, 1);
+}
+
+#[test]
+fn env_vars_alone_are_a_config() {
+    let env = [
+        ("DISCORD_TOKEN", "env-token"),
+        ("DISCORD_ADMIN_ROLE_ID", "1"),
+        ("DISCORD_APPLICATION_ID", "2"),
+        ("DISCORD_GUILD_ID", "3"),
+        (
+            "SECRET_KEYS",
+            "test:AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=",
+        ),
+        ("WEBHOOK_BIND_ADDR", "127.0.0.1:8000"),
+        ("DATHOST_PASSWORD", "password"),
+    ];
+    // vars within a section are ignored until the section is configured
+    let config = load(None, &env).unwrap();
+    assert!(config.webhooks.is_none());
+    assert!(config.dathost.is_none());
+    let config = load(
+        None,
+        &[
+            &env[..],
+            &[
+                ("WEBHOOK_PUBLIC_URL", "https://matchbot.example.com"),
+                ("DATHOST_USER", "user"),
+            ],
+        ]
+        .concat(),
+    )
+    .unwrap();
+    assert_eq!(config.webhooks.unwrap().bind_addr, "127.0.0.1:8000");
+    assert_eq!(config.dathost.unwrap().password, "password");
+}
+
+#[test]
+fn malformed_env_vars_are_rejected() {
+    let path = config_file("malformed.yml", YAML_CONFIG);
+    let integer = load(Some(&path), &[("DISCORD_GUILD_ID", "guild")]);
+    let bool = load(Some(&path), &[("TINYURL_FALLBACK", "yes")]);
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(integer, Err(ConfigError::Parse(var, _)) if var == "DISCORD_GUILD_ID"));
+    assert!(matches!(bool, Err(ConfigError::Parse(var, _)) if var == "TINYURL_FALLBACK"));
+}
+
+#[test]
+fn every_invalid_value_is_reported() {
+    let path = config_file("invalid.yml", YAML_CONFIG);
+    let errors = invalid(load(
+        Some(&path),
+        &[
+            ("DISCORD_TOKEN", " "),
+            ("DISCORD_GUILD_ID", "0"),
+            ("WEBHOOK_PUBLIC_URL", "matchbot.example.com"),
+            ("WEBHOOK_BIND_ADDR", "8080"),
+            ("METRICS_BIND_ADDR", "localhost"),
+            ("DEMO_STORAGE", "local"),
+            ("DEMO_DIR", ""),
+            ("SECRET_KEYS", "test:not-a-key"),
+        ],
+    ));
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        errors,
+        vec![
+            "discord.token is empty",
+            "discord.guild_id is 0",
+            "webhooks.bind_addr is not a socket address, i.e. 0.0.0.0:8080",
+            "webhooks.public_url is not an http(s) url",
+            "metrics.bind_addr is not a socket address, i.e. 127.0.0.1:9090",
+            "demos.dir is empty",
+            "demos.public_url is not an http(s) url",
+            "secret_keys is invalid: secret key 'test' is not a base64 encoded 32 byte key",
+        ]
+    );
+    let path = config_file(
+        "timers.toml",
+        &format!("{}webhook_poll_interval = 0\n", TOML_CONFIG),
+    );
+    let errors = invalid(load(Some(&path), &[]));
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        errors,
+        vec!["timers.webhook_poll_interval must be at least 1 second"]
+    );
+}
+
+#[test]
+fn secret_keys_are_required() {
+    let path = config_file("keys.yml", YAML_CONFIG);
+    let keyring = load_keyring_from(Some(&path), |_| None);
+    let overridden = load_keyring_from(Some(&path), |var| {
+        (var == "SECRET_KEYS").then(|| String::from("new:"))
+    });
+    fs::remove_file(&path).unwrap();
+    assert!(keyring.is_ok());
+    assert_eq!(
+        invalid(overridden),
+        vec!["secret_keys is invalid: secret key 'new' is not a base64 encoded 32 byte key"]
+    );
+    assert_eq!(
+        invalid(load_keyring_from(None, |_| None)),
+        vec!["secret_keys is invalid: no secret keys given"]
+    );
+    let path = config_file("no-keys.toml", &TOML_CONFIG.replace("secret_keys", "#"));
+    let errors = invalid(load(Some(&path), &[]));
+    fs::remove_file(&path).unwrap();
+    assert_eq!(errors, vec!["secret_keys is invalid: no secret keys given"]);
+}
@@ -0,0 +1,68 @@
+use csgo_matchbot::crypto::{secret_hash, CryptoError, Keyring};
+
+const OLD_KEY: &str = "old:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+const NEW_KEY: &str = "new:AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=";
+
+#[test]
+fn secrets_round_trip() {
+    let keyring = Keyring::parse(NEW_KEY).unwrap();
+    let encrypted = keyring.encrypt("ABCDEF0123456789");
+    assert!(!encrypted.contains("ABCDEF0123456789"));
+    assert_ne!(encrypted, keyring.encrypt("ABCDEF0123456789"));
+    assert_eq!(keyring.decrypt(&encrypted).unwrap(), "ABCDEF0123456789");
+    assert!(!keyring.needs_reencryption(&encrypted));
+}
+
+#[test]
+fn rotated_keys_still_decrypt() {
+    let old = Keyring::parse(OLD_KEY).unwrap();
+    let encrypted = old.encrypt("token");
+    let rotated = Keyring::parse(&format!("{},{}", NEW_KEY, OLD_KEY)).unwrap();
+    assert_eq!(rotated.decrypt(&encrypted).unwrap(), "token");
+    assert!(rotated.needs_reencryption(&encrypted));
+    let reencrypted = rotated.reencrypt(&encrypted).unwrap();
+    assert!(!rotated.needs_reencryption(&reencrypted));
+    let new = Keyring::parse(NEW_KEY).unwrap();
+    assert_eq!(new.decrypt(&reencrypted).unwrap(), "token");
+    assert_eq!(
+        new.decrypt(&encrypted),
+        Err(CryptoError::UnknownKey(String::from("old")))
+    );
+}
+
+#[test]
+fn plaintext_is_encrypted_on_reencryption() {
+    let keyring = Keyring::parse(NEW_KEY).unwrap();
+    assert!(keyring.needs_reencryption("token"));
+    assert_eq!(keyring.decrypt("token"), Err(CryptoError::Malformed));
+    let encrypted = keyring.reencrypt("token").unwrap();
+    assert_eq!(keyring.decrypt(&encrypted).unwrap(), "token");
+}
+
+#[test]
+fn tampered_secrets_are_rejected() {
+    let keyring = Keyring::parse(NEW_KEY).unwrap();
+    let encrypted = keyring.encrypt("token");
+    let mut tampered = encrypted.into_bytes();
+    let last = tampered.len() - 3;
+    tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+    let tampered = String::from_utf8(tampered).unwrap();
+    assert_eq!(keyring.decrypt(&tampered), Err(CryptoError::Decrypt));
+}
+
+#[test]
+fn invalid_keys_are_rejected() {
+    assert_eq!(Keyring::parse("").unwrap_err(), CryptoError::NoKeys);
+    assert_eq!(
+        Keyring::parse("short:AAAA").unwrap_err(),
+        CryptoError::InvalidKey(String::from("short"))
+    );
+}
+
+#[test]
+fn secret_hash_is_hex_sha256() {
+    assert_eq!(
+        secret_hash("abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
@@ -0,0 +1,157 @@
+use chrono::NaiveDate;
+use csgo_matchbot::models::{
+    parse_schedule, Match, MatchChanges, MatchEditError, MatchState, SeriesType,
+};
+
+fn test_match(state: MatchState) -> Match {
+    Match {
+        match_state: state,
+        ..Match::new(
+            1,
+            (1, "Team A"),
+            (2, "Team B"),
+            SeriesType::Bo3,
+            NaiveDate::from_ymd_opt(2022, 8, 30)
+                .and_then(|d| d.and_hms_opt(18, 0, 0))
+                .unwrap(),
+        )
+    }
+}
+
+#[test]
+fn only_changed_fields_are_logged() {
+    let m = test_match(MatchState::Entered);
+    let changes = MatchChanges {
+        series_type: Some(SeriesType::Bo3),
+        note: Some(String::from("Playoffs")),
+        ..MatchChanges::default()
+    };
+    let field_changes = changes.field_changes(&m, false).unwrap();
+    assert_eq!(field_changes.len(), 1);
+    assert_eq!(field_changes[0].field, "note");
+    assert_eq!(field_changes[0].old_value, None);
+    assert_eq!(field_changes[0].new_value.as_deref(), Some("Playoffs"));
+}
+
+#[test]
+fn teams_are_logged_with_role_ids() {
+    let m = test_match(MatchState::Entered);
+    let changes = MatchChanges {
+        team_two_role_id: Some(3),
+        team_two_name: Some(String::from("Team C")),
+        ..MatchChanges::default()
+    };
+    let field_changes = changes.field_changes(&m, false).unwrap();
+    assert_eq!(field_changes[0].field, "team two");
+    assert_eq!(field_changes[0].old_value.as_deref(), Some("Team B (2)"));
+    assert_eq!(field_changes[0].new_value.as_deref(), Some("Team C (3)"));
+}
+
+#[test]
+fn edits_without_changes_are_rejected() {
+    let m = test_match(MatchState::Entered);
+    let changes = MatchChanges {
+        series_type: Some(SeriesType::Bo3),
+        ..MatchChanges::default()
+    };
+    assert_eq!(
+        changes.field_changes(&m, false),
+        Err(MatchEditError::NoChanges)
+    );
+}
+
+#[test]
+fn team_cannot_play_itself() {
+    let m = test_match(MatchState::Entered);
+    let changes = MatchChanges {
+        team_one_role_id: Some(2),
+        team_one_name: Some(String::from("Team B")),
+        ..MatchChanges::default()
+    };
+    assert_eq!(
+        changes.field_changes(&m, false),
+        Err(MatchEditError::SameTeams)
+    );
+}
+
+#[test]
+fn series_type_and_teams_are_fixed_after_veto() {
+    let m = test_match(MatchState::Completed);
+    let series_type = MatchChanges {
+        series_type: Some(SeriesType::Bo1),
+        ..MatchChanges::default()
+    };
+    assert_eq!(
+        series_type.field_changes(&m, false),
+        Err(MatchEditError::SetupCompleted("series type"))
+    );
+    let team = MatchChanges {
+        team_one_role_id: Some(3),
+        team_one_name: Some(String::from("Team C")),
+        ..MatchChanges::default()
+    };
+    assert_eq!(
+        team.field_changes(&m, false),
+        Err(MatchEditError::SetupCompleted("teams"))
+    );
+}
+
+#[test]
+fn completed_matches_can_be_renamed_and_rescheduled() {
+    let m = test_match(MatchState::Completed);
+    let changes = MatchChanges {
+        team_one_role_id: Some(1),
+        team_one_name: Some(String::from("Team A Academy")),
+        scheduled_time_str: Some(String::from("Friday 20:00")),
+        ..MatchChanges::default()
+    };
+    assert_eq!(changes.field_changes(&m, false).unwrap().len(), 2);
+}
+
+#[test]
+fn archived_matches_cannot_be_edited() {
+    let m = test_match(MatchState::Archived);
+    let changes = MatchChanges {
+        note: Some(String::from("Playoffs")),
+        ..MatchChanges::default()
+    };
+    assert_eq!(
+        changes.field_changes(&m, false),
+        Err(MatchEditError::Archived)
+    );
+}
+
+#[test]
+fn series_type_and_teams_are_fixed_during_veto() {
+    let m = test_match(MatchState::Scheduled);
+    let team = MatchChanges {
+        team_two_role_id: Some(3),
+        team_two_name: Some(String::from("Team C")),
+        ..MatchChanges::default()
+    };
+    assert_eq!(
+        team.field_changes(&m, true),
+        Err(MatchEditError::SetupInProgress("teams"))
+    );
+    assert!(team.field_changes(&m, false).is_ok());
+    let note = MatchChanges {
+        note: Some(String::from("Playoffs")),
+        ..MatchChanges::default()
+    };
+    assert!(note.field_changes(&m, true).is_ok());
+}
+
+#[test]
+fn schedules_are_normalized() {
+    assert_eq!(
+        parse_schedule("8/30/2022 @ 6:00 pm cet").as_deref(),
+        Some("08/30/2022 @ 18:00 CET")
+    );
+    assert_eq!(
+        parse_schedule("08/30/2022@18:00").as_deref(),
+        Some("08/30/2022 @ 18:00")
+    );
+    assert_eq!(parse_schedule("Friday 20:00"), None);
+    assert_eq!(parse_schedule("30/08/2022 @ 18:00"), None);
+    assert_eq!(parse_schedule("08/30/2022 @ 18:00 next week"), None);
+}
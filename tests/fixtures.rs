@@ -0,0 +1,215 @@
+use chrono::NaiveDate;
+use csgo_matchbot::fixtures::{
+    assign_divisions, division_round_robin, fill_deadlines, parse_fixtures, round_deadline,
+    round_robin, FixtureError, FixtureFormat,
+};
+use csgo_matchbot::models::{SeasonTeam, SeriesType, Team};
+use std::collections::{HashMap, HashSet};
+
+fn teams(count: i64) -> Vec<Team> {
+    (1..=count)
+        .map(|role_id| Team {
+            role_id,
+            name: format!("Team {}", role_id),
+            captain_discord_id: None,
+            vice_captain_discord_id: None,
+        })
+        .collect()
+}
+
+#[test]
+fn every_team_plays_every_other_team_once() {
+    for count in 2..=11 {
+        let fixtures = round_robin(&teams(count), false);
+        let pairs: HashSet<(i64, i64)> = fixtures
+            .iter()
+            .map(|f| {
+                let (a, b) = (f.team_one_role_id, f.team_two_role_id);
+                (a.min(b), a.max(b))
+            })
+            .collect();
+        assert_eq!(fixtures.len() as i64, count * (count - 1) / 2);
+        assert_eq!(pairs.len(), fixtures.len());
+        let rounds = fixtures.iter().map(|f| f.round).max().unwrap();
+        assert_eq!(rounds as i64, count + count % 2 - 1);
+    }
+}
+
+#[test]
+fn teams_play_at_most_once_per_round() {
+    let fixtures = round_robin(&teams(7), true);
+    let mut booked = HashSet::new();
+    for f in &fixtures {
+        assert!(booked.insert((f.round, f.team_one_role_id)));
+        assert!(booked.insert((f.round, f.team_two_role_id)));
+    }
+}
+
+#[test]
+fn home_and_away_games_are_balanced() {
+    for count in 2..=12 {
+        let mut balance: HashMap<i64, i32> = HashMap::new();
+        for f in round_robin(&teams(count), false) {
+            *balance.entry(f.team_one_role_id).or_default() += 1;
+            *balance.entry(f.team_two_role_id).or_default() -= 1;
+        }
+        assert!(balance.values().all(|b| b.abs() <= 1), "{} teams", count);
+    }
+}
+
+#[test]
+fn double_round_robin_swaps_home_and_away() {
+    let single = round_robin(&teams(10), false);
+    let double = round_robin(&teams(10), true);
+    assert_eq!(double.len(), 90);
+    assert_eq!(double.iter().map(|f| f.round).max(), Some(18));
+    for (first, second) in single.iter().zip(&double[single.len()..]) {
+        assert_eq!(second.round, first.round + 9);
+        assert_eq!(second.team_one_role_id, first.team_two_role_id);
+        assert_eq!(second.team_two_role_id, first.team_one_role_id);
+    }
+}
+
+fn season_teams(divisions: &[(i64, Option<i32>)]) -> Vec<SeasonTeam> {
+    divisions
+        .iter()
+        .map(|&(role_id, division_id)| SeasonTeam {
+            season_id: 1,
+            role_id,
+            division_id,
+        })
+        .collect()
+}
+
+#[test]
+fn divisions_play_their_own_round_robin() {
+    let season = season_teams(&[
+        (1, Some(10)),
+        (2, Some(10)),
+        (3, Some(10)),
+        (4, Some(10)),
+        (5, Some(20)),
+        (6, Some(20)),
+        (7, None),
+        (8, None),
+    ]);
+    // team 9 is registered but does not play in the season
+    let fixtures = division_round_robin(&teams(9), &season, false);
+    assert_eq!(fixtures.len(), 6 + 1 + 1);
+    for f in &fixtures {
+        let division = |role_id| season.iter().find(|t| t.role_id == role_id).unwrap();
+        assert_eq!(division(f.team_one_role_id).division_id, f.division_id);
+        assert_eq!(division(f.team_two_role_id).division_id, f.division_id);
+    }
+    let rounds: Vec<i32> = fixtures.iter().map(|f| f.round).collect();
+    assert_eq!(rounds, vec![1, 1, 1, 1, 2, 2, 3, 3]);
+}
+
+#[test]
+fn imported_fixtures_are_put_in_their_teams_division() {
+    let csv = "round,team_one,team_two\n1,1,2\n1,3,4\n2,1,3\n";
+    let mut fixtures = parse_fixtures(csv.as_bytes(), FixtureFormat::Csv, &teams(4)).unwrap();
+    let season = season_teams(&[(1, Some(10)), (2, Some(10)), (3, Some(20)), (4, None)]);
+    assign_divisions(&mut fixtures, &season);
+    let divisions: Vec<Option<i32>> = fixtures.iter().map(|f| f.division_id).collect();
+    assert_eq!(divisions, vec![Some(10), None, None]);
+    assert_eq!(SeasonTeam::shared_division(&season, 2, 1), Some(10));
+    assert_eq!(SeasonTeam::shared_division(&season, 1, 5), None);
+}
+
+#[test]
+fn deadlines_are_the_end_of_each_round() {
+    let start = NaiveDate::from_ymd_opt(2022, 9, 5).unwrap();
+    assert_eq!(
+        round_deadline(start, 7, 1),
+        NaiveDate::from_ymd_opt(2022, 9, 11)
+            .and_then(|d| d.and_hms_opt(23, 59, 59))
+            .unwrap()
+    );
+    let mut fixtures = round_robin(&teams(4), false);
+    fill_deadlines(&mut fixtures, start, 7);
+    let last = fixtures.last().unwrap();
+    assert_eq!(last.round, 3);
+    assert_eq!(last.deadline, Some(round_deadline(start, 7, 3)));
+}
+
+#[test]
+fn csv_fixtures_are_imported() {
+    let csv = "round,team_one,team_two,series_type,deadline,note\n\
+               1,Team 1,2,bo3,2022-09-11 20:00,\n\
+               1, team 3 ,Team 4,,,Opening match\n";
+    let fixtures = parse_fixtures(csv.as_bytes(), FixtureFormat::Csv, &teams(4)).unwrap();
+    assert_eq!(fixtures.len(), 2);
+    assert_eq!(fixtures[0].team_two_name, "Team 2");
+    assert_eq!(fixtures[0].series_type, Some(SeriesType::Bo3));
+    assert_eq!(
+        fixtures[0].deadline,
+        NaiveDate::from_ymd_opt(2022, 9, 11).and_then(|d| d.and_hms_opt(20, 0, 0))
+    );
+    assert_eq!(fixtures[1].team_one_role_id, 3);
+    assert_eq!(fixtures[1].series_type, None);
+    assert_eq!(fixtures[1].deadline, None);
+    assert_eq!(fixtures[1].note.as_deref(), Some("Opening match"));
+}
+
+#[test]
+fn json_fixtures_are_imported() {
+    let json = r#"[
+        {"round": 1, "team_one": "1", "team_two": "Team 2", "deadline": "2022-09-11"},
+        {"round": 2, "team_one": "Team 2", "team_two": "Team 1"}
+    ]"#;
+    let fixtures = parse_fixtures(json.as_bytes(), FixtureFormat::Json, &teams(2)).unwrap();
+    assert_eq!(fixtures[1].round, 2);
+    assert_eq!(fixtures[1].team_one_role_id, 2);
+    assert_eq!(
+        fixtures[0].deadline,
+        NaiveDate::from_ymd_opt(2022, 9, 11).and_then(|d| d.and_hms_opt(23, 59, 59))
+    );
+}
+
+#[test]
+fn invalid_fixtures_are_rejected() {
+    let parse = |csv: &str| {
+        parse_fixtures(
+            format!("round,team_one,team_two\n{}", csv).as_bytes(),
+            FixtureFormat::Csv,
+            &teams(4),
+        )
+    };
+    assert_eq!(
+        parse("1,Team 1,Team 5"),
+        Err(FixtureError::UnknownTeam {
+            row: 1,
+            team: String::from("Team 5")
+        })
+    );
+    assert_eq!(
+        parse("1,Team 1,Team 2\n1,Team 1,Team 1"),
+        Err(FixtureError::SameTeams { row: 2 })
+    );
+    assert_eq!(
+        parse("1,Team 1,Team 2\n1,Team 3,Team 2"),
+        Err(FixtureError::DoubleBooked {
+            round: 1,
+            team: String::from("Team 2")
+        })
+    );
+    assert_eq!(
+        parse("0,Team 1,Team 2"),
+        Err(FixtureError::InvalidRound { row: 1 })
+    );
+    assert_eq!(parse(""), Err(FixtureError::Empty));
+}
+
+#[test]
+fn fixture_format_follows_the_file_name() {
+    assert_eq!(
+        FixtureFormat::from_file_name("season.CSV"),
+        Some(FixtureFormat::Csv)
+    );
+    assert_eq!(
+        FixtureFormat::from_file_name("season.json"),
+        Some(FixtureFormat::Json)
+    );
+    assert_eq!(FixtureFormat::from_file_name("season.txt"), None);
+}
@@ -0,0 +1,83 @@
+use chrono::NaiveDate;
+use csgo_matchbot::models::{Match, MatchState, ResultError, SeriesType};
+use csgo_matchbot::standings::standings;
+
+fn test_match(team_one: i64, team_two: i64) -> Match {
+    Match {
+        match_state: MatchState::Completed,
+        ..Match::new(
+            1,
+            (team_one, &format!("Team {}", team_one)),
+            (team_two, &format!("Team {}", team_two)),
+            SeriesType::Bo3,
+            NaiveDate::from_ymd_opt(2022, 9, 1)
+                .and_then(|d| d.and_hms_opt(18, 0, 0))
+                .unwrap(),
+        )
+    }
+}
+
+fn result(team_one: i64, team_two: i64, winner: i64, score: Option<(i32, i32)>) -> Match {
+    Match {
+        winner_role_id: Some(winner),
+        team_one_score: score.map(|(one, _)| one),
+        team_two_score: score.map(|(_, two)| two),
+        ..test_match(team_one, team_two)
+    }
+}
+
+#[test]
+fn results_are_checked_against_the_match() {
+    let m = test_match(1, 2);
+    assert_eq!(m.check_result(2, Some((0, 2))), Ok(()));
+    assert_eq!(m.check_result(1, None), Ok(()));
+    assert_eq!(m.check_result(3, None), Err(ResultError::NotInMatch));
+    assert_eq!(
+        m.check_result(1, Some((1, 2))),
+        Err(ResultError::ScoreMismatch)
+    );
+    assert_eq!(
+        result(1, 2, 1, None).check_result(2, None),
+        Err(ResultError::AlreadyReported)
+    );
+    let archived = Match {
+        match_state: MatchState::Archived,
+        ..test_match(1, 2)
+    };
+    assert_eq!(archived.check_result(1, None), Err(ResultError::Archived));
+}
+
+#[test]
+fn loser_is_the_other_team() {
+    assert_eq!(test_match(1, 2).loser_role_id(), None);
+    assert_eq!(result(1, 2, 2, None).loser_role_id(), Some(1));
+}
+
+#[test]
+fn standings_rank_wins_then_map_difference() {
+    let table = standings(&[
+        result(1, 2, 1, Some((2, 1))),
+        result(3, 4, 3, Some((2, 0))),
+        result(1, 3, 3, Some((1, 2))),
+        result(2, 4, 2, Some((2, 0))),
+        test_match(1, 4),
+    ]);
+    let order: Vec<i64> = table.iter().map(|s| s.role_id).collect();
+    assert_eq!(order, vec![3, 2, 1, 4]);
+    assert_eq!(table[0].played, 2);
+    assert_eq!(table[0].map_difference(), 3);
+    assert_eq!(table[3].losses, 2);
+}
+
+#[test]
+fn results_without_a_score_count_as_one_map() {
+    let table = standings(&[result(1, 2, 2, None)]);
+    assert_eq!(
+        (table[0].role_id, table[0].maps_won, table[0].maps_lost),
+        (2, 1, 0)
+    );
+    assert_eq!(
+        (table[1].role_id, table[1].maps_won, table[1].maps_lost),
+        (1, 0, 1)
+    );
+}
@@ -0,0 +1,149 @@
+use chrono::Utc;
+use csgo_matchbot::models::{
+    MatchState, NewMatch, NewSeason, NewSeriesMap, PlayerMapStats, PlayerStatsTotals, SeriesType,
+};
+use csgo_matchbot::{
+    activate_season, create_match, create_season, create_series_maps, get_next_team_match,
+    get_player_stats, get_series_maps, get_team_player_stats, run_migrations, update_match_state,
+    update_player_map_stats,
+};
+use diesel::{Connection, PgConnection};
+use std::env;
+
+/// Connection to the database in `TEST_DATABASE_URL` in a transaction that is never committed,
+/// none when no test database is set up
+fn test_conn() -> Option<PgConnection> {
+    let url = env::var("TEST_DATABASE_URL").ok()?;
+    let conn = PgConnection::establish(&url).expect("Cannot connect to the test database");
+    conn.begin_test_transaction().unwrap();
+    run_migrations(&conn, &mut Vec::new()).unwrap();
+    Some(conn)
+}
+
+#[test]
+fn matches_from_before_seasons_can_still_be_set_up() {
+    let conn = match test_conn() {
+        Some(conn) => conn,
+        None => return,
+    };
+    let date_added = Utc::now().naive_utc();
+    let new_match = |season_id| NewMatch {
+        team_one_role_id: 9001,
+        team_one_name: "Team A",
+        team_two_role_id: 9002,
+        team_two_name: "Team B",
+        note: None,
+        series_type: &SeriesType::Bo3,
+        date_added: &date_added,
+        match_state: &MatchState::Entered,
+        round: None,
+        deadline: None,
+        season_id,
+        division_id: None,
+    };
+    let old_season = create_season(
+        &conn,
+        NewSeason {
+            name: "Test season 1",
+            created_at: date_added,
+        },
+    )
+    .unwrap();
+    let season = create_season(
+        &conn,
+        NewSeason {
+            name: "Test season 2",
+            created_at: date_added,
+        },
+    )
+    .unwrap();
+    activate_season(&conn, season.id);
+    create_match(&conn, new_match(Some(old_season.id)));
+    create_match(&conn, new_match(None));
+
+    // the older match of the previous season is skipped, the match without a season is not
+    let next = get_next_team_match(&conn, 9001, Some(season.id)).unwrap();
+    assert_eq!(next.season_id, None);
+    let next = get_next_team_match(&conn, 9002, Some(old_season.id)).unwrap();
+    assert_eq!(next.season_id, Some(old_season.id));
+}
+
+#[test]
+fn stats_are_totalled_per_season() {
+    let conn = match test_conn() {
+        Some(conn) => conn,
+        None => return,
+    };
+    let date_added = Utc::now().naive_utc();
+    let mut seasons = Vec::new();
+    for (name, kills) in [("Stats season 1", 10), ("Stats season 2", 20)] {
+        let season = create_season(
+            &conn,
+            NewSeason {
+                name,
+                created_at: date_added,
+            },
+        )
+        .unwrap();
+        create_match(
+            &conn,
+            NewMatch {
+                team_one_role_id: 9011,
+                team_one_name: "Team A",
+                team_two_role_id: 9012,
+                team_two_name: "Team B",
+                note: None,
+                series_type: &SeriesType::Bo1,
+                date_added: &date_added,
+                match_state: &MatchState::Entered,
+                round: None,
+                deadline: None,
+                season_id: Some(season.id),
+                division_id: None,
+            },
+        );
+        let m = get_next_team_match(&conn, 9011, Some(season.id)).unwrap();
+        create_series_maps(
+            &conn,
+            vec![NewSeriesMap {
+                match_id: m.id,
+                map: String::from("de_nuke"),
+                picked_by_role_id: 9011,
+                start_attack_team_role_id: None,
+                start_defense_team_role_id: None,
+            }],
+        );
+        update_player_map_stats(
+            &conn,
+            vec![PlayerMapStats {
+                steam_id: String::from("STEAM_1:1:9011"),
+                series_map_id: get_series_maps(&conn, m.id)[0].id,
+                team_role_id: 9011,
+                kills,
+                deaths: 5,
+                assists: 0,
+                headshot_kills: 0,
+                damage: 1000,
+                rounds_played: 20,
+            }],
+        );
+        // the team's next match is the one of the next season
+        update_match_state(&conn, m.id, MatchState::Completed);
+        seasons.push(season.id);
+    }
+
+    let kills = |season| {
+        get_player_stats(&conn, "STEAM_1:1:9011", season)
+            .iter()
+            .collect::<PlayerStatsTotals>()
+            .kills
+    };
+    assert_eq!(kills(Some(seasons[0])), 10);
+    assert_eq!(kills(Some(seasons[1])), 20);
+    assert_eq!(kills(None), 30);
+    assert_eq!(
+        get_team_player_stats(&conn, 9011, Some(seasons[1])).len(),
+        1
+    );
+    assert_eq!(get_team_player_stats(&conn, 9011, None).len(), 2);
+}
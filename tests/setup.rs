@@ -0,0 +1,450 @@
+use csgo_matchbot::models::SeriesType;
+use csgo_matchbot::models::StepType::{Pick, Veto};
+use csgo_matchbot::veto::{rank_servers, Action, Event, MapId, Setup, SetupError, Side, State};
+use proptest::prelude::*;
+
+const TEAM_ONE: i64 = 1;
+const TEAM_TWO: i64 = 2;
+const OUTSIDER: i64 = 3;
+
+const MAPS: [&str; 7] = [
+    "de_ancient",
+    "de_dust2",
+    "de_inferno",
+    "de_mirage",
+    "de_Nuke",
+    "de_overpass",
+    "de_vertigo",
+];
+
+const SERVERS: [&str; 4] = ["eu-west", "eu-north", "na-east", "na-west"];
+
+fn servers() -> Vec<String> {
+    SERVERS.iter().map(|s| String::from(*s)).collect()
+}
+
+fn rank(setup: &mut Setup, team_role_id: i64, server_id: &str) -> Result<Event, SetupError> {
+    setup.apply(Action::RankServer {
+        team_role_id,
+        server_id: String::from(server_id),
+    })
+}
+
+fn setup(series_type: SeriesType) -> Setup {
+    Setup::new(
+        1,
+        series_type,
+        (TEAM_ONE, String::from("Home")),
+        (TEAM_TWO, String::from("Away")),
+        MAPS,
+        servers(),
+    )
+    .unwrap()
+}
+
+fn snapshot(setup: &Setup) -> String {
+    serde_json::to_string(setup).unwrap()
+}
+
+/// Both teams rank the servers in the same order
+fn pick_server(setup: &mut Setup) {
+    for team_role_id in [TEAM_TWO, TEAM_ONE] {
+        for server_id in &SERVERS[..setup.servers_to_rank()] {
+            rank(setup, team_role_id, server_id).unwrap();
+        }
+    }
+}
+
+/// Plays the next veto step using the first available map
+fn veto_next(setup: &mut Setup) -> Event {
+    let step = setup.veto_step().unwrap().clone();
+    let map = MapId::new(&setup.map_pool.available().next().unwrap().name);
+    let team_role_id = step.team_role_id;
+    let action = match step.step_type {
+        Veto => Action::Ban { team_role_id, map },
+        Pick => Action::Pick { team_role_id, map },
+    };
+    setup.apply(action).unwrap()
+}
+
+fn run_to_completion(setup: &mut Setup) {
+    pick_server(setup);
+    while setup.current_phase == State::MapVeto {
+        veto_next(setup);
+    }
+    while let Some(team_role_id) = setup.turn_role_id() {
+        setup
+            .apply(Action::PickSide {
+                team_role_id,
+                side: Side::CT,
+            })
+            .unwrap();
+    }
+}
+
+#[test]
+fn not_enough_maps_is_rejected() {
+    let err = Setup::new(
+        1,
+        SeriesType::Bo3,
+        (TEAM_ONE, String::from("Home")),
+        (TEAM_TWO, String::from("Away")),
+        vec![
+            "de_nuke",
+            "de_Nuke",
+            "de_inferno",
+            "de_mirage",
+            "de_ancient",
+        ],
+        servers(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        SetupError::NotEnoughMaps {
+            needed: 6,
+            available: 4
+        }
+    );
+}
+
+#[test]
+fn no_servers_is_rejected() {
+    let err = Setup::new(
+        1,
+        SeriesType::Bo1,
+        (TEAM_ONE, String::from("Home")),
+        (TEAM_TWO, String::from("Away")),
+        MAPS,
+        vec![],
+    )
+    .unwrap_err();
+    assert_eq!(err, SetupError::NoServers);
+}
+
+#[test]
+fn away_team_ranks_servers_first() {
+    let mut setup = setup(SeriesType::Bo1);
+    assert_eq!(setup.turn_role_id(), Some(TEAM_TWO));
+    let err = rank(&mut setup, TEAM_ONE, "eu-west").unwrap_err();
+    assert_eq!(err, SetupError::NotYourTurn(TEAM_ONE));
+    for (i, server_id) in SERVERS[..3].iter().enumerate() {
+        assert_eq!(
+            rank(&mut setup, TEAM_TWO, server_id),
+            Ok(Event::ServerRanked {
+                team_role_id: TEAM_TWO,
+                server_id: String::from(*server_id),
+                rank: i + 1,
+            })
+        );
+    }
+    assert_eq!(setup.turn_role_id(), Some(TEAM_ONE));
+    assert_eq!(setup.current_phase, State::ServerPick);
+}
+
+#[test]
+fn unknown_or_repeated_servers_are_rejected() {
+    let mut setup = setup(SeriesType::Bo1);
+    let err = rank(&mut setup, TEAM_TWO, "moon").unwrap_err();
+    assert_eq!(err, SetupError::UnknownServer(String::from("moon")));
+    rank(&mut setup, TEAM_TWO, "eu-west").unwrap();
+    let err = rank(&mut setup, TEAM_TWO, "eu-west").unwrap_err();
+    assert_eq!(
+        err,
+        SetupError::ServerAlreadyRanked(String::from("eu-west"))
+    );
+}
+
+#[test]
+fn best_mutual_server_is_picked() {
+    let mut setup = setup(SeriesType::Bo1);
+    for server_id in ["na-east", "eu-north", "eu-west"] {
+        rank(&mut setup, TEAM_TWO, server_id).unwrap();
+    }
+    for server_id in ["eu-west", "eu-north"] {
+        rank(&mut setup, TEAM_ONE, server_id).unwrap();
+    }
+    assert_eq!(
+        rank(&mut setup, TEAM_ONE, "na-west"),
+        Ok(Event::ServerPicked {
+            server_id: String::from("eu-north")
+        })
+    );
+    assert_eq!(setup.current_phase, State::MapVeto);
+    assert_eq!(setup.server_id.as_deref(), Some("eu-north"));
+    assert_eq!(
+        setup.server_ranking,
+        ["eu-north", "eu-west", "na-east", "na-west"]
+    );
+}
+
+#[test]
+fn teams_rank_every_server_when_there_are_few() {
+    let mut setup = Setup::new(
+        1,
+        SeriesType::Bo1,
+        (TEAM_ONE, String::from("Home")),
+        (TEAM_TWO, String::from("Away")),
+        MAPS,
+        vec![String::from("eu-west")],
+    )
+    .unwrap();
+    assert_eq!(setup.servers_to_rank(), 1);
+    rank(&mut setup, TEAM_TWO, "eu-west").unwrap();
+    rank(&mut setup, TEAM_ONE, "eu-west").unwrap();
+    assert_eq!(setup.server_id.as_deref(), Some("eu-west"));
+}
+
+#[test]
+fn server_ties_go_to_the_away_team() {
+    let servers = servers();
+    let ranking = rank_servers(
+        &servers,
+        &[String::from("eu-west"), String::from("na-east")],
+        &[String::from("na-east"), String::from("eu-west")],
+    );
+    assert_eq!(ranking, ["na-east", "eu-west", "eu-north", "na-west"]);
+}
+
+#[test]
+fn outsider_cannot_act() {
+    let mut setup = setup(SeriesType::Bo1);
+    let err = rank(&mut setup, OUTSIDER, "eu-west").unwrap_err();
+    assert_eq!(err, SetupError::NotInMatch(OUTSIDER));
+}
+
+#[test]
+fn veto_follows_pick_order() {
+    let mut setup = setup(SeriesType::Bo3);
+    pick_server(&mut setup);
+    let err = setup
+        .apply(Action::Ban {
+            team_role_id: TEAM_TWO,
+            map: MapId::new("de_nuke"),
+        })
+        .unwrap_err();
+    assert_eq!(err, SetupError::NotYourTurn(TEAM_TWO));
+    let err = setup
+        .apply(Action::Pick {
+            team_role_id: TEAM_ONE,
+            map: MapId::new("de_nuke"),
+        })
+        .unwrap_err();
+    assert_eq!(err, SetupError::WrongAction(State::MapVeto));
+    let event = setup
+        .apply(Action::Ban {
+            team_role_id: TEAM_ONE,
+            map: MapId::new("DE_NUKE"),
+        })
+        .unwrap();
+    assert_eq!(
+        event,
+        Event::MapBanned {
+            team_role_id: TEAM_ONE,
+            map: String::from("de_Nuke")
+        }
+    );
+    assert_eq!(setup.turn_role_id(), Some(TEAM_TWO));
+}
+
+#[test]
+fn banned_map_cannot_be_picked() {
+    let mut setup = setup(SeriesType::Bo3);
+    pick_server(&mut setup);
+    for team_role_id in [TEAM_ONE, TEAM_TWO] {
+        setup
+            .apply(Action::Ban {
+                team_role_id,
+                map: MapId::new(if team_role_id == TEAM_ONE {
+                    "de_nuke"
+                } else {
+                    "de_dust2"
+                }),
+            })
+            .unwrap();
+    }
+    let err = setup
+        .apply(Action::Pick {
+            team_role_id: TEAM_ONE,
+            map: MapId::new("de_nuke"),
+        })
+        .unwrap_err();
+    assert!(matches!(err, SetupError::Map(_)));
+}
+
+#[test]
+fn last_available_map_is_the_decider() {
+    let mut setup = Setup::new(
+        1,
+        SeriesType::Bo1,
+        (TEAM_ONE, String::from("Home")),
+        (TEAM_TWO, String::from("Away")),
+        MAPS[..6].to_vec(),
+        servers(),
+    )
+    .unwrap();
+    pick_server(&mut setup);
+    let mut last = None;
+    while setup.current_phase == State::MapVeto {
+        last = Some(veto_next(&mut setup));
+    }
+    assert!(matches!(last, Some(Event::MapPicked { decider: true, .. })));
+    assert_eq!(setup.maps.len(), 1);
+    assert_eq!(setup.current_phase, State::SidePick);
+}
+
+#[test]
+fn team_that_did_not_pick_chooses_side() {
+    let mut setup = setup(SeriesType::Bo3);
+    pick_server(&mut setup);
+    while setup.current_phase == State::MapVeto {
+        veto_next(&mut setup);
+    }
+    let picked_by = setup.side_pick_map().unwrap().picked_by;
+    assert_eq!(setup.turn_role_id(), Some(setup.other_team(picked_by)));
+    let err = setup
+        .apply(Action::PickSide {
+            team_role_id: picked_by,
+            side: Side::T,
+        })
+        .unwrap_err();
+    assert_eq!(err, SetupError::NotYourTurn(picked_by));
+    let chooser = setup.other_team(picked_by);
+    setup
+        .apply(Action::PickSide {
+            team_role_id: chooser,
+            side: Side::T,
+        })
+        .unwrap();
+    assert_eq!(setup.maps[0].start_attack_team_role_id, Some(chooser));
+    assert_eq!(setup.maps[0].start_defense_team_role_id, Some(picked_by));
+}
+
+#[test]
+fn series_complete_with_expected_map_count() {
+    for (series_type, maps) in [
+        (SeriesType::Bo1, 1),
+        (SeriesType::Bo3, 3),
+        (SeriesType::Bo5, 5),
+    ] {
+        let mut setup = setup(series_type);
+        run_to_completion(&mut setup);
+        assert!(setup.is_completed());
+        assert_eq!(setup.maps.len(), maps);
+        assert_eq!(setup.turn_role_id(), None);
+    }
+}
+
+#[test]
+fn undo_steps_back_through_every_phase() {
+    let mut setup = setup(SeriesType::Bo3);
+    assert_eq!(setup.apply(Action::Undo), Err(SetupError::NothingToUndo));
+    run_to_completion(&mut setup);
+    while setup.apply(Action::Undo).is_ok() {}
+    assert_eq!(setup.current_phase, State::ServerPick);
+    assert_eq!(setup.server_id, None);
+    assert!(setup.server_ranking.is_empty());
+    assert!(setup.team_one_servers.is_empty());
+    assert!(setup.team_two_servers.is_empty());
+    assert!(setup.maps.is_empty());
+    assert_eq!(setup.map_pool.available_count(), MAPS.len());
+    assert!(setup.veto_pick_order.iter().all(|s| s.map.is_none()));
+}
+
+#[test]
+fn completed_setup_rejects_further_actions() {
+    let mut setup = setup(SeriesType::Bo1);
+    run_to_completion(&mut setup);
+    let err = setup
+        .apply(Action::PickSide {
+            team_role_id: TEAM_ONE,
+            side: Side::CT,
+        })
+        .unwrap_err();
+    assert_eq!(err, SetupError::WrongAction(State::Completed));
+}
+
+fn series_type() -> impl Strategy<Value = SeriesType> {
+    prop_oneof![
+        Just(SeriesType::Bo1),
+        Just(SeriesType::Bo3),
+        Just(SeriesType::Bo5)
+    ]
+}
+
+fn action() -> impl Strategy<Value = Action> {
+    let team = prop_oneof![Just(TEAM_ONE), Just(TEAM_TWO), Just(OUTSIDER)];
+    let map = prop::sample::select(MAPS.to_vec()).prop_map(MapId::new);
+    let server = prop::sample::select(SERVERS.to_vec()).prop_map(String::from);
+    let side = prop_oneof![Just(Side::CT), Just(Side::T)];
+    prop_oneof![
+        (team.clone(), server).prop_map(|(team_role_id, server_id)| Action::RankServer {
+            team_role_id,
+            server_id,
+        }),
+        (team.clone(), map.clone())
+            .prop_map(|(team_role_id, map)| Action::Ban { team_role_id, map }),
+        (team.clone(), map).prop_map(|(team_role_id, map)| Action::Pick { team_role_id, map }),
+        (team, side).prop_map(|(team_role_id, side)| Action::PickSide { team_role_id, side }),
+        Just(Action::Undo),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn rejected_actions_leave_setup_untouched(
+        series_type in series_type(),
+        actions in prop::collection::vec(action(), 0..64),
+    ) {
+        let mut setup = setup(series_type);
+        for action in actions {
+            let before = snapshot(&setup);
+            if setup.apply(action).is_err() {
+                prop_assert_eq!(before, snapshot(&setup));
+            }
+        }
+    }
+
+    #[test]
+    fn undo_reverts_the_last_action(
+        series_type in series_type(),
+        actions in prop::collection::vec(action(), 0..64),
+    ) {
+        let mut setup = setup(series_type);
+        for action in actions {
+            let undoable = action != Action::Undo;
+            let before = snapshot(&setup);
+            if setup.apply(action).is_ok() && undoable {
+                let after = setup.clone();
+                setup.apply(Action::Undo).unwrap();
+                prop_assert_eq!(&before, &snapshot(&setup));
+                setup = after;
+            }
+        }
+    }
+
+    #[test]
+    fn map_pool_stays_consistent(
+        series_type in series_type(),
+        actions in prop::collection::vec(action(), 0..64),
+    ) {
+        let mut setup = setup(series_type);
+        for action in actions {
+            let _ = setup.apply(action);
+            let vetoed = setup.veto_pick_order.iter().filter(|s| s.map.is_some()).count();
+            prop_assert_eq!(setup.map_pool.available_count() + vetoed, MAPS.len());
+            let picked = setup
+                .veto_pick_order
+                .iter()
+                .filter(|s| s.step_type == Pick && s.map.is_some())
+                .count();
+            prop_assert_eq!(setup.maps.len(), picked);
+            if setup.is_completed() {
+                let sides_picked = setup.maps.iter().all(|m| {
+                    m.start_attack_team_role_id.is_some() && m.start_defense_team_role_id.is_some()
+                });
+                prop_assert!(sides_picked);
+            }
+        }
+    }
+}
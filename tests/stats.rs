@@ -0,0 +1,51 @@
+use csgo_matchbot::models::{PlayerMapStats, PlayerStatsTotals};
+
+fn map_stats(
+    kills: i32,
+    deaths: i32,
+    headshot_kills: i32,
+    damage: i32,
+    rounds_played: i32,
+) -> PlayerMapStats {
+    PlayerMapStats {
+        steam_id: String::from("STEAM_0:1:12345678"),
+        series_map_id: 1,
+        team_role_id: 1,
+        kills,
+        deaths,
+        assists: 2,
+        headshot_kills,
+        damage,
+        rounds_played,
+    }
+}
+
+#[test]
+fn totals_sum_every_map() {
+    let stats = [
+        map_stats(20, 10, 10, 2000, 25),
+        map_stats(10, 20, 0, 1000, 15),
+    ];
+    let totals: PlayerStatsTotals = stats.iter().collect();
+    assert_eq!(totals.maps, 2);
+    assert_eq!(totals.kills, 30);
+    assert_eq!(totals.deaths, 30);
+    assert_eq!(totals.assists, 4);
+    assert_eq!(totals.kd(), 1.0);
+    assert_eq!(totals.adr(), 75.0);
+    assert!((totals.hs_percent() - 100.0 / 3.0).abs() < 1e-9);
+}
+
+#[test]
+fn empty_totals_do_not_divide_by_zero() {
+    let totals = PlayerStatsTotals::default();
+    assert_eq!(totals.kd(), 0.0);
+    assert_eq!(totals.adr(), 0.0);
+    assert_eq!(totals.hs_percent(), 0.0);
+}
+
+#[test]
+fn deathless_kd_counts_kills() {
+    let totals: PlayerStatsTotals = [map_stats(12, 0, 6, 1500, 16)].iter().collect();
+    assert_eq!(totals.kd(), 12.0);
+}
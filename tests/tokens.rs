@@ -0,0 +1,71 @@
+use chrono::Utc;
+use csgo_matchbot::crypto::Keyring;
+use csgo_matchbot::models::{Match, MatchState, NewMatch, SeriesType};
+use csgo_matchbot::{
+    add_token, archive_match, count_free_tokens, create_match, get_fresh_token,
+    get_next_team_match, report_match_result, run_migrations, update_token,
+};
+use diesel::{Connection, PgConnection};
+use std::env;
+
+const KEY: &str = "test:AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=";
+
+/// Connection to the database in `TEST_DATABASE_URL` in a transaction that is never committed,
+/// none when no test database is set up
+fn test_conn() -> Option<PgConnection> {
+    let url = env::var("TEST_DATABASE_URL").ok()?;
+    let conn = PgConnection::establish(&url).expect("Cannot connect to the test database");
+    conn.begin_test_transaction().unwrap();
+    run_migrations(&conn, &mut Vec::new()).unwrap();
+    Some(conn)
+}
+
+/// Adds a match between the teams & assigns a fresh token to it, like starting its server does
+fn started_match(conn: &PgConnection, keyring: &Keyring, teams: (i64, i64), token: &str) -> Match {
+    let date_added = Utc::now().naive_utc();
+    create_match(
+        conn,
+        NewMatch {
+            team_one_role_id: teams.0,
+            team_one_name: "Team A",
+            team_two_role_id: teams.1,
+            team_two_name: "Team B",
+            note: None,
+            series_type: &SeriesType::Bo1,
+            date_added: &date_added,
+            match_state: &MatchState::Entered,
+            round: None,
+            deadline: None,
+            season_id: None,
+            division_id: None,
+        },
+    );
+    let m = get_next_team_match(conn, teams.0, None).unwrap();
+    assert!(add_token(conn, keyring, token));
+    let mut gslt = get_fresh_token(conn, keyring).unwrap();
+    gslt.in_use = true;
+    gslt.match_id = Some(m.id);
+    gslt.assigned_at = Some(date_added);
+    update_token(conn, gslt);
+    m
+}
+
+#[test]
+fn finished_matches_return_their_tokens() {
+    let conn = match test_conn() {
+        Some(conn) => conn,
+        None => return,
+    };
+    let keyring = Keyring::parse(KEY).unwrap();
+    let free = count_free_tokens(&conn);
+
+    let reported = started_match(&conn, &keyring, (9101, 9102), "TOKEN-REPORTED");
+    assert_eq!(count_free_tokens(&conn), free);
+    report_match_result(&conn, &reported, 9101, Some((1, 0))).unwrap();
+    assert_eq!(count_free_tokens(&conn), free + 1);
+
+    let archived = started_match(&conn, &keyring, (9103, 9104), "TOKEN-ARCHIVED");
+    assert_eq!(count_free_tokens(&conn), free + 1);
+    archive_match(&conn, archived.id, 1, Some("cancelled")).unwrap();
+    assert_eq!(count_free_tokens(&conn), free + 2);
+}
@@ -0,0 +1,106 @@
+use csgo_matchbot::veto::{MapId, MapPool, MapState, VetoError};
+
+const TEAM_ONE: i64 = 1;
+const TEAM_TWO: i64 = 2;
+
+fn pool() -> MapPool {
+    MapPool::new(vec!["de_Nuke", "de_inferno", "DE_MIRAGE"])
+}
+
+#[test]
+fn map_id_is_case_insensitive() {
+    assert_eq!(MapId::new("de_Nuke"), MapId::new("de_nuke"));
+    assert_eq!(MapId::new(" DE_NUKE "), MapId::from("de_nuke"));
+    assert_eq!(MapId::new("de_Nuke").as_str(), "de_nuke");
+}
+
+#[test]
+fn new_pool_has_every_map_available() {
+    let pool = pool();
+    assert_eq!(pool.available_count(), 3);
+    assert!(pool.maps().iter().all(|m| m.state == MapState::Available));
+}
+
+#[test]
+fn new_pool_ignores_duplicate_maps() {
+    let pool = MapPool::new(vec!["de_nuke", "de_Nuke", "de_inferno"]);
+    assert_eq!(pool.maps().len(), 2);
+    assert_eq!(pool.maps()[0].name, "de_nuke");
+}
+
+#[test]
+fn mixed_case_maps_can_be_vetoed_by_lowercase_id() {
+    let mut pool = pool();
+    let map = pool.ban(&MapId::new("de_mirage"), TEAM_ONE).unwrap();
+    assert_eq!(map.name, "DE_MIRAGE");
+    assert_eq!(
+        pool.state(&MapId::new("de_mirage")),
+        Some(MapState::Banned { by: TEAM_ONE })
+    );
+}
+
+#[test]
+fn vetoed_map_cannot_be_vetoed_again() {
+    let mut pool = pool();
+    let nuke = MapId::new("de_nuke");
+    pool.ban(&nuke, TEAM_ONE).unwrap();
+    assert_eq!(
+        pool.pick(&nuke, TEAM_TWO),
+        Err(VetoError::MapUnavailable(
+            nuke.clone(),
+            MapState::Banned { by: TEAM_ONE }
+        ))
+    );
+    assert!(pool.available_map(&nuke).is_err());
+    assert_eq!(pool.available_count(), 2);
+}
+
+#[test]
+fn unknown_map_is_rejected() {
+    let mut pool = pool();
+    let dust = MapId::new("de_dust2");
+    assert_eq!(
+        pool.ban(&dust, TEAM_ONE),
+        Err(VetoError::UnknownMap(dust.clone()))
+    );
+    assert_eq!(pool.restore(&dust), Err(VetoError::UnknownMap(dust)));
+}
+
+#[test]
+fn picking_last_available_map_makes_it_the_decider() {
+    let mut pool = pool();
+    pool.ban(&MapId::new("de_nuke"), TEAM_ONE).unwrap();
+    pool.pick(&MapId::new("de_inferno"), TEAM_TWO).unwrap();
+    pool.pick(&MapId::new("de_mirage"), TEAM_ONE).unwrap();
+    assert_eq!(
+        pool.state(&MapId::new("de_inferno")),
+        Some(MapState::Picked { by: TEAM_TWO })
+    );
+    assert_eq!(
+        pool.state(&MapId::new("de_mirage")),
+        Some(MapState::Decider)
+    );
+    assert_eq!(pool.available_count(), 0);
+}
+
+#[test]
+fn restore_makes_map_available_again() {
+    let mut pool = pool();
+    let inferno = MapId::new("de_inferno");
+    pool.pick(&inferno, TEAM_TWO).unwrap();
+    pool.restore(&inferno).unwrap();
+    assert_eq!(pool.state(&inferno), Some(MapState::Available));
+    assert_eq!(
+        pool.restore(&inferno),
+        Err(VetoError::MapNotVetoed(inferno))
+    );
+}
+
+#[test]
+fn available_maps_keep_pool_order() {
+    let mut pool = pool();
+    pool.ban(&MapId::new("de_inferno"), TEAM_ONE).unwrap();
+    pool.restore(&MapId::new("de_inferno")).unwrap();
+    let names: Vec<&str> = pool.available().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, vec!["de_Nuke", "de_inferno", "DE_MIRAGE"]);
+}